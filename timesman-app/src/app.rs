@@ -9,12 +9,14 @@ use crate::config::Config;
 use crate::log::LogRecord;
 use crate::pane::config::ConfigPane;
 use crate::pane::log::LogPane;
+use crate::pane::search::SearchPane;
 use crate::pane::select::SelectPane;
 use crate::pane::start::StartPane;
+use crate::pane::tag::TagPane;
 use crate::pane::times::TimesPane;
+use crate::pane::trash::TrashPane;
 use crate::pane::Pane;
 
-use eframe;
 use timesman_bstore::Store;
 use timesman_type::Times;
 
@@ -26,10 +28,18 @@ pub enum UIOperation {
 pub enum Event {
     Connect(Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>),
     Select(Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>, Times),
+    Search(Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>),
+    /// Shows the posts of a tag, at first only those of the given times.
+    Tag(
+        Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
+        Option<String>,
+        Option<Times>,
+    ),
+    Trash(Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>),
     Pop,
     Logs,
     Config,
-    UpdateConfig(Config),
+    UpdateConfig(Box<Config>),
     ChangeUI(UIOperation),
 }
 
@@ -42,6 +52,15 @@ impl fmt::Display for Event {
             Event::Select(_, _) => {
                 write!(f, "Disconnect")
             }
+            Event::Search(_) => {
+                write!(f, "Search")
+            }
+            Event::Tag(_, _, _) => {
+                write!(f, "Tag")
+            }
+            Event::Trash(_) => {
+                write!(f, "Trash")
+            }
             Event::Pop => {
                 write!(f, "Pop")
             }
@@ -79,6 +98,7 @@ impl App {
         stack.push_front(Box::new(StartPane::new(config.clone())));
 
         config.fonts.load_fonts(cc);
+        egui_extras::install_image_loaders(&cc.egui_ctx);
 
         let mut event_queue = VecDeque::new();
         config.append_init_events(&mut event_queue);
@@ -104,6 +124,18 @@ impl App {
             Event::Select(store, times) => self
                 .pane_stack
                 .push_front(Box::new(TimesPane::new(store, times, &self.rt))),
+            Event::Search(store) => {
+                self.pane_stack.push_front(Box::new(SearchPane::new(store)));
+            }
+            Event::Tag(store, tag, scope) => {
+                self.pane_stack.push_front(Box::new(TagPane::new(
+                    store, tag, scope, &self.rt,
+                )));
+            }
+            Event::Trash(store) => {
+                self.pane_stack
+                    .push_front(Box::new(TrashPane::new(store, &self.rt)));
+            }
             Event::Pop => {
                 self.pane_stack.pop_front();
                 let p: &mut Box<dyn Pane> = match self.pane_stack.front_mut() {
@@ -124,12 +156,12 @@ impl App {
                     .push_front(Box::new(ConfigPane::new(self.config.clone())));
             }
             Event::UpdateConfig(config) => {
-                self.config = config;
+                self.config = *config;
                 self.config
                     .store_config()
                     .map_err(|e| {
                         error!(format!("{}", e));
-                        format!("{e}")
+                        e.to_string()
                     })
                     .unwrap();
             }
@@ -162,20 +194,13 @@ impl eframe::App for App {
             }
         };
 
-        match pane.update(ctx, _frame, &self.rt) {
-            Some(event) => {
-                self.event_queue.push_back(event);
-            }
-            None => {}
+        if let Some(event) = pane.update(ctx, _frame, &self.rt) {
+            self.event_queue.push_back(event);
         };
 
-        loop {
-            if let Some(event) = self.event_queue.pop_front() {
-                debug!("Handle Event: {}", event);
-                self.handle_events(event, ctx);
-            } else {
-                break;
-            }
+        while let Some(event) = self.event_queue.pop_front() {
+            debug!("Handle Event: {}", event);
+            self.handle_events(event, ctx);
         }
     }
 }
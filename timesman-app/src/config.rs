@@ -3,8 +3,6 @@ use std::collections::VecDeque;
 use std::default::Default;
 use std::fs::File;
 use std::io::{BufWriter, Read, Write};
-use toml;
-use xdg;
 
 use crate::app::{Event, UIOperation};
 use crate::fonts::Fonts;
@@ -43,6 +41,9 @@ pub struct SqliteConfig {
 #[derive(Deserialize, Serialize, Clone)]
 pub struct RemoteConfig {
     pub server: String,
+    /// The API token to send, if the server requires one.
+    #[serde(default)]
+    pub token: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -77,6 +78,7 @@ impl Default for RemoteConfig {
     fn default() -> Self {
         Self {
             server: "http://localhost:8080".to_string(),
+            token: None,
         }
     }
 }
@@ -146,8 +148,7 @@ impl Config {
             .base
             .find_config_file(config_file_name)
             .ok_or("Can't found config file")?;
-        let file =
-            File::open(&path).map_err(|e| format!("{e}: {:?}", path))?;
+        let file = File::open(&path).map_err(|e| format!("{e}: {:?}", path))?;
 
         let mut bw = BufWriter::new(file);
         let param_str =
@@ -1,7 +1,6 @@
 use std::fs;
 use std::fs::File;
 use std::io::Read;
-use xdg;
 
 use egui::{FontData, FontDefinitions, FontFamily};
 
@@ -28,7 +27,7 @@ impl Fonts {
 
         let mut fonts = vec![];
 
-        for file in dir.read_dir().map_err(|e| format!("{e}"))?.into_iter() {
+        for file in dir.read_dir().map_err(|e| format!("{e}"))? {
             let path = file.map_err(|e| format!("{e}"))?.path();
 
             if !path.is_file() {
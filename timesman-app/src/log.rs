@@ -65,10 +65,7 @@ pub fn log(level: LogLevel, text: String) {
 }
 
 pub fn latest() -> Option<LogRecord> {
-    let logs = match LOGS.get() {
-        Some(l) => l,
-        None => return None,
-    };
+    let logs = LOGS.get()?;
 
     match logs.lock() {
         Ok(l) => l.last().cloned(),
@@ -1,12 +1,65 @@
 pub mod config;
 pub mod log;
+pub mod search;
 pub mod select;
 pub mod start;
+pub mod tag;
 pub mod times;
+pub mod trash;
+
+use std::sync::Arc;
 
 use crate::app::Event;
-use egui;
+use chrono::{DateTime, Local, Utc};
+use timesman_bstore::{Store, StoreError};
+use timesman_type::Change;
 use tokio::runtime;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::Mutex;
+
+/// Formats a time in the local timezone for display.
+pub fn local_time(t: &DateTime<Utc>) -> String {
+    t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// Forwards the changes made to `store` to a pane as `msg(Some(change))`
+/// until the pane is gone. `msg(None)` means the pane missed some changes
+/// and should reload, which also happens when the store stops reporting.
+pub fn subscribe_changes<M: Send + 'static>(
+    store: Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
+    rt: &runtime::Runtime,
+    tx: Sender<M>,
+    msg: fn(Option<Change>) -> M,
+) {
+    rt.spawn(async move {
+        let mut rx = match store.lock().await.subscribe().await {
+            Ok(rx) => rx,
+            Err(StoreError::Unsupported(_)) => return,
+            Err(e) => {
+                error!(format!("failed to subscribe changes: {e}"));
+                return;
+            }
+        };
+
+        loop {
+            let change = match rx.recv().await {
+                Ok(change) => Some(change),
+                Err(RecvError::Lagged(_)) => None,
+                Err(RecvError::Closed) => {
+                    info!("the store stopped reporting changes");
+                    let _ = tx.send(msg(None)).await;
+                    return;
+                }
+            };
+
+            if tx.send(msg(change)).await.is_err() {
+                // the pane is gone
+                return;
+            }
+        }
+    });
+}
 
 pub trait Pane {
     fn update(
@@ -41,7 +94,7 @@ pub trait Pane {
             e = Some(Event::Pop);
         }
 
-        if let Some(_) = &e {
+        if e.is_some() {
             ui.close_menu();
         }
 
@@ -29,7 +29,9 @@ impl Pane for ConfigPane {
             if self.edit_mode {
                 if ui.button("Done").clicked() {
                     self.edit_mode = false;
-                    event = Some(Event::UpdateConfig(self.config.clone()));
+                    event = Some(Event::UpdateConfig(Box::new(
+                        self.config.clone(),
+                    )));
                 }
             } else {
                 if ui.button("Edit").clicked() {
@@ -48,7 +50,7 @@ impl Pane for ConfigPane {
                 if self.edit_mode {
                     ui.text_edit_singleline(&mut self.config.params.store);
                 } else {
-                    ui.label(format!("{}", self.config.params.store));
+                    ui.label(self.config.params.store.to_string());
                 }
             });
 
@@ -58,7 +60,7 @@ impl Pane for ConfigPane {
                 if self.edit_mode {
                     ui.text_edit_singleline(&mut self.config.params.sqlite.db);
                 } else {
-                    ui.label(format!("{}", self.config.params.sqlite.db));
+                    ui.label(self.config.params.sqlite.db.to_string());
                 }
             });
 
@@ -70,7 +72,7 @@ impl Pane for ConfigPane {
                         &mut self.config.params.remote.server,
                     );
                 } else {
-                    ui.label(format!("{}", self.config.params.remote.server));
+                    ui.label(self.config.params.remote.server.to_string());
                 }
             });
 
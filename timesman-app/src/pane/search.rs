@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::app::Event;
+
+use eframe::egui::ScrollArea;
+use egui::{Key, Modifiers};
+use timesman_bstore::{SearchFilter, Store};
+use timesman_type::{Times, TimesPost};
+use tokio::sync::Mutex;
+
+use super::{local_time, Pane};
+use tokio::runtime;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::{Receiver, Sender};
+
+const SEARCH_LIMIT: usize = 100;
+
+pub struct SearchPane {
+    query: String,
+    results: Vec<TimesPost>,
+    times: HashMap<u64, Times>,
+    store: Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
+    tx: Sender<Message>,
+    rx: Receiver<Message>,
+}
+
+enum Message {
+    Result(HashMap<u64, Times>, Vec<TimesPost>),
+    Error(String),
+}
+
+impl Pane for SearchPane {
+    fn update(
+        &mut self,
+        ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+        rt: &runtime::Runtime,
+    ) -> Option<Event> {
+        let mut event = None;
+
+        self.handle_message();
+
+        egui::TopBottomPanel::top("top").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                event = self.times_menu(ui);
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("search");
+                ui.separator();
+                let resp = ui.text_edit_singleline(&mut self.query);
+
+                ui.separator();
+                if ui.button("go").clicked()
+                    || (resp.lost_focus()
+                        && ui.input(|i| i.key_pressed(Key::Enter)))
+                {
+                    self.search(rt);
+                }
+            });
+            if ui.input_mut(|i| i.consume_key(Modifiers::COMMAND, Key::Enter)) {
+                self.search(rt);
+            }
+        });
+
+        egui::TopBottomPanel::bottom("bottom").show(ctx, |ui| {
+            self.show_latest_log(ui);
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let scroll_area = ScrollArea::vertical()
+                .auto_shrink(false)
+                .max_height(ui.available_height());
+            scroll_area.show(ui, |ui| {
+                for r in &self.results {
+                    ui.horizontal(|ui| {
+                        ui.label(local_time(&r.post.created_at));
+
+                        ui.separator();
+                        if let Some(times) = self.times.get(&r.tid) {
+                            if ui.button(&times.title).clicked() {
+                                event = Some(Event::Select(
+                                    self.store.clone(),
+                                    times.clone(),
+                                ));
+                            }
+                        }
+
+                        ui.separator();
+                        ui.label(r.post.post.to_string());
+                    });
+                }
+            });
+        });
+
+        event
+    }
+
+    fn reload(&mut self, rt: &runtime::Runtime) {
+        if !self.query.trim().is_empty() {
+            self.search(rt);
+        }
+    }
+}
+
+impl SearchPane {
+    pub fn new(
+        store: Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel::<Message>(32);
+
+        Self {
+            query: "".to_string(),
+            results: vec![],
+            times: HashMap::new(),
+            store,
+            tx,
+            rx,
+        }
+    }
+
+    fn search(&self, rt: &runtime::Runtime) {
+        let store = self.store.clone();
+        let tx = self.tx.clone();
+        let query = self.query.clone();
+
+        rt.spawn(async move {
+            let mut store = store.lock().await;
+
+            let filter = SearchFilter {
+                limit: Some(SEARCH_LIMIT),
+                ..Default::default()
+            };
+
+            let result = match store.search_posts(query, filter).await {
+                Ok(posts) => match store.get_times().await {
+                    Ok(times) => {
+                        let times = times.into_iter().map(|t| (t.id, t));
+                        Message::Result(times.collect(), posts)
+                    }
+                    Err(e) => Message::Error(e.to_string()),
+                },
+                Err(e) => Message::Error(e.to_string()),
+            };
+
+            if let Err(e) = tx.send(result).await {
+                error!(format!("failed to send message: {e}"));
+            }
+        });
+    }
+
+    fn handle_message(&mut self) {
+        match self.rx.try_recv() {
+            Ok(msg) => match msg {
+                Message::Result(times, posts) => {
+                    debug!("found message which has search results");
+                    info!(format!("found {} posts", posts.len()));
+                    self.times = times;
+                    self.results = posts;
+                }
+                Message::Error(err) => {
+                    error!(err);
+                }
+            },
+            Err(_e) => {}
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::app::Event;
 
@@ -6,11 +7,11 @@ use eframe::egui::ScrollArea;
 use egui::{Key, Modifiers};
 use std::collections::HashMap;
 use timesman_bstore::Store;
-use timesman_type::{Post, Times};
+use timesman_type::{Change, Post, Times};
 use tokio;
 use tokio::sync::Mutex;
 
-use super::Pane;
+use super::{local_time, subscribe_changes, Pane};
 use tokio::runtime;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::{Receiver, Sender};
@@ -31,7 +32,8 @@ pub struct SelectPane {
 enum Message {
     Create(Times),
     Refresh(HashMap<u64, TimesData>),
-    UpdateLatest(u64, Post),
+    UpdateLatest(u64, Option<Post>),
+    Changed(Option<Change>),
     Error(String),
 }
 
@@ -44,10 +46,13 @@ impl Pane for SelectPane {
     ) -> Option<Event> {
         let mut event = None;
 
-        if let Some(event) = self.handle_message() {
+        if let Some(event) = self.handle_message(rt) {
             return Some(event);
         }
 
+        // changes are only noticed while repainting
+        ctx.request_repaint_after(Duration::from_millis(500));
+
         egui::TopBottomPanel::top("top").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 event = self.times_menu(ui);
@@ -83,7 +88,7 @@ impl Pane for SelectPane {
                                         .unwrap();
                                 }
                                 Err(e) => {
-                                    tx.send(Message::Error(format!("{}", e)))
+                                    tx.send(Message::Error(e.to_string()))
                                         .await
                                         .unwrap();
                                 }
@@ -91,6 +96,17 @@ impl Pane for SelectPane {
                         });
                     }
                 }
+
+                ui.separator();
+                if ui.button("search").clicked() {
+                    event = Some(Event::Search(self.store.clone()));
+                }
+                if ui.button("tags").clicked() {
+                    event = Some(Event::Tag(self.store.clone(), None, None));
+                }
+                if ui.button("trash").clicked() {
+                    event = Some(Event::Trash(self.store.clone()));
+                }
             });
             if ui.input_mut(|i| i.consume_key(Modifiers::COMMAND, Key::Enter)) {
                 let store = self.store.clone();
@@ -104,7 +120,7 @@ impl Pane for SelectPane {
                             tx.send(Message::Create(new_times)).await.unwrap();
                         }
                         Err(e) => {
-                            tx.send(Message::Error(format!("{}", e)))
+                            tx.send(Message::Error(e.to_string()))
                                 .await
                                 .unwrap();
                         }
@@ -122,15 +138,9 @@ impl Pane for SelectPane {
                 .auto_shrink(false)
                 .max_height(ui.available_height());
             scroll_area.show(ui, |ui| {
-                for (_tid, tdata) in &self.times {
+                for tdata in self.times.values() {
                     ui.horizontal(|ui| {
-                        ui.label(
-                            tdata
-                                .times
-                                .created_at
-                                .format("%Y-%m-%d %H:%M")
-                                .to_string(),
-                        );
+                        ui.label(local_time(&tdata.times.created_at));
 
                         ui.separator();
                         if ui.button(&tdata.times.title).clicked() {
@@ -142,13 +152,8 @@ impl Pane for SelectPane {
 
                         if let Some(latest) = &tdata.latest {
                             ui.separator();
-                            ui.label(format!("{}", latest.post));
-                            ui.label(
-                                latest
-                                    .created_at
-                                    .format("%Y-%m-%d %H:%M")
-                                    .to_string(),
-                            );
+                            ui.label(latest.post.to_string());
+                            ui.label(local_time(&latest.created_at));
                         }
                     });
                 }
@@ -188,7 +193,9 @@ impl Pane for SelectPane {
                     if let Some(latest) =
                         store.get_latest_post(t.id).await.unwrap()
                     {
-                        match tx.send(Message::UpdateLatest(t.id, latest)).await
+                        match tx
+                            .send(Message::UpdateLatest(t.id, Some(latest)))
+                            .await
                         {
                             Ok(_) => {}
                             Err(e) => {
@@ -218,11 +225,46 @@ impl SelectPane {
         };
 
         pane.reload(rt);
+        subscribe_changes(store, rt, pane.tx.clone(), Message::Changed);
 
         pane
     }
 
-    fn handle_message(&mut self) -> Option<Event> {
+    fn load_latest(&self, rt: &runtime::Runtime, tid: u64) {
+        let store = self.store.clone();
+        let tx = self.tx.clone();
+        rt.spawn(async move {
+            let mut store = store.lock().await;
+            match store.get_latest_post(tid).await {
+                Ok(latest) => {
+                    tx.send(Message::UpdateLatest(tid, latest)).await.unwrap();
+                }
+                Err(e) => {
+                    error!(e);
+                }
+            }
+        });
+    }
+
+    fn apply_change(&mut self, rt: &runtime::Runtime, change: Change) {
+        match change {
+            Change::TimesCreated { times } | Change::TimesUpdated { times } => {
+                let latest =
+                    self.times.remove(&times.id).and_then(|t| t.latest);
+                self.times.insert(times.id, TimesData { times, latest });
+            }
+            Change::TimesDeleted { tid } => {
+                self.times.remove(&tid);
+            }
+            Change::PostCreated { tid, .. }
+            | Change::PostUpdated { tid, .. }
+            | Change::PostDeleted { tid, .. } => {
+                self.load_latest(rt, tid);
+            }
+        }
+    }
+
+    fn handle_message(&mut self, rt: &runtime::Runtime) -> Option<Event> {
         match self.rx.try_recv() {
             Ok(msg) => match msg {
                 Message::Create(times) => {
@@ -237,9 +279,17 @@ impl SelectPane {
                 Message::UpdateLatest(tid, post) => {
                     debug!("found message which update latest");
                     if let Some(tdata) = self.times.get_mut(&tid) {
-                        tdata.latest = Some(post);
+                        tdata.latest = post;
                     }
                 }
+                Message::Changed(Some(change)) => {
+                    debug!("found message which has a change");
+                    self.apply_change(rt, change);
+                }
+                Message::Changed(None) => {
+                    debug!("found message which missed changes");
+                    self.reload(rt);
+                }
                 Message::Error(err) => {
                     error!(err);
                 }
@@ -7,10 +7,9 @@ use crate::config::Config;
 use super::Pane;
 
 use egui_file_dialog::FileDialog;
-#[cfg(feature = "grpc")]
-use timesman_bstore::grpc::GrpcStore;
 #[cfg(feature = "json")]
 use timesman_bstore::json::JsonStore;
+use timesman_bstore::notify::NotifyingStore;
 use timesman_bstore::ram::RamStore;
 #[cfg(feature = "http")]
 use timesman_bstore::remote::RemoteStore;
@@ -22,10 +21,19 @@ use tokio::sync::mpsc::{self};
 use tokio::sync::Mutex;
 
 pub struct StartPane {
+    #[cfg_attr(
+        not(any(feature = "http", feature = "sqlite")),
+        allow(dead_code)
+    )]
     config: Config,
     errmsg: Option<String>,
     store: StoreType,
+    #[cfg_attr(
+        not(any(feature = "json", feature = "sqlite")),
+        allow(dead_code)
+    )]
     file_dialog: FileDialog,
+    #[cfg_attr(not(feature = "json"), allow(dead_code))]
     json_file: Option<PathBuf>,
 }
 
@@ -45,28 +53,39 @@ impl StartPane {
             match self.store {
                 #[cfg(feature = "http")]
                 StoreType::Remote => {
-                    let server = self.config.params.remote.server.clone();
-                    Arc::new(Mutex::new(Box::new(RemoteStore::new(server))))
-                }
-                StoreType::Memory => {
-                    Arc::new(Mutex::new(Box::new(RamStore::new())))
+                    let remote = &self.config.params.remote;
+                    let mut store = RemoteStore::new(remote.server.clone());
+                    if let Some(token) = &remote.token {
+                        store = store.with_token(token.clone());
+                    }
+                    Arc::new(Mutex::new(Box::new(store)))
                 }
+                StoreType::Memory => notifying(RamStore::new()),
                 #[cfg(feature = "json")]
                 StoreType::Json => {
                     if let Some(path) = &self.json_file {
-                        let store = JsonStore::build(path.clone())?;
-                        Arc::new(Mutex::new(Box::new(store)))
+                        let store = JsonStore::build(path.clone())
+                            .map_err(|e| format!("{e}"))?;
+                        notifying(store)
                     } else {
-                        return Err(format!("You should select the json file"));
+                        return Err(
+                            "You should select the json file".to_string()
+                        );
                     }
                 }
                 #[cfg(feature = "sqlite")]
                 StoreType::Sqlite => {
                     let path = self.config.params.sqlite.db.clone();
                     let store = SqliteStoreBuilder::new(&path);
-                    let store =
-                        rt.block_on(async move { store.build().await })?;
-                    Arc::new(Mutex::new(Box::new(store)))
+                    let store = rt
+                        .block_on(async move { store.build().await })
+                        .map_err(|e| format!("{e}"))?;
+                    notifying(store)
+                }
+                // bstore features may be unified with other workspace crates
+                #[allow(unreachable_patterns)]
+                _ => {
+                    return Err("this store type is not enabled".to_string());
                 }
             };
 
@@ -76,7 +95,9 @@ impl StartPane {
 
             rt.block_on(async move {
                 let mut store = store.lock().await;
-                tx.send(store.check().await).await.unwrap();
+                tx.send(store.check().await.map_err(|e| format!("{e}")))
+                    .await
+                    .unwrap();
             });
 
             rx.blocking_recv().ok_or("failed to setup backing store")?
@@ -156,6 +177,8 @@ impl Pane for StartPane {
                     }
                     ui.label(&self.config.params.sqlite.db);
                 }
+                #[allow(unreachable_patterns)]
+                _ => {}
             }
 
             ui.separator();
@@ -180,3 +203,11 @@ impl Pane for StartPane {
 
     fn reload(&mut self, _rt: &runtime::Runtime) {}
 }
+
+/// Shares a local store, reporting its changes to the panes. Remote stores
+/// report the changes of the server instead.
+fn notifying(
+    store: impl Store + 'static,
+) -> Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>> {
+    Arc::new(Mutex::new(Box::new(NotifyingStore::new(Box::new(store)))))
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::app::Event;
+
+use eframe::egui::ScrollArea;
+use timesman_bstore::Store;
+use timesman_type::{Tag, Times, TimesPost};
+use tokio::sync::Mutex;
+
+use super::{local_time, Pane};
+use tokio::runtime;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::{Receiver, Sender};
+
+/// The posts of every times carrying a tag, chosen among the tags in use.
+pub struct TagPane {
+    tags: Vec<Tag>,
+    tag: Option<String>,
+    /// Only shows the posts of this times while `only_scope` is set.
+    scope: Option<Times>,
+    only_scope: bool,
+    results: Vec<TimesPost>,
+    times: HashMap<u64, Times>,
+    store: Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
+    tx: Sender<Message>,
+    rx: Receiver<Message>,
+}
+
+enum Message {
+    Tags(Vec<Tag>),
+    Result(String, HashMap<u64, Times>, Vec<TimesPost>),
+    Error(String),
+}
+
+impl Pane for TagPane {
+    fn update(
+        &mut self,
+        ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+        rt: &runtime::Runtime,
+    ) -> Option<Event> {
+        let mut event = None;
+        let mut select = None;
+
+        self.handle_message();
+
+        egui::TopBottomPanel::top("top").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                event = self.times_menu(ui);
+            });
+
+            ui.separator();
+            ui.horizontal_wrapped(|ui| {
+                ui.label("tags");
+                if let Some(scope) = &self.scope {
+                    ui.separator();
+                    ui.checkbox(
+                        &mut self.only_scope,
+                        format!("only {}", scope.title),
+                    );
+                }
+                ui.separator();
+                for tag in &self.tags {
+                    let selected = self.tag.as_ref() == Some(&tag.name);
+                    let chip = format!("#{} ({})", tag.name, tag.count);
+                    if ui.selectable_label(selected, chip).clicked() {
+                        select = Some(tag.name.clone());
+                    }
+                }
+            });
+        });
+
+        egui::TopBottomPanel::bottom("bottom").show(ctx, |ui| {
+            self.show_latest_log(ui);
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let scroll_area = ScrollArea::vertical()
+                .auto_shrink(false)
+                .max_height(ui.available_height());
+            scroll_area.show(ui, |ui| {
+                let scope = match &self.scope {
+                    Some(scope) if self.only_scope => Some(scope.id),
+                    _ => None,
+                };
+
+                for r in &self.results {
+                    if scope.is_some_and(|tid| tid != r.tid) {
+                        continue;
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.label(local_time(&r.post.created_at));
+
+                        ui.separator();
+                        if let Some(times) = self.times.get(&r.tid) {
+                            if ui.button(&times.title).clicked() {
+                                event = Some(Event::Select(
+                                    self.store.clone(),
+                                    times.clone(),
+                                ));
+                            }
+                        }
+
+                        ui.separator();
+                        ui.label(r.post.post.to_string());
+                    });
+                }
+            });
+        });
+
+        if let Some(tag) = select {
+            self.tag = Some(tag);
+            self.load_posts(rt);
+        }
+
+        event
+    }
+
+    fn reload(&mut self, rt: &runtime::Runtime) {
+        self.load_tags(rt);
+        self.load_posts(rt);
+    }
+}
+
+impl TagPane {
+    /// Opens the pane on `tag`, if given, limited to the posts of `scope`
+    /// until the user widens it to every times.
+    pub fn new(
+        store: Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
+        tag: Option<String>,
+        scope: Option<Times>,
+        rt: &runtime::Runtime,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel::<Message>(32);
+
+        let mut pane = Self {
+            tags: vec![],
+            tag,
+            only_scope: scope.is_some(),
+            scope,
+            results: vec![],
+            times: HashMap::new(),
+            store,
+            tx,
+            rx,
+        };
+        pane.reload(rt);
+
+        pane
+    }
+
+    fn load_tags(&self, rt: &runtime::Runtime) {
+        let store = self.store.clone();
+        let tx = self.tx.clone();
+
+        rt.spawn(async move {
+            let msg = match store.lock().await.list_tags().await {
+                Ok(tags) => Message::Tags(tags),
+                Err(e) => Message::Error(e.to_string()),
+            };
+
+            if let Err(e) = tx.send(msg).await {
+                error!(format!("failed to send message: {e}"));
+            }
+        });
+    }
+
+    fn load_posts(&self, rt: &runtime::Runtime) {
+        let Some(tag) = self.tag.clone() else {
+            return;
+        };
+        let store = self.store.clone();
+        let tx = self.tx.clone();
+
+        rt.spawn(async move {
+            let mut store = store.lock().await;
+
+            let result = match store.get_posts_by_tag(tag.clone()).await {
+                Ok(posts) => match store.get_times().await {
+                    Ok(times) => {
+                        let times = times.into_iter().map(|t| (t.id, t));
+                        Message::Result(tag, times.collect(), posts)
+                    }
+                    Err(e) => Message::Error(e.to_string()),
+                },
+                Err(e) => Message::Error(e.to_string()),
+            };
+
+            if let Err(e) = tx.send(result).await {
+                error!(format!("failed to send message: {e}"));
+            }
+        });
+    }
+
+    fn handle_message(&mut self) {
+        match self.rx.try_recv() {
+            Ok(msg) => match msg {
+                Message::Tags(tags) => {
+                    self.tags = tags;
+                }
+                Message::Result(tag, times, posts) => {
+                    // an answer for a tag no longer selected is stale
+                    if self.tag.as_ref() == Some(&tag) {
+                        info!(format!("found {} posts of #{tag}", posts.len()));
+                        self.times = times;
+                        self.results = posts;
+                    }
+                }
+                Message::Error(err) => {
+                    error!(err);
+                }
+            },
+            Err(_e) => {}
+        }
+    }
+}
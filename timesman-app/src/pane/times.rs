@@ -1,42 +1,74 @@
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 use url::Url;
 
 use crate::app::Event;
 
-use chrono::{DateTime, Local, TimeZone, Utc};
+use chrono::{DateTime, Local};
+use dissimilar::Chunk;
 use eframe::egui::ScrollArea;
-use egui::{Key, Modifiers, Ui};
+use egui::load::Bytes;
+use egui::{Color32, Key, Modifiers, RichText, Ui};
 use egui_file_dialog::FileDialog;
 #[cfg(feature = "json")]
 use timesman_bstore::json::JsonStore;
-use timesman_bstore::Store;
-use timesman_type::{Post, Times};
+use timesman_bstore::{PostPage, PostQuery, Store, StoreError};
+use timesman_type::{Attachment, Change, Post, Revision, Times};
 use tokio::runtime;
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::sync::Mutex;
 
-use super::Pane;
+use super::{local_time, subscribe_changes, Pane};
+
+/// Number of posts fetched at once when scrolling back in time.
+const PAGE_SIZE: usize = 100;
+
+/// Largest side of the inline preview of an attached image.
+const THUMBNAIL_SIZE: f32 = 160.0;
 
 pub struct TimesPane {
     times: Times,
     posts: Vec<Post>,
+    older: Option<u64>,
+    loading_older: bool,
+    /// Scroll offset of the previous frame, so that reaching the top loads
+    /// one older page rather than every page shown at the top in turn.
+    scroll_offset: f32,
     post_text: String,
     file_dialog: FileDialog,
     store: Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
     edit_title: bool,
     edit_post: Option<u64>,
+    /// Earlier versions of edited posts, fetched when first hovered.
+    revisions: HashMap<u64, Vec<Revision>>,
+    fetching: HashSet<u64>,
+    /// Attachments by the post they belong to.
+    attachments: HashMap<u64, Vec<Attachment>>,
+    /// Content of attached images, fetched when first shown.
+    images: HashMap<u64, Bytes>,
+    loading_images: HashSet<u64>,
+    attachment_dialog: FileDialog,
+    saving: Option<Attachment>,
     tx: Sender<Message>,
     rx: Receiver<Message>,
 }
 
 enum Message {
-    Refresh(Vec<Post>),
+    Refresh(PostPage),
+    Older(PostPage),
+    PageFailed,
     Create(Post),
     UpdateTimes(Times),
     UpdatePost(Post),
+    Revisions(u64, Vec<Revision>),
+    Attachments(Vec<Attachment>),
+    Attached(Attachment),
+    Image(u64, Vec<u8>),
     Delete(Post),
+    Changed(Option<Change>),
     Pop,
 }
 
@@ -48,36 +80,382 @@ impl TimesPane {
     ) -> Self {
         let (tx, rx) = mpsc::channel::<Message>(32);
 
-        {
-            let tid = times.id;
-            let store2 = store.clone();
-            let msg_tx = tx.clone();
-            rt.spawn(async move {
-                let mut store = store2.lock().await;
-                match store.get_posts(tid).await {
-                    Ok(posts) => {
-                        msg_tx.send(Message::Refresh(posts)).await.unwrap();
-                    }
-                    Err(_e) => {}
-                }
-            });
-        }
-
-        //TODO
-        // let store_ref = store.borrow();
-        // let posts = store_ref.get_posts(times.id).unwrap();
-
-        Self {
+        let pane = Self {
             posts: vec![],
+            older: None,
+            loading_older: true,
+            scroll_offset: 0.0,
             times,
             post_text: "".to_string(),
             file_dialog: FileDialog::new(),
             store: store.clone(),
             edit_title: false,
             edit_post: None,
+            revisions: HashMap::new(),
+            fetching: HashSet::new(),
+            attachments: HashMap::new(),
+            images: HashMap::new(),
+            loading_images: HashSet::new(),
+            attachment_dialog: FileDialog::new(),
+            saving: None,
             tx,
             rx,
+        };
+
+        pane.load_page(rt, None);
+        subscribe_changes(store, rt, pane.tx.clone(), Message::Changed);
+
+        pane
+    }
+
+    /// Adds `post` in order of ids, or replaces it when already shown.
+    fn insert_post(&mut self, post: Post) {
+        let i = self.posts.partition_point(|p| p.id < post.id);
+        match self.posts.get_mut(i) {
+            Some(p) if p.id == post.id => *p = post,
+            _ => self.posts.insert(i, post),
+        }
+    }
+
+    fn apply_change(&mut self, change: Change) -> Option<Event> {
+        if change.tid() != self.times.id {
+            return None;
+        }
+
+        match change {
+            Change::TimesCreated { .. } => {}
+            Change::TimesUpdated { times } => {
+                if !self.edit_title {
+                    self.times = times;
+                }
+            }
+            Change::TimesDeleted { .. } => {
+                info!("the times was deleted");
+                return Some(Event::Pop);
+            }
+            Change::PostCreated { post, .. } => {
+                // older posts show up once scrolled back to
+                if self.older.is_none_or(|older| post.id > older) {
+                    self.insert_post(post);
+                }
+            }
+            Change::PostUpdated { post, .. } => {
+                self.revisions.remove(&post.id);
+                self.fetching.remove(&post.id);
+                if let Some(p) = self.posts.iter_mut().find(|p| p.id == post.id)
+                {
+                    *p = post;
+                }
+            }
+            Change::PostDeleted { pid, .. } => {
+                self.posts.retain(|p| p.id != pid);
+            }
+        }
+
+        None
+    }
+
+    /// Fetches the page of posts older than `cursor`, or the newest page.
+    fn load_page(&self, rt: &runtime::Runtime, cursor: Option<u64>) {
+        let store = self.store.clone();
+        let tid = self.times.id;
+        let tx = self.tx.clone();
+        let query = PostQuery {
+            cursor,
+            limit: Some(PAGE_SIZE),
+            ..Default::default()
+        };
+
+        rt.spawn(async move {
+            let mut store = store.lock().await;
+            match store.get_posts_page(tid, query).await {
+                Ok(page) => {
+                    let msg = match cursor {
+                        Some(_) => Message::Older(page),
+                        None => Message::Refresh(page),
+                    };
+                    tx.send(msg).await.unwrap();
+                }
+                Err(e) => {
+                    error!(e);
+                    tx.send(Message::PageFailed).await.unwrap();
+                    return;
+                }
+            }
+
+            // the attachments of the whole times come with the newest page
+            if cursor.is_none() {
+                match store.list_attachments(tid).await {
+                    Ok(attachments) => {
+                        tx.send(Message::Attachments(attachments))
+                            .await
+                            .unwrap();
+                    }
+                    Err(StoreError::Unsupported(_)) => {}
+                    Err(e) => {
+                        error!(e);
+                    }
+                }
+            }
+        });
+    }
+
+    fn load_image(&mut self, rt: &runtime::Runtime, aid: u64) {
+        if !self.loading_images.insert(aid) {
+            return;
+        }
+
+        let store = self.store.clone();
+        let tid = self.times.id;
+        let tx = self.tx.clone();
+
+        rt.spawn(async move {
+            let mut store = store.lock().await;
+            match store.read_attachment(tid, aid).await {
+                Ok(data) => {
+                    tx.send(Message::Image(aid, data)).await.unwrap();
+                }
+                Err(e) => {
+                    error!(e);
+                }
+            }
+        });
+    }
+
+    fn save_attachment(
+        &self,
+        rt: &runtime::Runtime,
+        attachment: Attachment,
+        path: &Path,
+    ) {
+        let store = self.store.clone();
+        let tid = self.times.id;
+        let path = path.to_path_buf();
+
+        rt.spawn(async move {
+            let data = match store
+                .lock()
+                .await
+                .read_attachment(tid, attachment.id)
+                .await
+            {
+                Ok(data) => data,
+                Err(e) => {
+                    error!(e);
+                    return;
+                }
+            };
+
+            match std::fs::write(&path, data) {
+                Ok(()) => {
+                    info!(format!(
+                        "saved {} to {}",
+                        attachment.name,
+                        path.display()
+                    ));
+                }
+                Err(e) => {
+                    error!(format!("failed to save {}: {e}", path.display()));
+                }
+            }
+        });
+    }
+
+    /// Posts each dropped file as a new post carrying it as an attachment.
+    fn attach_files(
+        &self,
+        rt: &runtime::Runtime,
+        files: Vec<egui::DroppedFile>,
+    ) {
+        for file in files {
+            let (name, data) = match (&file.path, &file.bytes) {
+                (_, Some(bytes)) => (file.name.clone(), bytes.to_vec()),
+                (Some(path), None) => {
+                    let name = path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    match std::fs::read(path) {
+                        Ok(data) => (name, data),
+                        Err(e) => {
+                            error!(format!(
+                                "failed to read {}: {e}",
+                                path.display()
+                            ));
+                            continue;
+                        }
+                    }
+                }
+                (None, None) => continue,
+            };
+
+            let mime = if file.mime.is_empty() {
+                guess_mime(&name).to_string()
+            } else {
+                file.mime.clone()
+            };
+
+            let store = self.store.clone();
+            let tid = self.times.id;
+            let tx = self.tx.clone();
+
+            rt.spawn(async move {
+                let mut store = store.lock().await;
+                let post = match store.create_post(tid, name.clone()).await {
+                    Ok(post) => post,
+                    Err(e) => {
+                        error!(e);
+                        return;
+                    }
+                };
+                tx.send(Message::Create(post.clone())).await.unwrap();
+
+                match store.attach(tid, post.id, name, mime, data).await {
+                    Ok(attachment) => {
+                        tx.send(Message::Attached(attachment)).await.unwrap();
+                    }
+                    Err(e) => {
+                        error!(e);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Shows the attachments of a post: images inline, other files as
+    /// buttons. Returns the attachment the user asked to save.
+    fn show_attachments(
+        ui: &mut Ui,
+        attachments: &[Attachment],
+        images: &HashMap<u64, Bytes>,
+        load: &mut Vec<u64>,
+    ) -> Option<Attachment> {
+        let mut save = None;
+
+        ui.horizontal_wrapped(|ui| {
+            for a in attachments {
+                let hover = format!("{}\n{}, {} bytes", a.name, a.mime, a.size);
+
+                let resp = if a.is_image() {
+                    match images.get(&a.id) {
+                        Some(bytes) => ui.add(
+                            egui::Image::from_bytes(
+                                format!(
+                                    "bytes://attachment/{}/{}",
+                                    a.id, a.name
+                                ),
+                                bytes.clone(),
+                            )
+                            .max_width(THUMBNAIL_SIZE)
+                            .max_height(THUMBNAIL_SIZE)
+                            .sense(egui::Sense::click()),
+                        ),
+                        None => {
+                            load.push(a.id);
+                            ui.spinner()
+                        }
+                    }
+                } else {
+                    ui.small_button(format!("[{}]", a.name))
+                };
+
+                if resp.on_hover_text(hover).clicked() {
+                    save = Some(a.clone());
+                }
+            }
+        });
+
+        save
+    }
+
+    fn load_revisions(&mut self, rt: &runtime::Runtime, pid: u64) {
+        if !self.fetching.insert(pid) {
+            return;
+        }
+
+        let store = self.store.clone();
+        let tid = self.times.id;
+        let tx = self.tx.clone();
+
+        rt.spawn(async move {
+            let mut store = store.lock().await;
+            match store.list_revisions(tid, pid).await {
+                Ok(revisions) => {
+                    tx.send(Message::Revisions(pid, revisions)).await.unwrap();
+                }
+                Err(e) => {
+                    // remember the failure so hovering does not retry
+                    error!(e);
+                    tx.send(Message::Revisions(pid, vec![])).await.unwrap();
+                }
+            }
+        });
+    }
+
+    fn revert_post(&self, rt: &runtime::Runtime, pid: u64, rev: u64) {
+        let store = self.store.clone();
+        let tid = self.times.id;
+        let tx = self.tx.clone();
+
+        rt.spawn(async move {
+            let mut store = store.lock().await;
+            match store.revert_post(tid, pid, rev).await {
+                Ok(p) => {
+                    tx.send(Message::UpdatePost(p)).await.unwrap();
+                }
+                Err(e) => {
+                    error!(e);
+                }
+            }
+        });
+    }
+
+    /// Shows what changed from `old` to `new`, marking removed text red and
+    /// added text green.
+    fn show_diff(ui: &mut Ui, old: &str, new: &str) {
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing.x = 0.0;
+            for chunk in dissimilar::diff(old, new) {
+                let text = match chunk {
+                    Chunk::Equal(s) => RichText::new(s),
+                    Chunk::Delete(s) => RichText::new(s)
+                        .strikethrough()
+                        .color(Color32::LIGHT_RED),
+                    Chunk::Insert(s) => {
+                        RichText::new(s).color(Color32::LIGHT_GREEN)
+                    }
+                };
+                ui.label(text);
+            }
+        });
+    }
+
+    /// Lists the edits of a post, newest first, each as a diff against the
+    /// version which replaced it.
+    fn show_revisions(
+        ui: &mut Ui,
+        post: &Post,
+        revisions: &[Revision],
+    ) -> Option<u64> {
+        let mut revert = None;
+
+        if revisions.is_empty() {
+            ui.label("no earlier versions");
+        }
+
+        let mut newer = post.post.as_str();
+        for r in revisions.iter().rev() {
+            ui.horizontal(|ui| {
+                ui.label(format!("#{} {}", r.rev, local_time(&r.created_at)));
+                if ui.small_button("revert").clicked() {
+                    revert = Some(r.rev);
+                }
+            });
+            Self::show_diff(ui, &r.post, newer);
+            newer = &r.post;
         }
+
+        revert
     }
 
     fn is_same_hour<T: chrono::Datelike + chrono::Timelike>(
@@ -100,37 +478,55 @@ impl TimesPane {
             return false;
         }
 
-        return true;
+        true
     }
 
+    /// Shows the loaded posts. A click on a tag opens the posts of the tag,
+    /// which the store finds in every page and times.
     fn show_times(
         &mut self,
         rt: &runtime::Runtime,
         scroll_area: ScrollArea,
         ui: &mut Ui,
-    ) {
-        scroll_area.show(ui, |ui| {
-            let mut prev: Option<chrono::NaiveDateTime> = None;
+    ) -> Option<Event> {
+        let mut show_tag = None;
+        let output = scroll_area.show(ui, |ui| {
+            if let Some(cursor) = self.older {
+                if ui.button("load older posts").clicked()
+                    && !self.loading_older
+                {
+                    self.loading_older = true;
+                    self.load_page(rt, Some(cursor));
+                }
+            }
+
+            let mut prev: Option<DateTime<Local>> = None;
+            let mut fetch = None;
+            let mut revert = None;
+            let mut load = vec![];
+            let mut save = None;
 
             for p in &mut self.posts {
-                if let Some(ptime) = prev {
-                    if !Self::is_same_hour(&ptime, &p.created_at) {
+                let tags = p.tags();
+
+                // separate the posts by the hour they were written locally
+                let ptime = p.created_at.with_timezone(&Local);
+                if let Some(prev) = prev {
+                    if !Self::is_same_hour(&prev, &ptime) {
                         ui.separator();
                     }
                 }
-                prev = Some(p.created_at);
+                prev = Some(ptime);
 
                 ui.horizontal(|ui| {
-                    let utc_time = Utc.from_utc_datetime(&p.created_at);
-                    let local_time: DateTime<Local> = DateTime::from(utc_time);
-                    ui.label(
-                        local_time
-                            .naive_local()
-                            .format("%Y-%m-%d %H:%M")
-                            .to_string(),
-                    );
+                    ui.label(local_time(&p.created_at));
                     ui.separator();
 
+                    if let Some(author) = &p.author {
+                        ui.label(author);
+                        ui.separator();
+                    }
+
                     if let Some(edit_pid) = self.edit_post {
                         if p.id == edit_pid {
                             ui.text_edit_singleline(&mut p.post);
@@ -190,39 +586,135 @@ impl TimesPane {
                                 }
                             });
                         });
+
+                        if p.updated_at.is_some() {
+                            let mark = ui.label(
+                                RichText::new("(edited)").weak().small(),
+                            );
+                            if mark.hovered()
+                                && !self.revisions.contains_key(&p.id)
+                            {
+                                fetch = Some(p.id);
+                            }
+                            mark.on_hover_ui(|ui| {
+                                match self.revisions.get(&p.id) {
+                                    Some(revisions) => {
+                                        if let Some(rev) = Self::show_revisions(
+                                            ui, p, revisions,
+                                        ) {
+                                            revert = Some((p.id, rev));
+                                        }
+                                    }
+                                    None => {
+                                        ui.label("loading...");
+                                    }
+                                }
+                            });
+                        }
+
+                        for tag in tags {
+                            if ui.small_button(format!("#{tag}")).clicked() {
+                                show_tag = Some(tag);
+                            }
+                        }
                     }
                 });
+
+                if let Some(attachments) = self.attachments.get(&p.id) {
+                    if let Some(a) = Self::show_attachments(
+                        ui,
+                        attachments,
+                        &self.images,
+                        &mut load,
+                    ) {
+                        save = Some(a);
+                    }
+                }
+            }
+
+            if let Some(pid) = fetch {
+                self.load_revisions(rt, pid);
+            }
+            if let Some((pid, rev)) = revert {
+                self.revert_post(rt, pid, rev);
+            }
+            for aid in load {
+                self.load_image(rt, aid);
+            }
+            if let Some(a) = save {
+                self.saving = Some(a);
+                self.attachment_dialog.save_file();
             }
         });
+
+        // fetch the older page once scrolled up to the top of the list
+        let offset = output.state.offset.y;
+        if offset <= 0.0 && self.scroll_offset > 0.0 && !self.loading_older {
+            if let Some(cursor) = self.older {
+                self.loading_older = true;
+                self.load_page(rt, Some(cursor));
+            }
+        }
+        self.scroll_offset = offset;
+
+        show_tag.map(|tag| {
+            Event::Tag(self.store.clone(), Some(tag), Some(self.times.clone()))
+        })
     }
 
     #[cfg(not(feature = "json"))]
-    fn save_file(&self, _path: &PathBuf) -> Result<(), String> {
-        Err("Json feature is disabled.".to_string())
+    fn save_file(&self, _rt: &runtime::Runtime, _path: &Path) {
+        error!("Json feature is disabled.");
     }
 
+    /// Exports every post of the times, not only the loaded pages.
     #[cfg(feature = "json")]
-    fn save_file(&self, path: &PathBuf) -> Result<(), String> {
-        let mut json_store =
-            JsonStore::new(self.times.clone(), self.posts.clone());
-
-        json_store.save_to_file(path)?;
+    fn save_file(&self, rt: &runtime::Runtime, path: &Path) {
+        let store = self.store.clone();
+        let times = self.times.clone();
+        let path = path.to_path_buf();
+
+        rt.spawn(async move {
+            let posts = match store.lock().await.get_posts(times.id).await {
+                Ok(posts) => posts,
+                Err(e) => {
+                    error!(e);
+                    return;
+                }
+            };
 
-        Ok(())
+            let json_store = JsonStore::new(times, posts);
+            if let Err(e) = json_store.save_to_file(&path) {
+                error!(e);
+            }
+        });
     }
 
-    fn handle_message(&mut self) -> Option<Event> {
+    fn handle_message(&mut self, rt: &runtime::Runtime) -> Option<Event> {
         if self.rx.is_empty() {
             return None;
         }
 
         match self.rx.try_recv() {
             Ok(msg) => match msg {
-                Message::Refresh(posts) => {
+                Message::Refresh(page) => {
+                    self.posts = page.posts;
+                    self.older = page.next;
+                    self.loading_older = false;
+                }
+                Message::Older(page) => {
+                    let mut posts = page.posts;
+                    posts.append(&mut self.posts);
                     self.posts = posts;
+                    self.older = page.next;
+                    self.loading_older = false;
+                }
+                Message::PageFailed => {
+                    self.loading_older = false;
                 }
                 Message::Create(post) => {
-                    self.posts.push(post);
+                    // the change notification may have added it already
+                    self.insert_post(post);
                     self.post_text.clear();
                 }
                 Message::UpdateTimes(times) => {
@@ -230,6 +722,8 @@ impl TimesPane {
                     self.edit_title = false;
                 }
                 Message::UpdatePost(post) => {
+                    self.revisions.remove(&post.id);
+                    self.fetching.remove(&post.id);
                     if let Some(p) =
                         self.posts.iter_mut().find(|p| p.id == post.id)
                     {
@@ -239,11 +733,38 @@ impl TimesPane {
                     }
                     self.edit_post = None;
                 }
+                Message::Attachments(attachments) => {
+                    self.attachments.clear();
+                    for a in attachments {
+                        self.attachments.entry(a.pid).or_default().push(a);
+                    }
+                }
+                Message::Attached(attachment) => {
+                    info!(format!("attached {}", attachment.name));
+                    self.attachments
+                        .entry(attachment.pid)
+                        .or_default()
+                        .push(attachment);
+                }
+                Message::Image(aid, data) => {
+                    self.images.insert(aid, data.into());
+                    self.loading_images.remove(&aid);
+                }
+                Message::Revisions(pid, revisions) => {
+                    self.revisions.insert(pid, revisions);
+                    self.fetching.remove(&pid);
+                }
                 Message::Delete(post) => {
                     debug!("Handling delete post: {}", post.id);
-                    let prev_len = self.posts.len();
+                    let _prev_len = self.posts.len();
                     self.posts.retain(|x| x.id != post.id);
                 }
+                Message::Changed(Some(change)) => {
+                    return self.apply_change(change);
+                }
+                Message::Changed(None) => {
+                    self.load_page(rt, None);
+                }
                 Message::Pop => {
                     return Some(Event::Pop);
                 }
@@ -264,9 +785,10 @@ impl Pane for TimesPane {
         _frame: &mut eframe::Frame,
         rt: &runtime::Runtime,
     ) -> Option<Event> {
-        let mut event = None;
+        let mut event = self.handle_message(rt);
 
-        event = self.handle_message();
+        // changes are only noticed while repainting
+        ctx.request_repaint_after(Duration::from_millis(500));
 
         egui::TopBottomPanel::top("top").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
@@ -299,6 +821,7 @@ impl Pane for TimesPane {
                         let mut store = store.lock().await;
                         match store.delete_times(tid).await {
                             Ok(()) => {
+                                info!("moved the times to the trash");
                                 tx.send(Message::Pop).await.unwrap();
                             }
                             Err(e) => {
@@ -337,7 +860,15 @@ impl Pane for TimesPane {
                 self.file_dialog.update(ctx);
 
                 if let Some(path) = self.file_dialog.take_selected() {
-                    self.save_file(&path);
+                    self.save_file(rt, &path);
+                }
+
+                self.attachment_dialog.update(ctx);
+
+                if let Some(path) = self.attachment_dialog.take_selected() {
+                    if let Some(attachment) = self.saving.take() {
+                        self.save_attachment(rt, attachment, &path);
+                    }
                 }
             });
         });
@@ -375,12 +906,23 @@ impl Pane for TimesPane {
             }
         });
 
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        if !dropped.is_empty() {
+            self.attach_files(rt, dropped);
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
+            if ctx.input(|i| !i.raw.hovered_files.is_empty()) {
+                ui.label("drop the files to post them");
+            }
+
             let scroll_area = ScrollArea::vertical()
                 .auto_shrink(false)
                 .max_height(ui.available_height())
                 .stick_to_bottom(true);
-            self.show_times(rt, scroll_area, ui);
+            if let Some(e) = self.show_times(rt, scroll_area, ui) {
+                event = Some(e);
+            }
         });
 
         event
@@ -388,3 +930,21 @@ impl Pane for TimesPane {
 
     fn reload(&mut self, _rt: &runtime::Runtime) {}
 }
+
+/// Guesses the MIME type of a dropped file from its extension.
+fn guess_mime(name: &str) -> &'static str {
+    let ext = Path::new(name)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "txt" | "log" | "md" => "text/plain",
+        "json" => "application/json",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
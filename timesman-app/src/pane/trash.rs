@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use crate::app::Event;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use eframe::egui::ScrollArea;
+use timesman_bstore::{Store, StoreError};
+use timesman_type::Trash;
+use tokio::sync::Mutex;
+
+use super::{local_time, Pane};
+use tokio::runtime;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::{Receiver, Sender};
+
+/// Entries deleted longer ago than this are removed by the purge button.
+const KEEP_DAYS: i64 = 30;
+
+pub struct TrashPane {
+    trash: Trash,
+    store: Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
+    tx: Sender<Message>,
+    rx: Receiver<Message>,
+}
+
+enum Message {
+    Refresh(Trash),
+    Error(String),
+}
+
+enum Operation {
+    RestoreTimes(u64),
+    RestorePost(u64, u64),
+    PurgeTimes(u64),
+    PurgePost(u64, u64),
+    PurgeTrash(Option<DateTime<Utc>>),
+}
+
+impl Pane for TrashPane {
+    fn update(
+        &mut self,
+        ctx: &egui::Context,
+        _frame: &mut eframe::Frame,
+        rt: &runtime::Runtime,
+    ) -> Option<Event> {
+        let mut event = None;
+        let mut op = None;
+
+        self.handle_message();
+
+        egui::TopBottomPanel::top("top").show(ctx, |ui| {
+            egui::menu::bar(ui, |ui| {
+                event = self.times_menu(ui);
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("trash");
+                ui.separator();
+                if ui.button(format!("purge {KEEP_DAYS} days old")).clicked() {
+                    let before = Utc::now() - TimeDelta::days(KEEP_DAYS);
+                    op = Some(Operation::PurgeTrash(Some(before)));
+                }
+                if ui.button("empty").clicked() {
+                    op = Some(Operation::PurgeTrash(None));
+                }
+            });
+        });
+
+        egui::TopBottomPanel::bottom("bottom").show(ctx, |ui| {
+            self.show_latest_log(ui);
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let scroll_area = ScrollArea::vertical()
+                .auto_shrink(false)
+                .max_height(ui.available_height());
+            scroll_area.show(ui, |ui| {
+                ui.label("times");
+                for t in &self.trash.times {
+                    ui.horizontal(|ui| {
+                        ui.label(local_time(&t.deleted_at));
+                        ui.separator();
+                        ui.label(&t.times.title);
+                        ui.separator();
+                        if ui.button("restore").clicked() {
+                            op = Some(Operation::RestoreTimes(t.times.id));
+                        }
+                        if ui.button("purge").clicked() {
+                            op = Some(Operation::PurgeTimes(t.times.id));
+                        }
+                    });
+                }
+
+                ui.separator();
+                ui.label("posts");
+                for p in &self.trash.posts {
+                    ui.horizontal(|ui| {
+                        ui.label(local_time(&p.deleted_at));
+                        ui.separator();
+                        ui.label(&p.post.post);
+                        ui.separator();
+                        if ui.button("restore").clicked() {
+                            op = Some(Operation::RestorePost(p.tid, p.post.id));
+                        }
+                        if ui.button("purge").clicked() {
+                            op = Some(Operation::PurgePost(p.tid, p.post.id));
+                        }
+                    });
+                }
+            });
+        });
+
+        if let Some(op) = op {
+            self.operate(rt, op);
+        }
+
+        event
+    }
+
+    fn reload(&mut self, rt: &runtime::Runtime) {
+        let store = self.store.clone();
+        let tx = self.tx.clone();
+        rt.spawn(async move {
+            let mut store = store.lock().await;
+            Self::send_trash(&mut **store, &tx).await;
+        });
+    }
+}
+
+impl TrashPane {
+    pub fn new(
+        store: Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
+        rt: &runtime::Runtime,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel::<Message>(32);
+
+        let mut pane = Self {
+            trash: Trash::default(),
+            store,
+            tx,
+            rx,
+        };
+
+        pane.reload(rt);
+
+        pane
+    }
+
+    /// Runs `op` and refreshes the listing afterwards.
+    fn operate(&self, rt: &runtime::Runtime, op: Operation) {
+        let store = self.store.clone();
+        let tx = self.tx.clone();
+        rt.spawn(async move {
+            let mut store = store.lock().await;
+
+            let result = match op {
+                Operation::RestoreTimes(tid) => {
+                    store.restore_times(tid).await.map(|_| ())
+                }
+                Operation::RestorePost(tid, pid) => {
+                    store.restore_post(tid, pid).await.map(|_| ())
+                }
+                Operation::PurgeTimes(tid) => store.purge_times(tid).await,
+                Operation::PurgePost(tid, pid) => {
+                    store.purge_post(tid, pid).await
+                }
+                Operation::PurgeTrash(before) => {
+                    store.purge_trash(before).await.map(|n| {
+                        info!(format!("purged {n} entries"));
+                    })
+                }
+            };
+
+            if let Err(e) = result {
+                Self::send(&tx, Message::Error(e.to_string())).await;
+            }
+
+            Self::send_trash(&mut **store, &tx).await;
+        });
+    }
+
+    async fn send_trash(
+        store: &mut (dyn Store + Send + Sync + 'static),
+        tx: &Sender<Message>,
+    ) {
+        let msg = match store.list_trash().await {
+            Ok(trash) => Message::Refresh(trash),
+            Err(StoreError::Unsupported(e)) => {
+                Message::Error(format!("this store has no trash: {e}"))
+            }
+            Err(e) => Message::Error(e.to_string()),
+        };
+
+        Self::send(tx, msg).await;
+    }
+
+    async fn send(tx: &Sender<Message>, msg: Message) {
+        if let Err(e) = tx.send(msg).await {
+            error!(format!("failed to send message: {e}"));
+        }
+    }
+
+    fn handle_message(&mut self) {
+        match self.rx.try_recv() {
+            Ok(msg) => match msg {
+                Message::Refresh(trash) => {
+                    debug!("found message which refresh the trash");
+                    self.trash = trash;
+                }
+                Message::Error(err) => {
+                    error!(err);
+                }
+            },
+            Err(_e) => {}
+        }
+    }
+}
@@ -0,0 +1,5 @@
+-- Add down migration script here
+drop trigger posts_fts_update;
+drop trigger posts_fts_delete;
+drop trigger posts_fts_insert;
+drop table posts_fts;
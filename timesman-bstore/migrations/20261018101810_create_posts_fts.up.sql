@@ -0,0 +1,24 @@
+-- Add up migration script here
+create virtual table posts_fts using fts5(
+  post,
+  content = 'posts',
+  content_rowid = 'id',
+  tokenize = 'trigram'
+);
+
+insert into posts_fts(posts_fts) values ('rebuild');
+
+create trigger posts_fts_insert after insert on posts begin
+  insert into posts_fts(rowid, post) values (new.id, new.post);
+end;
+
+create trigger posts_fts_delete after delete on posts begin
+  insert into posts_fts(posts_fts, rowid, post)
+    values ('delete', old.id, old.post);
+end;
+
+create trigger posts_fts_update after update of post on posts begin
+  insert into posts_fts(posts_fts, rowid, post)
+    values ('delete', old.id, old.post);
+  insert into posts_fts(rowid, post) values (new.id, new.post);
+end;
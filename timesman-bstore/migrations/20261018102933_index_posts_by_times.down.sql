@@ -0,0 +1,2 @@
+-- Add down migration script here
+drop index posts_tid_id;
@@ -0,0 +1,2 @@
+-- Add up migration script here
+create index posts_tid_id on posts(tid, id);
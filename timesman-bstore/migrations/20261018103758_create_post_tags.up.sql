@@ -0,0 +1,9 @@
+-- Add up migration script here
+create table post_tags (
+  pid integer not null,
+  tag text not null,
+  primary key (pid, tag),
+  foreign key(pid) references posts(id) on delete cascade
+);
+
+create index post_tags_tag on post_tags(tag);
@@ -0,0 +1,5 @@
+-- Add down migration script here
+-- trashed posts cannot be kept without the column
+delete from posts where deleted_at is not null;
+alter table posts drop column deleted_at;
+alter table times drop column deleted_at;
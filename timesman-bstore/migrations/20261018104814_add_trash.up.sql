@@ -0,0 +1,5 @@
+-- Add up migration script here
+alter table times add column deleted_at datetime;
+update times set deleted_at = coalesce(updated_at, created_at) where deleted = 1;
+
+alter table posts add column deleted_at datetime;
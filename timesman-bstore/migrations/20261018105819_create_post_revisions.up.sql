@@ -0,0 +1,9 @@
+-- Add up migration script here
+create table post_revisions (
+  pid integer not null,
+  rev integer not null,
+  post text not null,
+  created_at datetime not null,
+  primary key (pid, rev),
+  foreign key(pid) references posts(id) on delete cascade
+);
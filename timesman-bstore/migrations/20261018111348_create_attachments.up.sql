@@ -0,0 +1,18 @@
+-- Add up migration script here
+create table attachments (
+  id integer primary key autoincrement,
+  pid integer not null,
+  name text not null,
+  mime text not null,
+  size integer not null,
+  hash text not null,
+  created_at datetime not null DEFAULT CURRENT_TIMESTAMP,
+  foreign key(pid) references posts(id) on delete cascade
+);
+
+create index attachments_pid on attachments(pid);
+
+create table blobs (
+  hash text primary key not null,
+  data blob not null
+);
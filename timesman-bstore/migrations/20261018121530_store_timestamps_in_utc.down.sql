@@ -0,0 +1,20 @@
+-- Add down migration script here
+update times set created_at = replace(substr(created_at, 1, length(created_at) - 6), 'T', ' ')
+  where created_at like '%+00:00';
+update times set updated_at = replace(substr(updated_at, 1, length(updated_at) - 6), 'T', ' ')
+  where updated_at like '%+00:00';
+update times set deleted_at = replace(substr(deleted_at, 1, length(deleted_at) - 6), 'T', ' ')
+  where deleted_at like '%+00:00';
+
+update posts set created_at = replace(substr(created_at, 1, length(created_at) - 6), 'T', ' ')
+  where created_at like '%+00:00';
+update posts set updated_at = replace(substr(updated_at, 1, length(updated_at) - 6), 'T', ' ')
+  where updated_at like '%+00:00';
+update posts set deleted_at = replace(substr(deleted_at, 1, length(deleted_at) - 6), 'T', ' ')
+  where deleted_at like '%+00:00';
+
+update post_revisions set created_at = replace(substr(created_at, 1, length(created_at) - 6), 'T', ' ')
+  where created_at like '%+00:00';
+
+update attachments set created_at = replace(substr(created_at, 1, length(created_at) - 6), 'T', ' ')
+  where created_at like '%+00:00';
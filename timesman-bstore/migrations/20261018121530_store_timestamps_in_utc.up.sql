@@ -0,0 +1,22 @@
+-- Add up migration script here
+-- CURRENT_TIMESTAMP wrote UTC without an offset, e.g. 2024-11-23 22:53:05;
+-- store it as RFC 3339 with the offset, e.g. 2024-11-23T22:53:05+00:00
+update times set created_at = replace(created_at, ' ', 'T') || '+00:00'
+  where created_at not like '%+00:00';
+update times set updated_at = replace(updated_at, ' ', 'T') || '+00:00'
+  where updated_at not like '%+00:00';
+update times set deleted_at = replace(deleted_at, ' ', 'T') || '+00:00'
+  where deleted_at not like '%+00:00';
+
+update posts set created_at = replace(created_at, ' ', 'T') || '+00:00'
+  where created_at not like '%+00:00';
+update posts set updated_at = replace(updated_at, ' ', 'T') || '+00:00'
+  where updated_at not like '%+00:00';
+update posts set deleted_at = replace(deleted_at, ' ', 'T') || '+00:00'
+  where deleted_at not like '%+00:00';
+
+update post_revisions set created_at = replace(created_at, ' ', 'T') || '+00:00'
+  where created_at not like '%+00:00';
+
+update attachments set created_at = replace(created_at, ' ', 'T') || '+00:00'
+  where created_at not like '%+00:00';
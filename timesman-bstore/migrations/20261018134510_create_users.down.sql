@@ -0,0 +1,5 @@
+-- Add down migration script here
+drop index times_owner;
+alter table posts drop column author;
+alter table times drop column owner;
+drop table users;
@@ -0,0 +1,12 @@
+-- Add up migration script here
+create table users (
+  id integer primary key autoincrement,
+  name text not null unique,
+  created_at datetime not null
+);
+
+-- names of users; null for what was written before accounts existed
+alter table times add column owner text;
+alter table posts add column author text;
+
+create index times_owner on times(owner);
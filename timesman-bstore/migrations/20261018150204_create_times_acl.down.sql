@@ -0,0 +1,3 @@
+-- Add down migration script here
+alter table times drop column public;
+drop table times_acl;
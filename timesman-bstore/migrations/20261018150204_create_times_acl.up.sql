@@ -0,0 +1,11 @@
+-- Add up migration script here
+-- the readers and writers of each times besides its owner
+create table times_acl (
+  tid integer not null,
+  user text not null,
+  writer boolean not null,
+  primary key (tid, user),
+  foreign key(tid) references times(id) on delete cascade
+);
+
+alter table times add column public boolean not null default false;
@@ -1,100 +1,337 @@
-use super::Store;
+use std::pin::Pin;
+use std::time::Duration;
+
+use super::{PostPage, PostQuery, SearchFilter, Store, StoreError};
 use async_trait::async_trait;
+use tokio::sync::broadcast;
+use tokio_stream::{Stream, StreamExt};
 
 use timesman_grpc::grpc;
 use timesman_grpc::grpc::times_man_client::TimesManClient;
+use timesman_grpc::grpc::{download_chunk, upload_chunk};
+use timesman_grpc::{ClientTls, TokenInterceptor};
 use tonic;
+use tonic::service::interceptor::InterceptedService;
+
+use timesman_type::{
+    Acl, Attachment, Change, Post, Revision, Tag, Times, TimesPost, Trash,
+};
+
+/// Changes buffered between the watches and the subscribers.
+const CHANGE_BUFFER: usize = 256;
+
+/// How long to wait before resuming a broken watch.
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+impl From<timesman_grpc::ConvertError> for StoreError {
+    fn from(value: timesman_grpc::ConvertError) -> Self {
+        StoreError::Backend(format!("malformed response: {value}"))
+    }
+}
+
+impl From<StoreError> for tonic::Status {
+    fn from(value: StoreError) -> Self {
+        let code = match &value {
+            StoreError::NotFound(_) => tonic::Code::NotFound,
+            StoreError::InvalidArgument(_) => tonic::Code::InvalidArgument,
+            StoreError::Unsupported(_) => tonic::Code::Unimplemented,
+            StoreError::Conflict(_) => tonic::Code::Aborted,
+            StoreError::Backend(_) => tonic::Code::Internal,
+            StoreError::Unauthenticated(_) => tonic::Code::Unauthenticated,
+            StoreError::PermissionDenied(_) => tonic::Code::PermissionDenied,
+        };
+
+        tonic::Status::new(code, value.message())
+    }
+}
+
+impl From<tonic::Status> for StoreError {
+    fn from(value: tonic::Status) -> Self {
+        let msg = value.message().to_string();
+        match value.code() {
+            tonic::Code::NotFound => StoreError::NotFound(msg),
+            tonic::Code::InvalidArgument | tonic::Code::OutOfRange => {
+                StoreError::InvalidArgument(msg)
+            }
+            tonic::Code::Unimplemented => StoreError::Unsupported(msg),
+            tonic::Code::Aborted
+            | tonic::Code::AlreadyExists
+            | tonic::Code::FailedPrecondition => StoreError::Conflict(msg),
+            tonic::Code::Unauthenticated => StoreError::Unauthenticated(msg),
+            tonic::Code::PermissionDenied => StoreError::PermissionDenied(msg),
+            _ => StoreError::Backend(msg),
+        }
+    }
+}
 
-use timesman_type::{Post, Times};
+type Client = TimesManClient<
+    InterceptedService<tonic::transport::channel::Channel, TokenInterceptor>,
+>;
 
 pub struct GrpcStore {
-    client: TimesManClient<tonic::transport::channel::Channel>,
+    client: Client,
+}
+
+#[derive(Clone, Copy)]
+enum Watch {
+    Times,
+    Posts,
+}
+
+/// The tokens and changes of a watch. Events without a change only move
+/// the token forward.
+type ChangeStream = Pin<
+    Box<dyn Stream<Item = Result<(u64, Option<Change>), StoreError>> + Send>,
+>;
+
+/// Starts `kind` of watch of every times, right after `resume_token`.
+async fn watch(
+    client: &mut Client,
+    kind: Watch,
+    resume_token: Option<u64>,
+) -> Result<ChangeStream, StoreError> {
+    Ok(match kind {
+        Watch::Times => {
+            let param = grpc::WatchParam { resume_token };
+            let stream = client.watch_times(param).await?.into_inner();
+            Box::pin(stream.map(|e| {
+                let e = e?;
+                Ok((e.token, e.into_change()?))
+            }))
+        }
+        Watch::Posts => {
+            let param = grpc::WatchPostsParam {
+                tid: None,
+                resume_token,
+            };
+            let stream = client.watch_posts(param).await?.into_inner();
+            Box::pin(stream.map(|e| {
+                let e = e?;
+                Ok((e.token, e.into_change()?))
+            }))
+        }
+    })
+}
+
+/// Forwards the changes of a watch to `tx`, resuming it whenever the
+/// connection breaks, until every subscriber is gone or the server no
+/// longer has the changes to resume from.
+async fn follow(
+    mut client: Client,
+    kind: Watch,
+    mut stream: ChangeStream,
+    tx: broadcast::Sender<Change>,
+) {
+    let mut token = None;
+    loop {
+        while let Some(Ok((t, change))) = stream.next().await {
+            token = Some(t);
+            if let Some(change) = change {
+                if tx.send(change).is_err() {
+                    return;
+                }
+            }
+        }
+
+        stream = loop {
+            if tx.receiver_count() == 0 {
+                return;
+            }
+            tokio::time::sleep(RETRY_INTERVAL).await;
+            match watch(&mut client, kind, token).await {
+                Ok(stream) => break stream,
+                // the server reports a lost token as out of range
+                Err(StoreError::InvalidArgument(_)) => return,
+                Err(_) => continue,
+            }
+        };
+    }
 }
 
 impl GrpcStore {
-    pub async fn build(server: String) -> Self {
-        let client = TimesManClient::connect(server).await.unwrap();
-        Self { client }
+    /// Connects to the timesd at `server`.
+    pub async fn build(server: String) -> Result<Self, StoreError> {
+        GrpcStoreBuilder::new(server).build().await
+    }
+
+    /// Connects to the timesd at `server`, authenticating with `token`.
+    pub async fn build_with_token(
+        server: String,
+        token: Option<String>,
+    ) -> Result<Self, StoreError> {
+        let mut builder = GrpcStoreBuilder::new(server);
+        if let Some(token) = token {
+            builder = builder.token(token);
+        }
+        builder.build().await
+    }
+}
+
+pub struct GrpcStoreBuilder {
+    server: String,
+    token: Option<String>,
+    tls: Option<ClientTls>,
+}
+
+impl GrpcStoreBuilder {
+    pub fn new(server: String) -> Self {
+        Self {
+            server,
+            token: None,
+            tls: None,
+        }
+    }
+
+    /// Authenticates every request with `token`.
+    pub fn token(mut self, token: String) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Connects over TLS with `tls`, e.g. to trust a private CA or to
+    /// present a client certificate. `https` servers are otherwise verified
+    /// against the system roots.
+    pub fn tls(mut self, tls: ClientTls) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    pub async fn build(&self) -> Result<GrpcStore, StoreError> {
+        let interceptor = TokenInterceptor::new(self.token.as_deref())
+            .map_err(|e| StoreError::InvalidArgument(format!("token: {e}")))?;
+        let mut endpoint = tonic::transport::Endpoint::new(self.server.clone())
+            .map_err(|e| StoreError::InvalidArgument(format!("{e}")))?;
+        if let Some(tls) = &self.tls {
+            let config = tls.config().map_err(StoreError::InvalidArgument)?;
+            endpoint = endpoint
+                .tls_config(config)
+                .map_err(|e| StoreError::InvalidArgument(format!("{e}")))?;
+        }
+        let channel = endpoint
+            .connect()
+            .await
+            .map_err(|e| StoreError::Backend(format!("{e}")))?;
+
+        Ok(GrpcStore {
+            client: TimesManClient::with_interceptor(channel, interceptor),
+        })
     }
 }
 
 #[async_trait]
 impl Store for GrpcStore {
-    async fn check(&mut self) -> Result<(), String> {
+    async fn check(&mut self) -> Result<(), StoreError> {
         self.get_times().await?;
         Ok(())
     }
 
     // for Times
-    async fn get_times(&mut self) -> Result<Vec<timesman_type::Times>, String> {
-        let gtimes = self
-            .client
-            .get_times(())
-            .await
-            .map_err(|e| format!("{e}"))?;
+    async fn get_times(
+        &mut self,
+    ) -> Result<Vec<timesman_type::Times>, StoreError> {
+        let gtimes =
+            self.client.get_times(()).await.map_err(StoreError::from)?;
 
         let times = gtimes
             .into_inner()
             .timeses
             .iter()
-            .map(|t| t.clone().into())
-            .collect();
+            .map(|t| t.clone().try_into())
+            .collect::<Result<_, _>>()?;
         Ok(times)
     }
 
-    async fn create_times(&mut self, title: String) -> Result<Times, String> {
+    async fn create_times(
+        &mut self,
+        title: String,
+    ) -> Result<Times, StoreError> {
         let title = grpc::TimesTitle { title };
         let times = self
             .client
             .create_times(tonic::Request::new(title))
             .await
-            .map_err(|e| format!("{e}"))?;
-        Ok(times.into_inner().into())
+            .map_err(StoreError::from)?;
+        Ok(times.into_inner().try_into()?)
     }
 
-    async fn delete_times(&mut self, tid: u64) -> Result<(), String> {
+    async fn delete_times(&mut self, tid: u64) -> Result<(), StoreError> {
         let id = grpc::TimesId { id: tid };
         self.client
             .delete_times(tonic::Request::new(id))
             .await
-            .map_err(|e| format!("{e}"))?;
+            .map_err(StoreError::from)?;
 
         Ok(())
     }
 
-    async fn update_times(&mut self, times: Times) -> Result<Times, String> {
+    async fn update_times(
+        &mut self,
+        times: Times,
+    ) -> Result<Times, StoreError> {
         let times = self
             .client
             .update_times(tonic::Request::new(times.into()))
             .await
-            .map_err(|e| format!("{e}"))?;
+            .map_err(StoreError::from)?;
 
-        Ok(times.into_inner().into())
+        Ok(times.into_inner().try_into()?)
     }
 
     // for Post
-    async fn get_posts(&mut self, tid: u64) -> Result<Vec<Post>, String> {
-        let tid = grpc::TimesId { id: tid };
+    async fn get_posts(&mut self, tid: u64) -> Result<Vec<Post>, StoreError> {
+        let param = grpc::GetPostsParam {
+            id: tid,
+            ..Default::default()
+        };
         let posts = self
             .client
-            .get_posts(tonic::Request::new(tid))
+            .get_posts(tonic::Request::new(param))
             .await
-            .map_err(|e| format!("{e}"))?;
+            .map_err(StoreError::from)?;
 
         let posts = posts
             .into_inner()
             .posts
             .iter()
-            .map(|t| t.clone().into())
-            .collect();
+            .map(|t| t.clone().try_into())
+            .collect::<Result<_, _>>()?;
         Ok(posts)
     }
 
+    async fn get_posts_page(
+        &mut self,
+        tid: u64,
+        query: PostQuery,
+    ) -> Result<PostPage, StoreError> {
+        let param = grpc::GetPostsParam {
+            id: tid,
+            cursor: query.cursor,
+            since: query.since.map(timesman_grpc::timestamp),
+            until: query.until.map(timesman_grpc::timestamp),
+            limit: query.limit.map(|l| l as u64),
+        };
+        let page = self
+            .client
+            .get_posts(tonic::Request::new(param))
+            .await
+            .map_err(StoreError::from)?
+            .into_inner();
+
+        Ok(PostPage {
+            posts: page
+                .posts
+                .into_iter()
+                .map(|p| p.try_into())
+                .collect::<Result<_, _>>()?,
+            next: page.next,
+        })
+    }
+
     async fn create_post(
         &mut self,
         tid: u64,
         post: String,
-    ) -> Result<Post, String> {
+    ) -> Result<Post, StoreError> {
         let param = grpc::CreatePostPrams {
             id: tid,
             text: post,
@@ -103,18 +340,22 @@ impl Store for GrpcStore {
             .client
             .create_post(tonic::Request::new(param))
             .await
-            .map_err(|e| format!("{e}"))?;
+            .map_err(StoreError::from)?;
 
-        Ok(post.into_inner().into())
+        Ok(post.into_inner().try_into()?)
     }
 
-    async fn delete_post(&mut self, tid: u64, pid: u64) -> Result<(), String> {
-        let param = grpc::DeletePostParam { tid: tid, pid: pid };
+    async fn delete_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<(), StoreError> {
+        let param = grpc::DeletePostParam { tid, pid };
 
         self.client
             .delete_post(tonic::Request::new(param))
             .await
-            .map_err(|e| format!("{e}"))?;
+            .map_err(StoreError::from)?;
         Ok(())
     }
 
@@ -122,7 +363,7 @@ impl Store for GrpcStore {
         &mut self,
         tid: u64,
         post: Post,
-    ) -> Result<Post, String> {
+    ) -> Result<Post, StoreError> {
         let param = grpc::UpdatePostParam {
             tid,
             post: Some(post.into()),
@@ -132,15 +373,355 @@ impl Store for GrpcStore {
             .client
             .update_post(tonic::Request::new(param))
             .await
-            .map_err(|e| format!("{e}"))?;
+            .map_err(StoreError::from)?;
 
-        Ok(post.into_inner().into())
+        Ok(post.into_inner().try_into()?)
     }
 
     async fn get_latest_post(
         &mut self,
         tid: u64,
-    ) -> Result<Option<Post>, String> {
-        Err("unimplemented".to_string())
+    ) -> Result<Option<Post>, StoreError> {
+        // the newest page of a single post
+        let query = PostQuery {
+            limit: Some(1),
+            ..Default::default()
+        };
+        let page = self.get_posts_page(tid, query).await?;
+
+        Ok(page.posts.into_iter().next())
+    }
+
+    async fn list_revisions(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<Vec<Revision>, StoreError> {
+        let key = grpc::PostKey { tid, pid };
+        let revisions = self
+            .client
+            .list_revisions(tonic::Request::new(key))
+            .await
+            .map_err(StoreError::from)?;
+
+        Ok(revisions
+            .into_inner()
+            .revisions
+            .into_iter()
+            .map(|r| r.try_into())
+            .collect::<Result<_, _>>()?)
+    }
+
+    async fn get_revision(
+        &mut self,
+        tid: u64,
+        pid: u64,
+        rev: u64,
+    ) -> Result<Revision, StoreError> {
+        let key = grpc::RevisionKey { tid, pid, rev };
+        let revision = self
+            .client
+            .get_revision(tonic::Request::new(key))
+            .await
+            .map_err(StoreError::from)?;
+
+        Ok(revision.into_inner().try_into()?)
+    }
+
+    async fn revert_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+        rev: u64,
+    ) -> Result<Post, StoreError> {
+        let key = grpc::RevisionKey { tid, pid, rev };
+        let post = self
+            .client
+            .revert_post(tonic::Request::new(key))
+            .await
+            .map_err(StoreError::from)?;
+
+        Ok(post.into_inner().try_into()?)
+    }
+
+    async fn attach(
+        &mut self,
+        tid: u64,
+        pid: u64,
+        name: String,
+        mime: String,
+        data: Vec<u8>,
+    ) -> Result<Attachment, StoreError> {
+        let header = upload_chunk::Chunk::Header(grpc::AttachmentHeader {
+            tid,
+            pid,
+            name,
+            mime,
+        });
+        let chunks: Vec<grpc::UploadChunk> = std::iter::once(header)
+            .chain(
+                data.chunks(timesman_grpc::CHUNK_SIZE)
+                    .map(|c| upload_chunk::Chunk::Data(c.to_vec())),
+            )
+            .map(|chunk| grpc::UploadChunk { chunk: Some(chunk) })
+            .collect();
+
+        let attachment = self
+            .client
+            .upload_attachment(tokio_stream::iter(chunks))
+            .await
+            .map_err(StoreError::from)?;
+
+        Ok(attachment.into_inner().try_into()?)
+    }
+
+    async fn list_attachments(
+        &mut self,
+        tid: u64,
+    ) -> Result<Vec<Attachment>, StoreError> {
+        let id = grpc::TimesId { id: tid };
+        let attachments = self
+            .client
+            .list_attachments(tonic::Request::new(id))
+            .await
+            .map_err(StoreError::from)?;
+
+        Ok(attachments
+            .into_inner()
+            .attachments
+            .into_iter()
+            .map(|a| a.try_into())
+            .collect::<Result<_, _>>()?)
+    }
+
+    async fn read_attachment(
+        &mut self,
+        tid: u64,
+        aid: u64,
+    ) -> Result<Vec<u8>, StoreError> {
+        let key = grpc::AttachmentKey { tid, id: aid };
+        let mut stream = self
+            .client
+            .download_attachment(tonic::Request::new(key))
+            .await
+            .map_err(StoreError::from)?
+            .into_inner();
+
+        let mut data = vec![];
+        while let Some(chunk) = stream.message().await? {
+            if let Some(download_chunk::Chunk::Data(bytes)) = chunk.chunk {
+                data.extend_from_slice(&bytes);
+            }
+        }
+
+        Ok(data)
+    }
+
+    async fn detach(&mut self, tid: u64, aid: u64) -> Result<(), StoreError> {
+        let key = grpc::AttachmentKey { tid, id: aid };
+        self.client
+            .delete_attachment(tonic::Request::new(key))
+            .await
+            .map_err(StoreError::from)?;
+
+        Ok(())
+    }
+
+    async fn search_posts(
+        &mut self,
+        query: String,
+        filter: SearchFilter,
+    ) -> Result<Vec<TimesPost>, StoreError> {
+        let param = grpc::SearchPostsParam {
+            query,
+            tid: filter.tid,
+            since: filter.since.map(timesman_grpc::timestamp),
+            until: filter.until.map(timesman_grpc::timestamp),
+            limit: filter.limit.map(|l| l as u64),
+        };
+
+        let posts = self
+            .client
+            .search_posts(tonic::Request::new(param))
+            .await
+            .map_err(StoreError::from)?;
+
+        Ok(posts
+            .into_inner()
+            .posts
+            .into_iter()
+            .map(|p| p.try_into())
+            .collect::<Result<_, _>>()?)
+    }
+
+    async fn get_acl(&mut self, tid: u64) -> Result<Acl, StoreError> {
+        let acl = self
+            .client
+            .get_acl(tonic::Request::new(grpc::TimesId { id: tid }))
+            .await
+            .map_err(StoreError::from)?;
+
+        Ok(acl.into_inner().into())
+    }
+
+    async fn set_acl(&mut self, tid: u64, acl: Acl) -> Result<Acl, StoreError> {
+        let param = grpc::SetAclParam {
+            id: tid,
+            acl: Some(acl.into()),
+        };
+
+        let acl = self
+            .client
+            .set_acl(tonic::Request::new(param))
+            .await
+            .map_err(StoreError::from)?;
+
+        Ok(acl.into_inner().into())
+    }
+
+    async fn list_tags(&mut self) -> Result<Vec<Tag>, StoreError> {
+        let tags = self.client.list_tags(()).await.map_err(StoreError::from)?;
+
+        Ok(tags
+            .into_inner()
+            .tags
+            .into_iter()
+            .map(|t| t.into())
+            .collect())
+    }
+
+    async fn get_posts_by_tag(
+        &mut self,
+        tag: String,
+    ) -> Result<Vec<TimesPost>, StoreError> {
+        let param = grpc::TagName { name: tag };
+        let posts = self
+            .client
+            .get_posts_by_tag(tonic::Request::new(param))
+            .await
+            .map_err(StoreError::from)?;
+
+        Ok(posts
+            .into_inner()
+            .posts
+            .into_iter()
+            .map(|p| p.try_into())
+            .collect::<Result<_, _>>()?)
+    }
+
+    async fn list_trash(&mut self) -> Result<Trash, StoreError> {
+        let trash =
+            self.client.list_trash(()).await.map_err(StoreError::from)?;
+
+        Ok(trash.into_inner().try_into()?)
+    }
+
+    async fn restore_times(&mut self, tid: u64) -> Result<Times, StoreError> {
+        let id = grpc::TimesId { id: tid };
+        let times = self
+            .client
+            .restore_times(tonic::Request::new(id))
+            .await
+            .map_err(StoreError::from)?;
+
+        Ok(times.into_inner().try_into()?)
+    }
+
+    async fn restore_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<Post, StoreError> {
+        let key = grpc::PostKey { tid, pid };
+        let post = self
+            .client
+            .restore_post(tonic::Request::new(key))
+            .await
+            .map_err(StoreError::from)?;
+
+        Ok(post.into_inner().try_into()?)
+    }
+
+    async fn purge_times(&mut self, tid: u64) -> Result<(), StoreError> {
+        let id = grpc::TimesId { id: tid };
+        self.client
+            .purge_times(tonic::Request::new(id))
+            .await
+            .map_err(StoreError::from)?;
+
+        Ok(())
+    }
+
+    async fn purge_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<(), StoreError> {
+        let key = grpc::PostKey { tid, pid };
+        self.client
+            .purge_post(tonic::Request::new(key))
+            .await
+            .map_err(StoreError::from)?;
+
+        Ok(())
+    }
+
+    async fn purge_trash(
+        &mut self,
+        before: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<u64, StoreError> {
+        let param = grpc::PurgeTrashParam {
+            before: before.map(timesman_grpc::timestamp),
+        };
+        let count = self
+            .client
+            .purge_trash(tonic::Request::new(param))
+            .await
+            .map_err(StoreError::from)?;
+
+        Ok(count.into_inner().count)
+    }
+
+    async fn subscribe(
+        &mut self,
+    ) -> Result<broadcast::Receiver<Change>, StoreError> {
+        let times = watch(&mut self.client, Watch::Times, None).await?;
+        let posts = watch(&mut self.client, Watch::Posts, None).await?;
+
+        let (tx, rx) = broadcast::channel(CHANGE_BUFFER);
+        let times =
+            follow(self.client.clone(), Watch::Times, times, tx.clone());
+        let posts = follow(self.client.clone(), Watch::Posts, posts, tx);
+        tokio::spawn(async move {
+            // giving up either watch closes the channel, so that the
+            // subscribers know they missed changes
+            tokio::select! {
+                _ = times => {}
+                _ = posts => {}
+            }
+        });
+
+        Ok(rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_error_survives_status_round_trip() {
+        let errors = [
+            StoreError::NotFound("times 1".to_string()),
+            StoreError::InvalidArgument("empty title".to_string()),
+            StoreError::Unsupported("search".to_string()),
+            StoreError::Conflict("stale".to_string()),
+            StoreError::Backend("disk".to_string()),
+        ];
+
+        for e in errors {
+            let status = tonic::Status::from(e.clone());
+            assert_eq!(StoreError::from(status), e);
+        }
     }
 }
@@ -1,133 +1,779 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::{BufWriter, Write};
-use std::{fs::File, path::PathBuf};
+use std::{fs::File, path::Path, path::PathBuf};
 
-use super::{Post, Store, Times};
+use super::{
+    check_user_name, count_tags, normalize_acl, normalize_tag, scan_posts,
+    sort_newest_first, Post, SearchFilter, Store, StoreError, Times,
+};
 use async_trait::async_trait;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use timesman_type::{
+    Acl, Tag, TimesPost, Trash, TrashedPost, TrashedTimes, User,
+};
 
+/// Version of the file layout written by this binary.
+const FORMAT_VERSION: u64 = 3;
+
+/// The first version whose timestamps are in UTC. Earlier versions wrote
+/// them in local time without an offset.
+const UTC_VERSION: u64 = 3;
+
+/// The fields holding a timestamp, at any depth of the file.
+const TIMESTAMP_FIELDS: [&str; 3] = ["created_at", "updated_at", "deleted_at"];
+
+/// A store which keeps every times and post in a single JSON file.
+///
+/// The file is rewritten after every mutation, which only takes effect once
+/// the file is written. A store created by
+/// [`JsonStore::new`] has no file and only lives in memory until
+/// [`JsonStore::save_to_file`] is called.
 pub struct JsonStore {
+    path: Option<PathBuf>,
     data: Data,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 struct Data {
+    version: u64,
+    next_tid: u64,
+    next_pid: u64,
+    times: Vec<JsonTimes>,
+    #[serde(default)]
+    next_uid: u64,
+    #[serde(default)]
+    users: Vec<User>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct JsonTimes {
+    times: Times,
+    posts: Vec<Post>,
+    /// Set while the times is in the trash.
+    #[serde(default)]
+    deleted_at: Option<DateTime<Utc>>,
+    /// Deleted posts.
+    #[serde(default)]
+    trash: Vec<JsonTrashedPost>,
+    /// Who may see and change the times besides its owner.
+    #[serde(default)]
+    acl: Acl,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct JsonTrashedPost {
+    post: Post,
+    deleted_at: DateTime<Utc>,
+}
+
+impl JsonTimes {
+    fn new(times: Times, posts: Vec<Post>) -> Self {
+        Self {
+            times,
+            posts,
+            deleted_at: None,
+            trash: vec![],
+            acl: Acl::default(),
+        }
+    }
+}
+
+/// The layout written before the format was versioned: exactly one times.
+#[derive(Deserialize)]
+struct LegacyData {
     times: Times,
     posts: Vec<Post>,
 }
 
+impl Data {
+    fn new(times: Vec<JsonTimes>) -> Self {
+        let next_tid = times.iter().map(|t| t.times.id + 1).max().unwrap_or(0);
+        let next_pid = times
+            .iter()
+            .flat_map(|t| t.posts.iter().chain(t.trash.iter().map(|p| &p.post)))
+            .map(|p| p.id + 1)
+            .max()
+            .unwrap_or(0);
+
+        Self {
+            version: FORMAT_VERSION,
+            next_tid,
+            next_pid,
+            times,
+            next_uid: 0,
+            users: vec![],
+        }
+    }
+
+    fn find_user(&self, name: &str) -> Result<&User, StoreError> {
+        self.users
+            .iter()
+            .find(|u| u.name == name)
+            .ok_or(StoreError::NotFound(format!("user {name}")))
+    }
+
+    /// The times which are not in the trash.
+    fn live(&self) -> impl Iterator<Item = &JsonTimes> {
+        self.times.iter().filter(|t| t.deleted_at.is_none())
+    }
+
+    fn find(&self, tid: u64) -> Result<&JsonTimes, StoreError> {
+        self.live()
+            .find(|t| t.times.id == tid)
+            .ok_or(StoreError::NotFound(format!("times {tid}")))
+    }
+
+    fn find_mut(&mut self, tid: u64) -> Result<&mut JsonTimes, StoreError> {
+        self.times
+            .iter_mut()
+            .find(|t| t.times.id == tid && t.deleted_at.is_none())
+            .ok_or(StoreError::NotFound(format!("times {tid}")))
+    }
+
+    /// Looks up a times, whether it is in the trash or not.
+    fn find_any_mut(&mut self, tid: u64) -> Result<&mut JsonTimes, StoreError> {
+        self.times
+            .iter_mut()
+            .find(|t| t.times.id == tid)
+            .ok_or(StoreError::NotFound(format!("times {tid}")))
+    }
+}
+
 #[async_trait]
 impl Store for JsonStore {
-    async fn check(&mut self) -> Result<(), String> {
+    async fn check(&mut self) -> Result<(), StoreError> {
         Ok(())
     }
 
-    async fn get_times(&mut self) -> Result<Vec<super::Times>, String> {
-        Ok(vec![self.data.times.clone()])
+    async fn get_times(&mut self) -> Result<Vec<super::Times>, StoreError> {
+        Ok(self.data.live().map(|t| t.times.clone()).collect())
     }
 
     async fn create_times(
         &mut self,
-        _title: String,
-    ) -> Result<super::Times, String> {
-        Err("not supported to create times".to_string())
+        title: String,
+    ) -> Result<super::Times, StoreError> {
+        self.insert_times(title, None)
     }
 
-    async fn delete_times(&mut self, _tid: u64) -> Result<(), String> {
-        Err("not supported to delete times".to_string())
+    async fn delete_times(&mut self, tid: u64) -> Result<(), StoreError> {
+        self.change(|data| {
+            data.find_mut(tid)?.deleted_at = Some(Utc::now());
+            Ok(())
+        })
     }
 
     async fn update_times(
         &mut self,
-        _times: super::Times,
-    ) -> Result<Times, String> {
-        Err("not supported to update times".to_string())
+        times: super::Times,
+    ) -> Result<Times, StoreError> {
+        self.change(|data| {
+            let jtimes = data.find_mut(times.id)?;
+
+            jtimes.times.title = times.title;
+            jtimes.times.updated_at = Some(Utc::now());
+            Ok(jtimes.times.clone())
+        })
     }
 
     async fn get_posts(
         &mut self,
         tid: u64,
-    ) -> Result<Vec<super::Post>, String> {
-        if self.data.times.id != tid {
-            return Err("unknown tid found".to_string());
-        }
-
-        Ok(self.data.posts.clone())
+    ) -> Result<Vec<super::Post>, StoreError> {
+        Ok(self.data.find(tid)?.posts.clone())
     }
 
     async fn create_post(
         &mut self,
         tid: u64,
-        _post: String,
-    ) -> Result<super::Post, String> {
-        if self.data.times.id != tid {
-            return Err("unknown tid found".to_string());
-        }
-
-        Err("not supported to create post".to_string())
+        post: String,
+    ) -> Result<super::Post, StoreError> {
+        self.insert_post(tid, post, None)
     }
 
     async fn update_post(
         &mut self,
         tid: u64,
-        mut _post: super::Post,
-    ) -> Result<super::Post, String> {
-        if self.data.times.id != tid {
-            return Err("unknown tid found".to_string());
+        post: super::Post,
+    ) -> Result<super::Post, StoreError> {
+        self.change(|data| {
+            let old = data
+                .find_mut(tid)?
+                .posts
+                .iter_mut()
+                .find(|p| p.id == post.id)
+                .ok_or(StoreError::NotFound(format!("post {}", post.id)))?;
+
+            old.post = post.post;
+            old.updated_at = Some(Utc::now());
+            Ok(old.clone())
+        })
+    }
+
+    async fn delete_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<(), StoreError> {
+        self.change(|data| {
+            let jtimes = data.find_mut(tid)?;
+            let Some(pos) = jtimes.posts.iter().position(|p| p.id == pid)
+            else {
+                return Err(StoreError::NotFound(format!("post {pid}")));
+            };
+
+            let post = jtimes.posts.remove(pos);
+            jtimes.trash.push(JsonTrashedPost {
+                post,
+                deleted_at: Utc::now(),
+            });
+            Ok(())
+        })
+    }
+
+    async fn get_latest_post(
+        &mut self,
+        tid: u64,
+    ) -> Result<Option<Post>, StoreError> {
+        let jtimes = self.data.find(tid)?;
+
+        Ok(jtimes.posts.iter().max_by_key(|p| p.id).cloned())
+    }
+
+    async fn search_posts(
+        &mut self,
+        query: String,
+        filter: SearchFilter,
+    ) -> Result<Vec<TimesPost>, StoreError> {
+        let posts = self.data.live().flat_map(|jtimes| {
+            jtimes.posts.iter().map(|p| (jtimes.times.id, p))
+        });
+
+        scan_posts(&query, &filter, posts)
+    }
+
+    async fn list_tags(&mut self) -> Result<Vec<Tag>, StoreError> {
+        let posts = self.data.live().flat_map(|t| t.posts.iter());
+
+        Ok(count_tags(posts))
+    }
+
+    async fn get_posts_by_tag(
+        &mut self,
+        tag: String,
+    ) -> Result<Vec<TimesPost>, StoreError> {
+        let tag = normalize_tag(&tag)?;
+
+        let mut posts: Vec<TimesPost> = self
+            .data
+            .live()
+            .flat_map(|jtimes| {
+                jtimes.posts.iter().map(|p| TimesPost {
+                    tid: jtimes.times.id,
+                    post: p.clone(),
+                })
+            })
+            .filter(|tp| tp.post.tags().contains(&tag))
+            .collect();
+        sort_newest_first(&mut posts);
+
+        Ok(posts)
+    }
+
+    async fn list_trash(&mut self) -> Result<Trash, StoreError> {
+        let mut times: Vec<TrashedTimes> = self
+            .data
+            .times
+            .iter()
+            .filter_map(|jtimes| {
+                Some(TrashedTimes {
+                    times: jtimes.times.clone(),
+                    deleted_at: jtimes.deleted_at?,
+                })
+            })
+            .collect();
+        times.sort_by(|a, b| {
+            (b.deleted_at, b.times.id).cmp(&(a.deleted_at, a.times.id))
+        });
+
+        let mut posts: Vec<TrashedPost> = self
+            .data
+            .live()
+            .flat_map(|jtimes| {
+                jtimes.trash.iter().map(|p| TrashedPost {
+                    tid: jtimes.times.id,
+                    post: p.post.clone(),
+                    deleted_at: p.deleted_at,
+                })
+            })
+            .collect();
+        posts.sort_by(|a, b| {
+            (b.deleted_at, b.post.id).cmp(&(a.deleted_at, a.post.id))
+        });
+
+        Ok(Trash { times, posts })
+    }
+
+    async fn restore_times(&mut self, tid: u64) -> Result<Times, StoreError> {
+        self.change(|data| {
+            let jtimes = data
+                .times
+                .iter_mut()
+                .find(|t| t.times.id == tid && t.deleted_at.is_some())
+                .ok_or(StoreError::NotFound(format!(
+                    "times {tid} in the trash"
+                )))?;
+
+            jtimes.deleted_at = None;
+            Ok(jtimes.times.clone())
+        })
+    }
+
+    async fn restore_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<Post, StoreError> {
+        self.change(|data| {
+            let jtimes = data.find_any_mut(tid)?;
+            if jtimes.deleted_at.is_some() {
+                return Err(StoreError::Conflict(format!(
+                    "times {tid} is in the trash"
+                )));
+            }
+
+            let Some(pos) = jtimes.trash.iter().position(|p| p.post.id == pid)
+            else {
+                return Err(StoreError::NotFound(format!(
+                    "post {pid} in the trash"
+                )));
+            };
+
+            let post = jtimes.trash.remove(pos).post;
+            jtimes.posts.push(post.clone());
+            jtimes.posts.sort_by_key(|p| p.id);
+            Ok(post)
+        })
+    }
+
+    async fn purge_times(&mut self, tid: u64) -> Result<(), StoreError> {
+        self.change(|data| {
+            let len = data.times.len();
+            data.times
+                .retain(|t| t.times.id != tid || t.deleted_at.is_none());
+
+            if data.times.len() == len {
+                return Err(StoreError::NotFound(format!(
+                    "times {tid} in the trash"
+                )));
+            }
+            Ok(())
+        })
+    }
+
+    async fn purge_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<(), StoreError> {
+        self.change(|data| {
+            let jtimes = data.find_any_mut(tid)?;
+
+            let len = jtimes.trash.len();
+            jtimes.trash.retain(|p| p.post.id != pid);
+
+            if jtimes.trash.len() == len {
+                return Err(StoreError::NotFound(format!(
+                    "post {pid} in the trash"
+                )));
+            }
+            Ok(())
+        })
+    }
+
+    async fn purge_trash(
+        &mut self,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<u64, StoreError> {
+        let expired = |deleted_at: &DateTime<Utc>| {
+            before.is_none_or(|before| *deleted_at < before)
+        };
+
+        let mut data = self.data.clone();
+        let len = data.times.len();
+        data.times
+            .retain(|t| !t.deleted_at.as_ref().is_some_and(expired));
+        let mut purged = len - data.times.len();
+
+        for jtimes in data.times.iter_mut() {
+            let len = jtimes.trash.len();
+            jtimes.trash.retain(|p| !expired(&p.deleted_at));
+            purged += len - jtimes.trash.len();
+        }
+
+        if purged > 0 {
+            self.replace(data)?;
         }
 
-        Err("not supported to update post".to_string())
+        Ok(purged as u64)
     }
 
-    async fn delete_post(&mut self, tid: u64, _pid: u64) -> Result<(), String> {
-        if self.data.times.id != tid {
-            return Err("unknown tid found".to_string());
+    async fn create_user(&mut self, name: String) -> Result<User, StoreError> {
+        check_user_name(&name)?;
+        if self.data.find_user(&name).is_ok() {
+            return Err(StoreError::Conflict(format!("user {name} exists")));
         }
 
-        Err("not supported to delete post".to_string())
+        self.change(|data| {
+            let user = User {
+                id: data.next_uid,
+                name,
+                created_at: Utc::now(),
+            };
+            data.next_uid += 1;
+            data.users.push(user.clone());
+            Ok(user)
+        })
     }
 
-    async fn get_latest_post(
+    async fn list_users(&mut self) -> Result<Vec<User>, StoreError> {
+        Ok(self.data.users.clone())
+    }
+
+    async fn create_times_for(
+        &mut self,
+        owner: String,
+        title: String,
+    ) -> Result<Times, StoreError> {
+        self.data.find_user(&owner)?;
+        self.insert_times(title, Some(owner))
+    }
+
+    async fn create_post_by(
         &mut self,
-        _tid: u64,
-    ) -> Result<Option<Post>, String> {
-        Ok(None)
+        tid: u64,
+        author: String,
+        post: String,
+    ) -> Result<Post, StoreError> {
+        self.data.find_user(&author)?;
+        self.insert_post(tid, post, Some(author))
+    }
+
+    async fn get_acl(&mut self, tid: u64) -> Result<Acl, StoreError> {
+        let jtimes = self.data.find_any_mut(tid)?;
+        Ok(Acl {
+            owner: jtimes.times.owner.clone(),
+            ..jtimes.acl.clone()
+        })
+    }
+
+    async fn set_acl(&mut self, tid: u64, acl: Acl) -> Result<Acl, StoreError> {
+        let owner = self.data.find_any_mut(tid)?.times.owner.clone();
+        let acl = normalize_acl(acl, owner)?;
+        for name in acl.readers.iter().chain(&acl.writers) {
+            self.data.find_user(name)?;
+        }
+
+        self.change(|data| {
+            data.find_any_mut(tid)?.acl = acl.clone();
+            Ok(acl)
+        })
     }
 }
 
 impl JsonStore {
+    fn insert_times(
+        &mut self,
+        title: String,
+        owner: Option<String>,
+    ) -> Result<Times, StoreError> {
+        self.change(|data| {
+            let times = Times {
+                id: data.next_tid,
+                title,
+                owner,
+                created_at: Utc::now(),
+                updated_at: None,
+            };
+
+            data.next_tid += 1;
+            data.times.push(JsonTimes::new(times.clone(), vec![]));
+            Ok(times)
+        })
+    }
+
+    fn insert_post(
+        &mut self,
+        tid: u64,
+        post: String,
+        author: Option<String>,
+    ) -> Result<Post, StoreError> {
+        self.change(|data| {
+            let post = Post {
+                id: data.next_pid,
+                post,
+                author,
+                created_at: Utc::now(),
+                updated_at: None,
+            };
+            data.find_mut(tid)?.posts.push(post.clone());
+
+            data.next_pid += 1;
+            Ok(post)
+        })
+    }
+
+    /// Applies `f` to a copy of the data, which replaces the data only once
+    /// it is saved, so a failed save leaves the store as it was.
+    fn change<T>(
+        &mut self,
+        f: impl FnOnce(&mut Data) -> Result<T, StoreError>,
+    ) -> Result<T, StoreError> {
+        let mut data = self.data.clone();
+        let value = f(&mut data)?;
+        self.replace(data)?;
+
+        Ok(value)
+    }
+
+    /// Saves `data` and keeps it if that succeeded.
+    fn replace(&mut self, data: Data) -> Result<(), StoreError> {
+        if let Some(path) = &self.path {
+            write_data(&data, path)?;
+        }
+        self.data = data;
+
+        Ok(())
+    }
+
+    /// Creates an in-memory store holding one times, e.g. for exporting it.
     pub fn new(times: Times, posts: Vec<Post>) -> Self {
         Self {
-            data: Data { times, posts },
+            path: None,
+            data: Data::new(vec![JsonTimes::new(times, posts)]),
+        }
+    }
+
+    /// Opens the store backed by `path`, creating the file if it is missing.
+    pub fn build(path: PathBuf) -> Result<Self, StoreError> {
+        if !path.exists() {
+            let data = Data::new(vec![]);
+            write_data(&data, &path)?;
+            return Ok(Self {
+                path: Some(path),
+                data,
+            });
         }
+
+        let data = Self::load_from_file(&path)?;
+
+        Ok(Self {
+            path: Some(path),
+            data,
+        })
     }
 
-    pub fn build(path: PathBuf) -> Result<Self, String> {
-        let data = Self::load_from_file(path)?;
+    pub fn save_to_file(&self, filepath: &Path) -> Result<(), StoreError> {
+        write_data(&self.data, filepath)
+    }
+
+    fn load_from_file(filepath: &Path) -> Result<Data, StoreError> {
+        let content = fs::read_to_string(filepath)
+            .map_err(|e| StoreError::Backend(format!("{e}")))?;
+
+        let mut value: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| StoreError::Backend(format!("{e}")))?;
 
-        Ok(Self { data })
+        let version = value.get("version").and_then(|v| v.as_u64());
+        if version.is_none_or(|v| v < UTC_VERSION) {
+            local_timestamps_to_utc(&mut value);
+        }
+
+        let data = match version {
+            Some(version) if version > FORMAT_VERSION => {
+                return Err(StoreError::Backend(format!(
+                    "json format version {version} is newer than \
+                     the supported version {FORMAT_VERSION}"
+                )));
+            }
+            Some(_) => serde_json::from_value::<Data>(value),
+            None => serde_json::from_value::<LegacyData>(value)
+                .map(|d| Data::new(vec![JsonTimes::new(d.times, d.posts)])),
+        };
+
+        data.map_err(|e| StoreError::Backend(format!("{e}")))
     }
+}
 
-    pub fn save_to_file(&mut self, filepath: &PathBuf) -> Result<(), String> {
-        let serialized =
-            serde_json::to_string(&self.data).map_err(|e| format!("{e}"))?;
+fn write_data(data: &Data, filepath: &Path) -> Result<(), StoreError> {
+    let serialized = serde_json::to_string(data)
+        .map_err(|e| StoreError::Backend(format!("{e}")))?;
 
-        println!("{serialized}");
-        let file = File::create(filepath).map_err(|e| format!("{e}"))?;
+    // write a sibling file first so a crash never leaves a torn file
+    let mut tmpname = filepath.file_name().unwrap_or_default().to_owned();
+    tmpname.push(".tmp");
+    let tmppath = filepath.with_file_name(tmpname);
 
+    let write = || -> std::io::Result<()> {
+        let file = File::create(&tmppath)?;
         let mut bw = BufWriter::new(file);
+        writeln!(bw, "{serialized}")?;
+        bw.into_inner()?.sync_all()?;
+        fs::rename(&tmppath, filepath)
+    };
 
-        writeln!(bw, "{serialized}").map_err(|e| format!("{e}"))?;
+    write().map_err(|e| {
+        let _ = fs::remove_file(&tmppath);
+        StoreError::Backend(format!(
+            "failed to save {}: {e}",
+            filepath.to_string_lossy()
+        ))
+    })
+}
 
-        Ok(())
+/// Rewrites the timestamps an older version wrote in local time as UTC.
+fn local_timestamps_to_utc(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                let naive = match value.as_str() {
+                    Some(text) if TIMESTAMP_FIELDS.contains(&key.as_str()) => {
+                        text.parse::<NaiveDateTime>().ok()
+                    }
+                    _ => None,
+                };
+                let local = naive
+                    .and_then(|t| Local.from_local_datetime(&t).earliest());
+                match local {
+                    Some(t) => {
+                        *value = serde_json::json!(t.with_timezone(&Utc))
+                    }
+                    None => local_timestamps_to_utc(value),
+                }
+            }
+        }
+        serde_json::Value::Array(values) => {
+            values.iter_mut().for_each(local_timestamps_to_utc)
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jsonfile(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "timesman-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn conformance() {
+        let path = jsonfile("conformance");
+        let mut store = JsonStore::build(path.clone()).unwrap();
+
+        crate::testsuite::run(&mut store).await.assert_ok();
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn mutations_are_persisted() {
+        let path = jsonfile("persist");
+        let (times, post) = {
+            let mut store = JsonStore::build(path.clone()).unwrap();
+            let times =
+                store.create_times("persist".to_string()).await.unwrap();
+            let post = store
+                .create_post(times.id, "hello".to_string())
+                .await
+                .unwrap();
+            (times, post)
+        };
+
+        let mut store = JsonStore::build(path.clone()).unwrap();
+        let posts = store.get_posts(times.id).await.unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].post, "hello");
+
+        // ids keep counting after a reload
+        let next = store.create_post(times.id, "x".to_string()).await.unwrap();
+        assert!(next.id > post.id);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn failed_saves_change_nothing() {
+        let dir = jsonfile("unsaved").with_extension("d");
+        fs::create_dir_all(&dir).unwrap();
+        let mut store = JsonStore::build(dir.join("store.json")).unwrap();
+        let times = store.create_times("kept".to_string()).await.unwrap();
+
+        // the file can no longer be written
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(store.create_times("lost".to_string()).await.is_err());
+        assert!(store.delete_times(times.id).await.is_err());
+
+        let titles: Vec<String> = store
+            .get_times()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.title)
+            .collect();
+        assert_eq!(titles, ["kept"]);
+    }
+
+    #[tokio::test]
+    async fn loads_legacy_single_times_file() {
+        let path = jsonfile("legacy");
+        let created_at = Local::now().naive_local();
+        let legacy = serde_json::json!({
+            "times": {
+                "id": 3,
+                "title": "legacy",
+                "created_at": created_at,
+                "updated_at": null,
+            },
+            "posts": [],
+        });
+        fs::write(&path, legacy.to_string()).unwrap();
+
+        let mut store = JsonStore::build(path.clone()).unwrap();
+        let times = store.get_times().await.unwrap();
+        assert_eq!(times[0].title, "legacy");
+        // the local time is read as the same instant
+        assert_eq!(
+            times[0].created_at.with_timezone(&Local).naive_local(),
+            created_at
+        );
+        let new = store.create_times("new".to_string()).await.unwrap();
+        assert_eq!(new.id, 4);
+
+        let _ = fs::remove_file(&path);
     }
 
-    fn load_from_file(filepath: PathBuf) -> Result<Data, String> {
-        let content =
-            fs::read_to_string(filepath).map_err(|e| format!("{e}"))?;
+    #[test]
+    fn rejects_newer_format() {
+        let path = jsonfile("newer");
+        let newer = serde_json::json!({
+            "version": FORMAT_VERSION + 1,
+            "next_tid": 0,
+            "next_pid": 0,
+            "times": [],
+        });
+        fs::write(&path, newer.to_string()).unwrap();
 
-        println!("{content}");
+        assert!(JsonStore::build(path.clone()).is_err());
 
-        serde_json::from_str(&content).map_err(|e| format!("{e}"))
+        let _ = fs::remove_file(&path);
     }
 }
@@ -2,15 +2,313 @@
 pub mod grpc;
 #[cfg(feature = "json")]
 pub mod json;
+pub mod notify;
 pub mod ram;
 #[cfg(feature = "http")]
 pub mod remote;
 #[cfg(feature = "sqlite")]
 pub mod sqlite;
+#[cfg(any(test, feature = "testsuite"))]
+pub mod testsuite;
+
+use std::fmt;
 
 use async_trait::async_trait;
 
-use timesman_type::{Post, Times};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use timesman_type::{
+    Acl, Attachment, Change, Post, Revision, Tag, Times, TimesPost, Trash, User,
+};
+use tokio::sync::broadcast;
+
+/// Errors reported by every [`Store`] backend.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoreError {
+    /// The requested times or post does not exist.
+    NotFound(String),
+    /// The request itself is malformed.
+    InvalidArgument(String),
+    /// The backend does not support the operation.
+    Unsupported(String),
+    /// The request conflicts with the current state of the store.
+    Conflict(String),
+    /// The backing storage or the remote server failed.
+    Backend(String),
+    /// The remote server did not accept the credentials.
+    Unauthenticated(String),
+    /// The caller may not do this to the times.
+    PermissionDenied(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::NotFound(m) => write!(f, "not found: {m}"),
+            StoreError::InvalidArgument(m) => {
+                write!(f, "invalid argument: {m}")
+            }
+            StoreError::Unsupported(m) => write!(f, "unsupported: {m}"),
+            StoreError::Conflict(m) => write!(f, "conflict: {m}"),
+            StoreError::Backend(m) => write!(f, "backend error: {m}"),
+            StoreError::Unauthenticated(m) => {
+                write!(f, "unauthenticated: {m}")
+            }
+            StoreError::PermissionDenied(m) => {
+                write!(f, "permission denied: {m}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl StoreError {
+    /// The message without the kind prefix.
+    pub fn message(&self) -> &str {
+        match self {
+            StoreError::NotFound(m)
+            | StoreError::InvalidArgument(m)
+            | StoreError::Unsupported(m)
+            | StoreError::Conflict(m)
+            | StoreError::Backend(m)
+            | StoreError::Unauthenticated(m)
+            | StoreError::PermissionDenied(m) => m,
+        }
+    }
+}
+
+/// Narrows down the posts returned by [`Store::search_posts`].
+#[derive(Clone, Debug, Default)]
+pub struct SearchFilter {
+    /// Only search the posts of this times.
+    pub tid: Option<u64>,
+    /// Only posts created at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only posts created before this time.
+    pub until: Option<DateTime<Utc>>,
+    /// Return at most this many posts.
+    pub limit: Option<usize>,
+}
+
+impl SearchFilter {
+    fn accepts(&self, tid: u64, post: &Post) -> bool {
+        self.tid.is_none_or(|t| t == tid)
+            && self.since.is_none_or(|s| post.created_at >= s)
+            && self.until.is_none_or(|u| post.created_at < u)
+    }
+}
+
+/// Splits a search query into lowercase terms which must all match.
+pub(crate) fn search_terms(query: &str) -> Result<Vec<String>, StoreError> {
+    let terms: Vec<String> =
+        query.split_whitespace().map(|t| t.to_lowercase()).collect();
+
+    if terms.is_empty() {
+        return Err(StoreError::InvalidArgument(
+            "search query is empty".to_string(),
+        ));
+    }
+
+    Ok(terms)
+}
+
+/// Searches posts by scanning them, for backends without a search index.
+/// Hits are returned newest first.
+pub(crate) fn scan_posts<'a>(
+    query: &str,
+    filter: &SearchFilter,
+    posts: impl Iterator<Item = (u64, &'a Post)>,
+) -> Result<Vec<TimesPost>, StoreError> {
+    let terms = search_terms(query)?;
+
+    let mut hits: Vec<TimesPost> = posts
+        .filter(|(tid, post)| filter.accepts(*tid, post))
+        .filter(|(_, post)| {
+            let text = post.post.to_lowercase();
+            terms.iter().all(|t| text.contains(t))
+        })
+        .map(|(tid, post)| TimesPost {
+            tid,
+            post: post.clone(),
+        })
+        .collect();
+
+    sort_newest_first(&mut hits);
+    if let Some(limit) = filter.limit {
+        hits.truncate(limit);
+    }
+
+    Ok(hits)
+}
+
+/// Selects a page of posts for [`Store::get_posts_page`].
+///
+/// Pages walk backwards in time: the first page holds the newest posts and
+/// [`PostPage::next`] is the cursor for the page before it.
+#[derive(Clone, Debug, Default)]
+pub struct PostQuery {
+    /// Only posts older than the post with this id.
+    pub cursor: Option<u64>,
+    /// Only posts created at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only posts created before this time.
+    pub until: Option<DateTime<Utc>>,
+    /// Return at most this many posts.
+    pub limit: Option<usize>,
+}
+
+impl PostQuery {
+    fn accepts(&self, post: &Post) -> bool {
+        self.cursor.is_none_or(|c| post.id < c)
+            && self.since.is_none_or(|s| post.created_at >= s)
+            && self.until.is_none_or(|u| post.created_at < u)
+    }
+
+    /// Cuts a page out of every post of a times, for backends which keep
+    /// them all in memory anyway.
+    pub(crate) fn paginate(&self, posts: Vec<Post>) -> PostPage {
+        let mut posts: Vec<Post> =
+            posts.into_iter().filter(|p| self.accepts(p)).collect();
+        posts.sort_by_key(|p| p.id);
+
+        let mut next = None;
+        if let Some(limit) = self.limit {
+            if posts.len() > limit {
+                posts.drain(..posts.len() - limit);
+                next = posts.first().map(|p| p.id);
+            }
+        }
+
+        PostPage { posts, next }
+    }
+}
+
+/// A page of posts, oldest first.
+#[derive(Clone, Default)]
+pub struct PostPage {
+    pub posts: Vec<Post>,
+    /// The cursor of the older page, if there is one.
+    pub next: Option<u64>,
+}
+
+/// Validates a tag given by a caller, accepting it with or without `#`.
+pub(crate) fn normalize_tag(tag: &str) -> Result<String, StoreError> {
+    let name = tag.strip_prefix('#').unwrap_or(tag).to_lowercase();
+
+    if name.is_empty() || !name.chars().all(timesman_type::is_tag_char) {
+        return Err(StoreError::InvalidArgument(format!(
+            "{tag:?} is not a valid tag"
+        )));
+    }
+
+    Ok(name)
+}
+
+/// Counts the tags of posts, for backends without a tag index.
+/// The most used tags come first.
+#[cfg(feature = "json")]
+pub(crate) fn count_tags<'a>(
+    posts: impl Iterator<Item = &'a Post>,
+) -> Vec<Tag> {
+    let mut counts: std::collections::HashMap<String, u64> =
+        std::collections::HashMap::new();
+    for post in posts {
+        for tag in post.tags() {
+            *counts.entry(tag).or_default() += 1;
+        }
+    }
+
+    let mut tags: Vec<Tag> = counts
+        .into_iter()
+        .map(|(name, count)| Tag { name, count })
+        .collect();
+    tags.sort_by(|a, b| b.count.cmp(&a.count).then(a.name.cmp(&b.name)));
+
+    tags
+}
+
+/// Orders posts newest first, as returned by searches and tag lookups.
+pub(crate) fn sort_newest_first(posts: &mut [TimesPost]) {
+    posts.sort_by(|a, b| {
+        (b.post.created_at, b.post.id).cmp(&(a.post.created_at, a.post.id))
+    });
+}
+
+/// The largest attachment a store accepts, in bytes.
+pub const MAX_ATTACHMENT_SIZE: usize = 32 << 20;
+
+/// Validates a file given to [`Store::attach`].
+pub(crate) fn check_attachment(
+    name: &str,
+    mime: &str,
+    data: &[u8],
+) -> Result<(), StoreError> {
+    if name.trim().is_empty() {
+        return Err(StoreError::InvalidArgument(
+            "attachment name is empty".to_string(),
+        ));
+    }
+
+    if !mime.contains('/') {
+        return Err(StoreError::InvalidArgument(format!(
+            "invalid MIME type {mime:?}"
+        )));
+    }
+
+    if data.len() > MAX_ATTACHMENT_SIZE {
+        return Err(StoreError::InvalidArgument(format!(
+            "attachment is larger than {MAX_ATTACHMENT_SIZE} bytes"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Validates the name of a new user: letters, digits, `_`, `-` and `.`.
+pub(crate) fn check_user_name(name: &str) -> Result<(), StoreError> {
+    let valid = |c: char| c.is_alphanumeric() || "_-.".contains(c);
+    if name.is_empty() || !name.chars().all(valid) {
+        return Err(StoreError::InvalidArgument(format!(
+            "{name:?} is not a valid user name"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Validates the users of an ACL given to [`Store::set_acl`] and puts them
+/// in order, keeping the owner of the times. Writers are not listed again
+/// as readers.
+pub(crate) fn normalize_acl(
+    acl: Acl,
+    owner: Option<String>,
+) -> Result<Acl, StoreError> {
+    let normalize = |mut names: Vec<String>| {
+        for name in &names {
+            check_user_name(name)?;
+        }
+        names.sort();
+        names.dedup();
+        Ok::<_, StoreError>(names)
+    };
+
+    let writers = normalize(acl.writers)?;
+    let mut readers = normalize(acl.readers)?;
+    readers.retain(|r| !writers.contains(r));
+
+    Ok(Acl {
+        owner,
+        readers,
+        writers,
+        public: acl.public,
+    })
+}
+
+/// The address of attachment content: its SHA-256 in hex.
+pub(crate) fn content_hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
 
 #[derive(PartialEq, Default)]
 pub enum StoreType {
@@ -26,38 +324,336 @@ pub enum StoreType {
 
 #[async_trait]
 pub trait Store: Send + Sync + 'static {
-    async fn check(&mut self) -> Result<(), String>;
+    async fn check(&mut self) -> Result<(), StoreError>;
+
+    /// Releases the resources of the store, e.g. its database connections,
+    /// after the last request. Nothing else may be called afterwards.
+    async fn close(&mut self) -> Result<(), StoreError> {
+        Ok(())
+    }
 
     // for Times
-    async fn get_times(&mut self) -> Result<Vec<Times>, String>;
-    async fn create_times(&mut self, title: String) -> Result<Times, String>;
-    async fn delete_times(&mut self, tid: u64) -> Result<(), String>;
-    async fn update_times(&mut self, times: Times) -> Result<Times, String>;
+    async fn get_times(&mut self) -> Result<Vec<Times>, StoreError>;
+    async fn create_times(
+        &mut self,
+        title: String,
+    ) -> Result<Times, StoreError>;
+    async fn delete_times(&mut self, tid: u64) -> Result<(), StoreError>;
+    async fn update_times(&mut self, times: Times)
+        -> Result<Times, StoreError>;
 
     // for Post
-    async fn get_posts(&mut self, tid: u64) -> Result<Vec<Post>, String>;
+    async fn get_posts(&mut self, tid: u64) -> Result<Vec<Post>, StoreError>;
+    async fn get_posts_page(
+        &mut self,
+        tid: u64,
+        query: PostQuery,
+    ) -> Result<PostPage, StoreError> {
+        if query.limit == Some(0) {
+            return Err(StoreError::InvalidArgument(
+                "page limit must not be zero".to_string(),
+            ));
+        }
+
+        let posts = self.get_posts(tid).await?;
+        Ok(query.paginate(posts))
+    }
     async fn create_post(
         &mut self,
         tid: u64,
         post: String,
-    ) -> Result<Post, String>;
-    async fn delete_post(&mut self, tid: u64, pid: u64) -> Result<(), String>;
+    ) -> Result<Post, StoreError>;
+    async fn delete_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<(), StoreError>;
     async fn update_post(
         &mut self,
         tid: u64,
         post: Post,
-    ) -> Result<Post, String>;
+    ) -> Result<Post, StoreError>;
 
     async fn get_latest_post(
         &mut self,
         tid: u64,
-    ) -> Result<Option<Post>, String>;
+    ) -> Result<Option<Post>, StoreError>;
+
+    // for Revisions
+    /// The earlier versions of a post, oldest first. The current text of
+    /// the post is not included.
+    async fn list_revisions(
+        &mut self,
+        _tid: u64,
+        _pid: u64,
+    ) -> Result<Vec<Revision>, StoreError> {
+        Err(StoreError::Unsupported(
+            "revisions are not supported by this store".to_string(),
+        ))
+    }
+
+    async fn get_revision(
+        &mut self,
+        tid: u64,
+        pid: u64,
+        rev: u64,
+    ) -> Result<Revision, StoreError> {
+        self.list_revisions(tid, pid)
+            .await?
+            .into_iter()
+            .find(|r| r.rev == rev)
+            .ok_or(StoreError::NotFound(format!(
+                "revision {rev} of post {pid}"
+            )))
+    }
+
+    /// Sets the text of a post back to a revision. This is an edit itself,
+    /// so the replaced text becomes a new revision.
+    async fn revert_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+        rev: u64,
+    ) -> Result<Post, StoreError> {
+        let revision = self.get_revision(tid, pid, rev).await?;
+        let mut post = self
+            .get_posts(tid)
+            .await?
+            .into_iter()
+            .find(|p| p.id == pid)
+            .ok_or(StoreError::NotFound(format!("post {pid}")))?;
+
+        post.post = revision.post;
+        self.update_post(tid, post).await
+    }
+
+    // for Attachments
+    /// Stores a file and attaches it to a post.
+    async fn attach(
+        &mut self,
+        _tid: u64,
+        _pid: u64,
+        _name: String,
+        _mime: String,
+        _data: Vec<u8>,
+    ) -> Result<Attachment, StoreError> {
+        Err(StoreError::Unsupported(
+            "attachments are not supported by this store".to_string(),
+        ))
+    }
+
+    /// The attachments of every post in a times, in the order they were
+    /// attached. Attachments of posts in the trash are left out.
+    async fn list_attachments(
+        &mut self,
+        _tid: u64,
+    ) -> Result<Vec<Attachment>, StoreError> {
+        Err(StoreError::Unsupported(
+            "attachments are not supported by this store".to_string(),
+        ))
+    }
+
+    async fn get_attachment(
+        &mut self,
+        tid: u64,
+        aid: u64,
+    ) -> Result<Attachment, StoreError> {
+        self.list_attachments(tid)
+            .await?
+            .into_iter()
+            .find(|a| a.id == aid)
+            .ok_or(StoreError::NotFound(format!("attachment {aid}")))
+    }
+
+    /// The content of an attachment.
+    async fn read_attachment(
+        &mut self,
+        _tid: u64,
+        _aid: u64,
+    ) -> Result<Vec<u8>, StoreError> {
+        Err(StoreError::Unsupported(
+            "attachments are not supported by this store".to_string(),
+        ))
+    }
+
+    async fn detach(&mut self, _tid: u64, _aid: u64) -> Result<(), StoreError> {
+        Err(StoreError::Unsupported(
+            "attachments are not supported by this store".to_string(),
+        ))
+    }
+
+    // for Search
+    async fn search_posts(
+        &mut self,
+        _query: String,
+        _filter: SearchFilter,
+    ) -> Result<Vec<TimesPost>, StoreError> {
+        Err(StoreError::Unsupported(
+            "search is not supported by this store".to_string(),
+        ))
+    }
+
+    // for Tags
+    /// Every hashtag used by the posts of live times, most used first.
+    async fn list_tags(&mut self) -> Result<Vec<Tag>, StoreError> {
+        Err(StoreError::Unsupported(
+            "tags are not supported by this store".to_string(),
+        ))
+    }
+
+    /// The posts of every live times carrying `tag`, newest first.
+    async fn get_posts_by_tag(
+        &mut self,
+        _tag: String,
+    ) -> Result<Vec<TimesPost>, StoreError> {
+        Err(StoreError::Unsupported(
+            "tags are not supported by this store".to_string(),
+        ))
+    }
+
+    // for Trash
+    /// Deleted times and posts which can still be restored.
+    async fn list_trash(&mut self) -> Result<Trash, StoreError> {
+        Err(StoreError::Unsupported(
+            "trash is not supported by this store".to_string(),
+        ))
+    }
+
+    /// Moves a times back from the trash, with the posts it had when it
+    /// was deleted.
+    async fn restore_times(&mut self, _tid: u64) -> Result<Times, StoreError> {
+        Err(StoreError::Unsupported(
+            "trash is not supported by this store".to_string(),
+        ))
+    }
+
+    /// Moves a post back from the trash. Its times must not be in the
+    /// trash.
+    async fn restore_post(
+        &mut self,
+        _tid: u64,
+        _pid: u64,
+    ) -> Result<Post, StoreError> {
+        Err(StoreError::Unsupported(
+            "trash is not supported by this store".to_string(),
+        ))
+    }
+
+    /// Permanently removes a times in the trash and all of its posts.
+    async fn purge_times(&mut self, _tid: u64) -> Result<(), StoreError> {
+        Err(StoreError::Unsupported(
+            "trash is not supported by this store".to_string(),
+        ))
+    }
+
+    /// Permanently removes a post in the trash.
+    async fn purge_post(
+        &mut self,
+        _tid: u64,
+        _pid: u64,
+    ) -> Result<(), StoreError> {
+        Err(StoreError::Unsupported(
+            "trash is not supported by this store".to_string(),
+        ))
+    }
+
+    /// Permanently removes what was deleted before `before`, or the whole
+    /// trash. Returns the number of trashed times and posts removed.
+    async fn purge_trash(
+        &mut self,
+        _before: Option<DateTime<Utc>>,
+    ) -> Result<u64, StoreError> {
+        Err(StoreError::Unsupported(
+            "trash is not supported by this store".to_string(),
+        ))
+    }
+
+    // for Users
+    /// Adds a user account. Names are unique in a store.
+    async fn create_user(&mut self, _name: String) -> Result<User, StoreError> {
+        Err(StoreError::Unsupported(
+            "users are not supported by this store".to_string(),
+        ))
+    }
+
+    async fn list_users(&mut self) -> Result<Vec<User>, StoreError> {
+        Err(StoreError::Unsupported(
+            "users are not supported by this store".to_string(),
+        ))
+    }
+
+    /// Creates a times belonging to the user `owner`.
+    async fn create_times_for(
+        &mut self,
+        _owner: String,
+        _title: String,
+    ) -> Result<Times, StoreError> {
+        Err(StoreError::Unsupported(
+            "users are not supported by this store".to_string(),
+        ))
+    }
+
+    /// Creates a post written by the user `author`.
+    async fn create_post_by(
+        &mut self,
+        _tid: u64,
+        _author: String,
+        _post: String,
+    ) -> Result<Post, StoreError> {
+        Err(StoreError::Unsupported(
+            "users are not supported by this store".to_string(),
+        ))
+    }
+
+    /// The times the user `user` may read by their ACL.
+    async fn get_visible_times(
+        &mut self,
+        user: String,
+    ) -> Result<Vec<Times>, StoreError> {
+        let mut visible = vec![];
+        for times in self.get_times().await? {
+            if self.get_acl(times.id).await?.can_read(&user) {
+                visible.push(times);
+            }
+        }
+
+        Ok(visible)
+    }
+
+    // for ACLs
+    /// The ACL of the times `tid`, which may be in the trash.
+    async fn get_acl(&mut self, _tid: u64) -> Result<Acl, StoreError> {
+        Err(StoreError::Unsupported(
+            "ACLs are not supported by this store".to_string(),
+        ))
+    }
+
+    /// Replaces the readers, writers and public flag of the times `tid`.
+    /// The owner in `acl` is ignored. Every named user must exist.
+    async fn set_acl(
+        &mut self,
+        _tid: u64,
+        _acl: Acl,
+    ) -> Result<Acl, StoreError> {
+        Err(StoreError::Unsupported(
+            "ACLs are not supported by this store".to_string(),
+        ))
+    }
+
+    // for Changes
+    /// Subscribes to the changes made to the store from now on. Wrap a
+    /// store in [`notify::NotifyingStore`] to publish its changes.
+    async fn subscribe(
+        &mut self,
+    ) -> Result<broadcast::Receiver<Change>, StoreError> {
+        Err(StoreError::Unsupported(
+            "change notification is not supported by this store".to_string(),
+        ))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-
     #[test]
     fn it_works() {}
 }
@@ -0,0 +1,347 @@
+//! Publishes the changes made through a store to its subscribers.
+
+use super::{
+    Post, PostPage, PostQuery, SearchFilter, Store, StoreError, Times,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use timesman_type::{
+    Acl, Attachment, Change, Revision, Tag, TimesPost, Trash, User,
+};
+use tokio::sync::broadcast;
+
+/// Changes kept for subscribers which fall behind. A subscriber which
+/// misses more sees [`broadcast::error::RecvError::Lagged`] and should
+/// reload what it shows.
+const CHANGE_BUFFER: usize = 256;
+
+/// Wraps any store and reports every successful change made through it to
+/// the receivers handed out by [`Store::subscribe`].
+pub struct NotifyingStore {
+    inner: Box<dyn Store + Send + Sync + 'static>,
+    tx: broadcast::Sender<Change>,
+}
+
+impl NotifyingStore {
+    pub fn new(inner: Box<dyn Store + Send + Sync + 'static>) -> Self {
+        let (tx, _) = broadcast::channel(CHANGE_BUFFER);
+        Self { inner, tx }
+    }
+
+    fn publish(&self, change: Change) {
+        // nobody listening is not an error
+        let _ = self.tx.send(change);
+    }
+}
+
+#[async_trait]
+impl Store for NotifyingStore {
+    async fn check(&mut self) -> Result<(), StoreError> {
+        self.inner.check().await
+    }
+
+    async fn close(&mut self) -> Result<(), StoreError> {
+        self.inner.close().await
+    }
+
+    async fn get_times(&mut self) -> Result<Vec<Times>, StoreError> {
+        self.inner.get_times().await
+    }
+
+    async fn create_times(
+        &mut self,
+        title: String,
+    ) -> Result<Times, StoreError> {
+        let times = self.inner.create_times(title).await?;
+        self.publish(Change::TimesCreated {
+            times: times.clone(),
+        });
+        Ok(times)
+    }
+
+    async fn delete_times(&mut self, tid: u64) -> Result<(), StoreError> {
+        self.inner.delete_times(tid).await?;
+        self.publish(Change::TimesDeleted { tid });
+        Ok(())
+    }
+
+    async fn update_times(
+        &mut self,
+        times: Times,
+    ) -> Result<Times, StoreError> {
+        let times = self.inner.update_times(times).await?;
+        self.publish(Change::TimesUpdated {
+            times: times.clone(),
+        });
+        Ok(times)
+    }
+
+    async fn get_posts(&mut self, tid: u64) -> Result<Vec<Post>, StoreError> {
+        self.inner.get_posts(tid).await
+    }
+
+    async fn get_posts_page(
+        &mut self,
+        tid: u64,
+        query: PostQuery,
+    ) -> Result<PostPage, StoreError> {
+        self.inner.get_posts_page(tid, query).await
+    }
+
+    async fn create_post(
+        &mut self,
+        tid: u64,
+        post: String,
+    ) -> Result<Post, StoreError> {
+        let post = self.inner.create_post(tid, post).await?;
+        self.publish(Change::PostCreated {
+            tid,
+            post: post.clone(),
+        });
+        Ok(post)
+    }
+
+    async fn delete_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<(), StoreError> {
+        self.inner.delete_post(tid, pid).await?;
+        self.publish(Change::PostDeleted { tid, pid });
+        Ok(())
+    }
+
+    async fn update_post(
+        &mut self,
+        tid: u64,
+        post: Post,
+    ) -> Result<Post, StoreError> {
+        let post = self.inner.update_post(tid, post).await?;
+        self.publish(Change::PostUpdated {
+            tid,
+            post: post.clone(),
+        });
+        Ok(post)
+    }
+
+    async fn get_latest_post(
+        &mut self,
+        tid: u64,
+    ) -> Result<Option<Post>, StoreError> {
+        self.inner.get_latest_post(tid).await
+    }
+
+    async fn list_revisions(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<Vec<Revision>, StoreError> {
+        self.inner.list_revisions(tid, pid).await
+    }
+
+    async fn get_revision(
+        &mut self,
+        tid: u64,
+        pid: u64,
+        rev: u64,
+    ) -> Result<Revision, StoreError> {
+        self.inner.get_revision(tid, pid, rev).await
+    }
+
+    async fn revert_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+        rev: u64,
+    ) -> Result<Post, StoreError> {
+        let post = self.inner.revert_post(tid, pid, rev).await?;
+        self.publish(Change::PostUpdated {
+            tid,
+            post: post.clone(),
+        });
+        Ok(post)
+    }
+
+    async fn attach(
+        &mut self,
+        tid: u64,
+        pid: u64,
+        name: String,
+        mime: String,
+        data: Vec<u8>,
+    ) -> Result<Attachment, StoreError> {
+        self.inner.attach(tid, pid, name, mime, data).await
+    }
+
+    async fn list_attachments(
+        &mut self,
+        tid: u64,
+    ) -> Result<Vec<Attachment>, StoreError> {
+        self.inner.list_attachments(tid).await
+    }
+
+    async fn get_attachment(
+        &mut self,
+        tid: u64,
+        aid: u64,
+    ) -> Result<Attachment, StoreError> {
+        self.inner.get_attachment(tid, aid).await
+    }
+
+    async fn read_attachment(
+        &mut self,
+        tid: u64,
+        aid: u64,
+    ) -> Result<Vec<u8>, StoreError> {
+        self.inner.read_attachment(tid, aid).await
+    }
+
+    async fn detach(&mut self, tid: u64, aid: u64) -> Result<(), StoreError> {
+        self.inner.detach(tid, aid).await
+    }
+
+    async fn search_posts(
+        &mut self,
+        query: String,
+        filter: SearchFilter,
+    ) -> Result<Vec<TimesPost>, StoreError> {
+        self.inner.search_posts(query, filter).await
+    }
+
+    async fn list_tags(&mut self) -> Result<Vec<Tag>, StoreError> {
+        self.inner.list_tags().await
+    }
+
+    async fn get_posts_by_tag(
+        &mut self,
+        tag: String,
+    ) -> Result<Vec<TimesPost>, StoreError> {
+        self.inner.get_posts_by_tag(tag).await
+    }
+
+    async fn list_trash(&mut self) -> Result<Trash, StoreError> {
+        self.inner.list_trash().await
+    }
+
+    async fn restore_times(&mut self, tid: u64) -> Result<Times, StoreError> {
+        let times = self.inner.restore_times(tid).await?;
+        self.publish(Change::TimesCreated {
+            times: times.clone(),
+        });
+        Ok(times)
+    }
+
+    async fn restore_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<Post, StoreError> {
+        let post = self.inner.restore_post(tid, pid).await?;
+        self.publish(Change::PostCreated {
+            tid,
+            post: post.clone(),
+        });
+        Ok(post)
+    }
+
+    async fn purge_times(&mut self, tid: u64) -> Result<(), StoreError> {
+        self.inner.purge_times(tid).await
+    }
+
+    async fn purge_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<(), StoreError> {
+        self.inner.purge_post(tid, pid).await
+    }
+
+    async fn purge_trash(
+        &mut self,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<u64, StoreError> {
+        self.inner.purge_trash(before).await
+    }
+
+    async fn create_user(&mut self, name: String) -> Result<User, StoreError> {
+        self.inner.create_user(name).await
+    }
+
+    async fn list_users(&mut self) -> Result<Vec<User>, StoreError> {
+        self.inner.list_users().await
+    }
+
+    async fn create_times_for(
+        &mut self,
+        owner: String,
+        title: String,
+    ) -> Result<Times, StoreError> {
+        let times = self.inner.create_times_for(owner, title).await?;
+        self.publish(Change::TimesCreated {
+            times: times.clone(),
+        });
+        Ok(times)
+    }
+
+    async fn create_post_by(
+        &mut self,
+        tid: u64,
+        author: String,
+        post: String,
+    ) -> Result<Post, StoreError> {
+        let post = self.inner.create_post_by(tid, author, post).await?;
+        self.publish(Change::PostCreated {
+            tid,
+            post: post.clone(),
+        });
+        Ok(post)
+    }
+
+    async fn get_visible_times(
+        &mut self,
+        user: String,
+    ) -> Result<Vec<Times>, StoreError> {
+        self.inner.get_visible_times(user).await
+    }
+
+    async fn get_acl(&mut self, tid: u64) -> Result<Acl, StoreError> {
+        self.inner.get_acl(tid).await
+    }
+
+    async fn set_acl(&mut self, tid: u64, acl: Acl) -> Result<Acl, StoreError> {
+        self.inner.set_acl(tid, acl).await
+    }
+
+    async fn subscribe(
+        &mut self,
+    ) -> Result<broadcast::Receiver<Change>, StoreError> {
+        Ok(self.tx.subscribe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ram::RamStore;
+
+    #[tokio::test]
+    async fn conformance() {
+        let mut store = NotifyingStore::new(Box::new(RamStore::new()));
+        crate::testsuite::run(&mut store).await.assert_ok();
+    }
+
+    #[tokio::test]
+    async fn failed_changes_are_not_published() {
+        let mut store = NotifyingStore::new(Box::new(RamStore::new()));
+        let mut rx = store.subscribe().await.unwrap();
+
+        assert!(store.delete_times(1).await.is_err());
+        let times = store.create_times("t".to_string()).await.unwrap();
+
+        match rx.try_recv() {
+            Ok(Change::TimesCreated { times: t }) => assert_eq!(t.id, times.id),
+            _ => panic!("expected only the creation"),
+        }
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -1,51 +1,81 @@
-use super::{Post, Store, Times};
+use super::{
+    check_attachment, check_user_name, content_hash, normalize_acl,
+    normalize_tag, scan_posts, sort_newest_first, Post, SearchFilter, Store,
+    StoreError, Times,
+};
 use async_trait::async_trait;
-use chrono::Local;
-use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use timesman_type::{
+    Acl, Attachment, Revision, Tag, TimesPost, Trash, TrashedPost,
+    TrashedTimes, User,
+};
 
 struct LocalTimes {
     times: Times,
     posts: HashMap<u64, Post>,
     next_pid: u64,
+    /// Deleted posts and when they were deleted.
+    trash: HashMap<u64, (Post, DateTime<Utc>)>,
+    /// Earlier versions of each edited post, oldest first.
+    revisions: HashMap<u64, Vec<Revision>>,
+    acl: Acl,
 }
 
+#[derive(Default)]
 pub struct RamStore {
     times: HashMap<u64, LocalTimes>,
+    /// Deleted times and when they were deleted.
+    trash: HashMap<u64, (LocalTimes, DateTime<Utc>)>,
     next_tid: u64,
+    /// (tid, pid) of the posts carrying each tag.
+    tags: HashMap<String, BTreeSet<(u64, u64)>>,
+    /// Attachments by id, with the times of the post they belong to.
+    attachments: BTreeMap<u64, (u64, Attachment)>,
+    next_aid: u64,
+    /// Attachment content by hash.
+    blobs: HashMap<String, Vec<u8>>,
+    /// User accounts by name.
+    users: BTreeMap<String, User>,
+    next_uid: u64,
 }
 
 impl RamStore {
     pub fn new() -> Self {
         Self {
             times: HashMap::new(),
+            trash: HashMap::new(),
             next_tid: 0,
+            tags: HashMap::new(),
+            attachments: BTreeMap::new(),
+            next_aid: 0,
+            blobs: HashMap::new(),
+            users: BTreeMap::new(),
+            next_uid: 0,
         }
     }
-}
 
-#[async_trait]
-impl Store for RamStore {
-    async fn check(&mut self) -> Result<(), String> {
-        Ok(())
+    fn find_user(&self, name: &str) -> Result<&User, StoreError> {
+        self.users
+            .get(name)
+            .ok_or(StoreError::NotFound(format!("user {name}")))
     }
 
-    async fn get_times(&mut self) -> Result<Vec<super::Times>, String> {
-        Ok(self.times.iter().map(|t| t.1.times.clone()).collect())
-    }
-
-    async fn create_times(
-        &mut self,
-        title: String,
-    ) -> Result<super::Times, String> {
+    fn insert_times(&mut self, title: String, owner: Option<String>) -> Times {
         let id = self.next_tid;
         self.next_tid += 1;
 
-        let now = Local::now();
+        let now = Utc::now();
 
+        let acl = Acl {
+            owner: owner.clone(),
+            ..Acl::default()
+        };
         let times = Times {
             id,
             title,
-            created_at: now.naive_local(),
+            owner,
+            created_at: now,
             updated_at: None,
         };
 
@@ -53,40 +83,184 @@ impl Store for RamStore {
             times: times.clone(),
             posts: HashMap::new(),
             next_pid: 0,
+            trash: HashMap::new(),
+            revisions: HashMap::new(),
+            acl,
         };
 
         self.times.insert(id, ltimes);
 
+        times
+    }
+
+    fn insert_post(
+        &mut self,
+        tid: u64,
+        post: String,
+        author: Option<String>,
+    ) -> Result<Post, StoreError> {
+        let ltimes = self
+            .times
+            .get_mut(&tid)
+            .ok_or(StoreError::NotFound(format!("times {tid}")))?;
+
+        let post = Post {
+            id: ltimes.next_pid,
+            post,
+            author,
+            created_at: Utc::now(),
+            updated_at: None,
+        };
+
+        ltimes.posts.insert(post.id, post.clone());
+        ltimes.next_pid += 1;
+        self.index_post(tid, &post);
+
+        Ok(post)
+    }
+
+    fn index_post(&mut self, tid: u64, post: &Post) {
+        for tag in post.tags() {
+            self.tags.entry(tag).or_default().insert((tid, post.id));
+        }
+    }
+
+    /// A times, either live or in the trash.
+    fn find_times_mut(
+        &mut self,
+        tid: u64,
+    ) -> Result<&mut LocalTimes, StoreError> {
+        match self.times.get_mut(&tid) {
+            Some(ltimes) => Ok(ltimes),
+            None => match self.trash.get_mut(&tid) {
+                Some((ltimes, _)) => Ok(ltimes),
+                None => Err(StoreError::NotFound(format!("times {tid}"))),
+            },
+        }
+    }
+
+    /// Whether a post exists, either live or in the trash.
+    fn has_post(&self, tid: u64, pid: u64) -> bool {
+        let ltimes = match self.times.get(&tid) {
+            Some(ltimes) => ltimes,
+            None => match self.trash.get(&tid) {
+                Some((ltimes, _)) => ltimes,
+                None => return false,
+            },
+        };
+
+        ltimes.posts.contains_key(&pid) || ltimes.trash.contains_key(&pid)
+    }
+
+    /// Drops the attachments of purged posts and content nothing refers to.
+    fn collect_attachments(&mut self) {
+        let attachments = std::mem::take(&mut self.attachments);
+        self.attachments = attachments
+            .into_iter()
+            .filter(|(_, (tid, a))| self.has_post(*tid, a.pid))
+            .collect();
+
+        let used: HashSet<&String> =
+            self.attachments.values().map(|(_, a)| &a.hash).collect();
+        self.blobs.retain(|hash, _| used.contains(hash));
+    }
+
+    /// Looks up an attachment of a live post in the times `tid`.
+    fn find_attachment(
+        &self,
+        tid: u64,
+        aid: u64,
+    ) -> Result<&Attachment, StoreError> {
+        let ltimes = self
+            .times
+            .get(&tid)
+            .ok_or(StoreError::NotFound(format!("times {tid}")))?;
+
+        match self.attachments.get(&aid) {
+            Some((t, a)) if *t == tid && ltimes.posts.contains_key(&a.pid) => {
+                Ok(a)
+            }
+            _ => Err(StoreError::NotFound(format!("attachment {aid}"))),
+        }
+    }
+
+    fn unindex_post(&mut self, tid: u64, post: &Post) {
+        for tag in post.tags() {
+            if let Some(posts) = self.tags.get_mut(&tag) {
+                posts.remove(&(tid, post.id));
+                if posts.is_empty() {
+                    self.tags.remove(&tag);
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Store for RamStore {
+    async fn check(&mut self) -> Result<(), StoreError> {
+        Ok(())
+    }
+
+    async fn get_times(&mut self) -> Result<Vec<super::Times>, StoreError> {
+        let mut times: Vec<Times> =
+            self.times.values().map(|t| t.times.clone()).collect();
+        times.sort_by_key(|t| t.id);
+
         Ok(times)
     }
 
-    async fn delete_times(&mut self, _tid: u64) -> Result<(), String> {
-        unimplemented!();
+    async fn create_times(
+        &mut self,
+        title: String,
+    ) -> Result<super::Times, StoreError> {
+        Ok(self.insert_times(title, None))
+    }
+
+    async fn delete_times(&mut self, tid: u64) -> Result<(), StoreError> {
+        match self.times.remove(&tid) {
+            Some(ltimes) => {
+                for post in ltimes.posts.values() {
+                    self.unindex_post(tid, post);
+                }
+                let now = Utc::now();
+                self.trash.insert(tid, (ltimes, now));
+                Ok(())
+            }
+            None => Err(StoreError::NotFound(format!("times {tid}"))),
+        }
     }
 
     async fn update_times(
         &mut self,
         times: super::Times,
-    ) -> Result<Times, String> {
+    ) -> Result<Times, StoreError> {
         if let Some(t) = self.times.get_mut(&times.id) {
-            t.times = times;
-            let now = Local::now();
-            t.times.updated_at = Some(now.naive_local());
+            // the owner of a times never changes
+            t.times = Times {
+                owner: t.times.owner.clone(),
+                ..times
+            };
+            let now = Utc::now();
+            t.times.updated_at = Some(now);
             Ok(t.times.clone())
         } else {
-            return Err("times id is invalid".to_string());
+            Err(StoreError::NotFound(format!("times {}", times.id)))
         }
     }
 
     async fn get_posts(
         &mut self,
         tid: u64,
-    ) -> Result<Vec<super::Post>, String> {
-        let ltimes = self.times.get(&tid).ok_or("invalid tid")?;
+    ) -> Result<Vec<super::Post>, StoreError> {
+        let ltimes = self
+            .times
+            .get(&tid)
+            .ok_or(StoreError::NotFound(format!("times {tid}")))?;
 
         let mut pairs: Vec<(&u64, &Post)> = ltimes.posts.iter().collect();
 
-        pairs.sort_by(|a, b| a.0.cmp(&b.0));
+        pairs.sort_by(|a, b| a.0.cmp(b.0));
 
         let posts = pairs.iter().map(|x| x.1.clone()).collect();
 
@@ -97,71 +271,438 @@ impl Store for RamStore {
         &mut self,
         tid: u64,
         post: String,
-    ) -> Result<super::Post, String> {
-        let ltimes = self.times.get_mut(&tid).ok_or("invalid tid")?;
-
-        let post = Post {
-            id: ltimes.next_pid,
-            post,
-            created_at: Local::now().naive_local(),
-            updated_at: None,
-        };
-
-        ltimes.posts.insert(post.id, post.clone());
-        ltimes.next_pid += 1;
-
-        Ok(post)
+    ) -> Result<super::Post, StoreError> {
+        self.insert_post(tid, post, None)
     }
 
     async fn update_post(
         &mut self,
         tid: u64,
         mut post: super::Post,
-    ) -> Result<super::Post, String> {
+    ) -> Result<super::Post, StoreError> {
         let times = match self.times.get_mut(&tid) {
             Some(t) => t,
             None => {
-                return Err("Invalid tid".to_string());
+                return Err(StoreError::NotFound(format!("times {tid}")));
             }
         };
 
         let oldpost = match times.posts.get_mut(&post.id) {
             Some(p) => p,
-            None => return Err("Invalid pid".to_string()),
+            None => {
+                return Err(StoreError::NotFound(format!("post {}", post.id)))
+            }
         };
 
-        post.updated_at = Some(Local::now().naive_local());
+        post.updated_at = Some(Utc::now());
+        post.author.clone_from(&oldpost.author);
 
-        *oldpost = post.clone();
+        let old = std::mem::replace(oldpost, post.clone());
+        if old.post != post.post {
+            let revisions = times.revisions.entry(post.id).or_default();
+            revisions.push(Revision {
+                rev: revisions.len() as u64 + 1,
+                post: old.post.clone(),
+                created_at: old.updated_at.unwrap_or(old.created_at),
+            });
+        }
+        self.unindex_post(tid, &old);
+        self.index_post(tid, &post);
 
         Ok(post)
     }
 
-    async fn delete_post(&mut self, tid: u64, pid: u64) -> Result<(), String> {
+    async fn delete_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<(), StoreError> {
         if let Some(times) = self.times.get_mut(&tid) {
-            if let Some(_) = times.posts.remove(&pid) {
+            if let Some(post) = times.posts.remove(&pid) {
+                let now = Utc::now();
+                times.trash.insert(pid, (post.clone(), now));
+                self.unindex_post(tid, &post);
                 Ok(())
             } else {
-                Err("Invalid pid".to_string())
+                Err(StoreError::NotFound(format!("post {pid}")))
             }
         } else {
-            Err("Invalid tid".to_string())
+            Err(StoreError::NotFound(format!("times {tid}")))
         }
     }
 
     async fn get_latest_post(
         &mut self,
         tid: u64,
-    ) -> Result<Option<Post>, String> {
-        if let Some(ltimes) = self.times.get(&tid) {
-            let keys: Vec<u64> = ltimes.posts.clone().into_keys().collect();
-            if let Some(latest_pid) = keys.iter().max() {
-                if let Some(post) = ltimes.posts.get(latest_pid) {
-                    return Ok(Some(post.clone()));
-                }
+    ) -> Result<Option<Post>, StoreError> {
+        let ltimes = self
+            .times
+            .get(&tid)
+            .ok_or(StoreError::NotFound(format!("times {tid}")))?;
+
+        Ok(ltimes.posts.values().max_by_key(|p| p.id).cloned())
+    }
+
+    async fn list_revisions(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<Vec<Revision>, StoreError> {
+        let ltimes = self
+            .times
+            .get(&tid)
+            .ok_or(StoreError::NotFound(format!("times {tid}")))?;
+
+        if !ltimes.posts.contains_key(&pid) {
+            return Err(StoreError::NotFound(format!("post {pid}")));
+        }
+
+        Ok(ltimes.revisions.get(&pid).cloned().unwrap_or_default())
+    }
+
+    async fn attach(
+        &mut self,
+        tid: u64,
+        pid: u64,
+        name: String,
+        mime: String,
+        data: Vec<u8>,
+    ) -> Result<Attachment, StoreError> {
+        let ltimes = self
+            .times
+            .get(&tid)
+            .ok_or(StoreError::NotFound(format!("times {tid}")))?;
+        if !ltimes.posts.contains_key(&pid) {
+            return Err(StoreError::NotFound(format!("post {pid}")));
+        }
+
+        check_attachment(&name, &mime, &data)?;
+
+        let attachment = Attachment {
+            id: self.next_aid,
+            pid,
+            name,
+            mime,
+            size: data.len() as u64,
+            hash: content_hash(&data),
+            created_at: Utc::now(),
+        };
+        self.next_aid += 1;
+
+        self.blobs.entry(attachment.hash.clone()).or_insert(data);
+        self.attachments
+            .insert(attachment.id, (tid, attachment.clone()));
+
+        Ok(attachment)
+    }
+
+    async fn list_attachments(
+        &mut self,
+        tid: u64,
+    ) -> Result<Vec<Attachment>, StoreError> {
+        let ltimes = self
+            .times
+            .get(&tid)
+            .ok_or(StoreError::NotFound(format!("times {tid}")))?;
+
+        Ok(self
+            .attachments
+            .values()
+            .filter(|(t, a)| *t == tid && ltimes.posts.contains_key(&a.pid))
+            .map(|(_, a)| a.clone())
+            .collect())
+    }
+
+    async fn get_attachment(
+        &mut self,
+        tid: u64,
+        aid: u64,
+    ) -> Result<Attachment, StoreError> {
+        self.find_attachment(tid, aid).cloned()
+    }
+
+    async fn read_attachment(
+        &mut self,
+        tid: u64,
+        aid: u64,
+    ) -> Result<Vec<u8>, StoreError> {
+        let attachment = self.find_attachment(tid, aid)?;
+
+        self.blobs
+            .get(&attachment.hash)
+            .cloned()
+            .ok_or(StoreError::Backend(format!(
+                "content of attachment {aid} is missing"
+            )))
+    }
+
+    async fn detach(&mut self, tid: u64, aid: u64) -> Result<(), StoreError> {
+        self.find_attachment(tid, aid)?;
+
+        self.attachments.remove(&aid);
+        self.collect_attachments();
+
+        Ok(())
+    }
+
+    async fn search_posts(
+        &mut self,
+        query: String,
+        filter: SearchFilter,
+    ) -> Result<Vec<TimesPost>, StoreError> {
+        let posts = self.times.iter().flat_map(|(tid, ltimes)| {
+            ltimes.posts.values().map(move |p| (*tid, p))
+        });
+
+        scan_posts(&query, &filter, posts)
+    }
+
+    async fn list_tags(&mut self) -> Result<Vec<Tag>, StoreError> {
+        let mut tags: Vec<Tag> = self
+            .tags
+            .iter()
+            .map(|(name, posts)| Tag {
+                name: name.clone(),
+                count: posts.len() as u64,
+            })
+            .collect();
+        tags.sort_by(|a, b| b.count.cmp(&a.count).then(a.name.cmp(&b.name)));
+
+        Ok(tags)
+    }
+
+    async fn get_posts_by_tag(
+        &mut self,
+        tag: String,
+    ) -> Result<Vec<TimesPost>, StoreError> {
+        let tag = normalize_tag(&tag)?;
+
+        let Some(ids) = self.tags.get(&tag) else {
+            return Ok(vec![]);
+        };
+
+        let mut posts: Vec<TimesPost> = ids
+            .iter()
+            .filter_map(|(tid, pid)| {
+                let post = self.times.get(tid)?.posts.get(pid)?;
+                Some(TimesPost {
+                    tid: *tid,
+                    post: post.clone(),
+                })
+            })
+            .collect();
+        sort_newest_first(&mut posts);
+
+        Ok(posts)
+    }
+
+    async fn list_trash(&mut self) -> Result<Trash, StoreError> {
+        let mut times: Vec<TrashedTimes> = self
+            .trash
+            .values()
+            .map(|(ltimes, deleted_at)| TrashedTimes {
+                times: ltimes.times.clone(),
+                deleted_at: *deleted_at,
+            })
+            .collect();
+        times.sort_by(|a, b| {
+            (b.deleted_at, b.times.id).cmp(&(a.deleted_at, a.times.id))
+        });
+
+        let mut posts: Vec<TrashedPost> = self
+            .times
+            .iter()
+            .flat_map(|(tid, ltimes)| {
+                ltimes.trash.values().map(|(post, deleted_at)| TrashedPost {
+                    tid: *tid,
+                    post: post.clone(),
+                    deleted_at: *deleted_at,
+                })
+            })
+            .collect();
+        posts.sort_by(|a, b| {
+            (b.deleted_at, b.post.id).cmp(&(a.deleted_at, a.post.id))
+        });
+
+        Ok(Trash { times, posts })
+    }
+
+    async fn restore_times(&mut self, tid: u64) -> Result<Times, StoreError> {
+        let (ltimes, _) = self
+            .trash
+            .remove(&tid)
+            .ok_or(StoreError::NotFound(format!("times {tid} in the trash")))?;
+
+        let times = ltimes.times.clone();
+        let posts: Vec<Post> = ltimes.posts.values().cloned().collect();
+        self.times.insert(tid, ltimes);
+        for post in &posts {
+            self.index_post(tid, post);
+        }
+
+        Ok(times)
+    }
+
+    async fn restore_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<Post, StoreError> {
+        if self.trash.contains_key(&tid) {
+            return Err(StoreError::Conflict(format!(
+                "times {tid} is in the trash"
+            )));
+        }
+
+        let ltimes = self
+            .times
+            .get_mut(&tid)
+            .ok_or(StoreError::NotFound(format!("times {tid}")))?;
+        let (post, _) = ltimes
+            .trash
+            .remove(&pid)
+            .ok_or(StoreError::NotFound(format!("post {pid} in the trash")))?;
+
+        ltimes.posts.insert(pid, post.clone());
+        self.index_post(tid, &post);
+
+        Ok(post)
+    }
+
+    async fn purge_times(&mut self, tid: u64) -> Result<(), StoreError> {
+        match self.trash.remove(&tid) {
+            Some(_) => {
+                self.collect_attachments();
+                Ok(())
+            }
+            None => {
+                Err(StoreError::NotFound(format!("times {tid} in the trash")))
+            }
+        }
+    }
+
+    async fn purge_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<(), StoreError> {
+        let ltimes = self.find_times_mut(tid)?;
+
+        match ltimes.trash.remove(&pid) {
+            Some(_) => {
+                ltimes.revisions.remove(&pid);
+                self.collect_attachments();
+                Ok(())
+            }
+            None => {
+                Err(StoreError::NotFound(format!("post {pid} in the trash")))
             }
         }
+    }
+
+    async fn purge_trash(
+        &mut self,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<u64, StoreError> {
+        let expired = |deleted_at: &DateTime<Utc>| {
+            before.is_none_or(|before| *deleted_at < before)
+        };
+
+        let mut purged = 0;
+
+        let len = self.trash.len();
+        self.trash.retain(|_, (_, deleted_at)| !expired(deleted_at));
+        purged += len - self.trash.len();
+
+        for ltimes in self.times.values_mut() {
+            let len = ltimes.trash.len();
+            ltimes
+                .trash
+                .retain(|_, (_, deleted_at)| !expired(deleted_at));
+            purged += len - ltimes.trash.len();
+
+            let LocalTimes {
+                posts,
+                trash,
+                revisions,
+                ..
+            } = ltimes;
+            revisions.retain(|pid, _| {
+                posts.contains_key(pid) || trash.contains_key(pid)
+            });
+        }
+
+        self.collect_attachments();
+
+        Ok(purged as u64)
+    }
+
+    async fn create_user(&mut self, name: String) -> Result<User, StoreError> {
+        check_user_name(&name)?;
+        if self.users.contains_key(&name) {
+            return Err(StoreError::Conflict(format!("user {name} exists")));
+        }
+
+        let user = User {
+            id: self.next_uid,
+            name: name.clone(),
+            created_at: Utc::now(),
+        };
+        self.next_uid += 1;
+        self.users.insert(name, user.clone());
+
+        Ok(user)
+    }
+
+    async fn list_users(&mut self) -> Result<Vec<User>, StoreError> {
+        let mut users: Vec<User> = self.users.values().cloned().collect();
+        users.sort_by_key(|u| u.id);
+
+        Ok(users)
+    }
+
+    async fn create_times_for(
+        &mut self,
+        owner: String,
+        title: String,
+    ) -> Result<Times, StoreError> {
+        self.find_user(&owner)?;
+        Ok(self.insert_times(title, Some(owner)))
+    }
+
+    async fn create_post_by(
+        &mut self,
+        tid: u64,
+        author: String,
+        post: String,
+    ) -> Result<Post, StoreError> {
+        self.find_user(&author)?;
+        self.insert_post(tid, post, Some(author))
+    }
+
+    async fn get_acl(&mut self, tid: u64) -> Result<Acl, StoreError> {
+        Ok(self.find_times_mut(tid)?.acl.clone())
+    }
+
+    async fn set_acl(&mut self, tid: u64, acl: Acl) -> Result<Acl, StoreError> {
+        let owner = self.find_times_mut(tid)?.acl.owner.clone();
+        let acl = normalize_acl(acl, owner)?;
+        for name in acl.readers.iter().chain(&acl.writers) {
+            self.find_user(name)?;
+        }
+
+        self.find_times_mut(tid)?.acl = acl.clone();
+        Ok(acl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        Ok(None)
+    #[tokio::test]
+    async fn conformance() {
+        let mut store = RamStore::new();
+        crate::testsuite::run(&mut store).await.assert_ok();
     }
 }
@@ -1,22 +1,39 @@
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
-use super::{Post, Store, Times};
+use super::{
+    Post, PostPage, PostQuery, SearchFilter, Store, StoreError, Times,
+};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Method;
+use timesman_type::{
+    Acl, Attachment, Change, Revision, Tag, TimesPost, Trash, TrashedPost,
+    TrashedTimes,
+};
+use tokio::sync::broadcast;
+
+/// Changes buffered between the event stream and the subscribers.
+const CHANGE_BUFFER: usize = 256;
 
 #[derive(Deserialize, Clone)]
 struct RemPost {
     pub id: u64,
     pub post: String,
-    pub created_at: chrono::NaiveDateTime,
-    pub updated_at: Option<chrono::NaiveDateTime>,
+    #[serde(default)]
+    pub author: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Deserialize, Clone)]
 struct RemTimes {
     pub id: u64,
     pub title: String,
-    pub created_at: chrono::NaiveDateTime,
-    pub updated_at: Option<chrono::NaiveDateTime>,
+    #[serde(default)]
+    pub owner: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 impl From<RemTimes> for Times {
@@ -24,6 +41,7 @@ impl From<RemTimes> for Times {
         Self {
             id: value.id,
             title: value.title,
+            owner: value.owner,
             created_at: value.created_at,
             updated_at: value.updated_at,
         }
@@ -35,76 +53,270 @@ impl From<RemPost> for Post {
         Self {
             id: value.id,
             post: value.post,
+            author: value.author,
             created_at: value.created_at,
             updated_at: value.updated_at,
         }
     }
 }
 
+#[derive(Deserialize, Clone)]
+struct RemTimesPost {
+    pub tid: u64,
+    pub post: RemPost,
+}
+
+impl From<RemTimesPost> for TimesPost {
+    fn from(value: RemTimesPost) -> Self {
+        Self {
+            tid: value.tid,
+            post: value.post.into(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+struct RemAcl {
+    pub owner: Option<String>,
+    pub readers: Vec<String>,
+    pub writers: Vec<String>,
+    pub public: bool,
+}
+
+impl From<RemAcl> for Acl {
+    fn from(value: RemAcl) -> Self {
+        Self {
+            owner: value.owner,
+            readers: value.readers,
+            writers: value.writers,
+            public: value.public,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+struct RemTag {
+    pub name: String,
+    pub count: u64,
+}
+
+impl From<RemTag> for Tag {
+    fn from(value: RemTag) -> Self {
+        Self {
+            name: value.name,
+            count: value.count,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+struct RemRevision {
+    pub rev: u64,
+    pub post: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<RemRevision> for Revision {
+    fn from(value: RemRevision) -> Self {
+        Self {
+            rev: value.rev,
+            post: value.post,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+struct RemAttachment {
+    pub id: u64,
+    pub pid: u64,
+    pub name: String,
+    pub mime: String,
+    pub size: u64,
+    pub hash: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<RemAttachment> for Attachment {
+    fn from(value: RemAttachment) -> Self {
+        Self {
+            id: value.id,
+            pid: value.pid,
+            name: value.name,
+            mime: value.mime,
+            size: value.size,
+            hash: value.hash,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+struct RemTrashedTimes {
+    pub times: RemTimes,
+    pub deleted_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Clone)]
+struct RemTrashedPost {
+    pub tid: u64,
+    pub post: RemPost,
+    pub deleted_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Clone)]
+struct RemTrash {
+    pub times: Vec<RemTrashedTimes>,
+    pub posts: Vec<RemTrashedPost>,
+}
+
+impl From<RemTrash> for Trash {
+    fn from(value: RemTrash) -> Self {
+        Self {
+            times: value
+                .times
+                .into_iter()
+                .map(|t| TrashedTimes {
+                    times: t.times.into(),
+                    deleted_at: t.deleted_at,
+                })
+                .collect(),
+            posts: value
+                .posts
+                .into_iter()
+                .map(|p| TrashedPost {
+                    tid: p.tid,
+                    post: p.post.into(),
+                    deleted_at: p.deleted_at,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The body the server sends with every failed request.
 #[derive(Deserialize)]
-struct ResponseBase {
-    status: u64,
-    text: String,
+struct ErrorResponse {
+    error: ErrorDetail,
 }
 
+#[derive(Deserialize)]
+struct ErrorDetail {
+    message: String,
+}
+
+impl From<reqwest::Error> for StoreError {
+    fn from(value: reqwest::Error) -> Self {
+        StoreError::Backend(format!("{value}"))
+    }
+}
+
+/// Decodes a successful response, or maps the HTTP status of a failed one
+/// back to the [`StoreError`] the server reported.
+async fn parse_response<T: DeserializeOwned>(
+    resp: reqwest::Response,
+) -> Result<T, StoreError> {
+    Ok(check_response(resp).await?.json::<T>().await?)
+}
+
+/// Passes a successful response through, or maps the HTTP status of a
+/// failed one back to the [`StoreError`] the server reported.
+async fn check_response(
+    resp: reqwest::Response,
+) -> Result<reqwest::Response, StoreError> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+
+    let text = match resp.json::<ErrorResponse>().await {
+        Ok(body) => body.error.message,
+        Err(_) => status.to_string(),
+    };
+
+    Err(match status {
+        reqwest::StatusCode::NOT_FOUND => StoreError::NotFound(text),
+        reqwest::StatusCode::BAD_REQUEST
+        | reqwest::StatusCode::UNPROCESSABLE_ENTITY => {
+            StoreError::InvalidArgument(text)
+        }
+        reqwest::StatusCode::NOT_IMPLEMENTED
+        | reqwest::StatusCode::METHOD_NOT_ALLOWED => {
+            StoreError::Unsupported(text)
+        }
+        reqwest::StatusCode::CONFLICT => StoreError::Conflict(text),
+        reqwest::StatusCode::UNAUTHORIZED => StoreError::Unauthenticated(text),
+        reqwest::StatusCode::FORBIDDEN => StoreError::PermissionDenied(text),
+        _ => StoreError::Backend(text),
+    })
+}
+
+/// The path of the REST API on the server.
+const API_PATH: &str = "/api/v1";
+
 pub struct RemoteStore {
     server: String,
+    client: reqwest::Client,
+    token: Option<String>,
 }
 
 impl RemoteStore {
     pub fn new(mut server: String) -> Self {
-        let server = if server.ends_with('/') {
+        if server.ends_with('/') {
             server.pop();
-            server
-        } else {
-            server
-        };
+        }
 
-        Self { server }
+        Self {
+            server: server + API_PATH,
+            client: reqwest::Client::new(),
+            token: None,
+        }
+    }
+
+    /// Authenticates every request with the API token `token`.
+    pub fn with_token(mut self, token: String) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    fn request<U: reqwest::IntoUrl>(
+        &self,
+        method: Method,
+        url: U,
+    ) -> reqwest::RequestBuilder {
+        let req = self.client.request(method, url);
+        match &self.token {
+            Some(token) => req.bearer_auth(token),
+            None => req,
+        }
     }
 }
 
 #[async_trait]
 impl Store for RemoteStore {
-    async fn check(&mut self) -> Result<(), String> {
+    async fn check(&mut self) -> Result<(), StoreError> {
         match self.get_times().await {
             Ok(_) => Ok(()),
             Err(e) => Err(e),
         }
     }
 
-    async fn get_times(&mut self) -> Result<Vec<Times>, String> {
+    async fn get_times(&mut self) -> Result<Vec<Times>, StoreError> {
         let url = self.server.clone() + "/times";
 
         // debug!("Request HTTP Get to {}", url);
 
-        #[derive(Deserialize)]
-        struct Response {
-            base: ResponseBase,
-            times: Vec<RemTimes>,
-        }
+        let times: Vec<RemTimes> =
+            parse_response(self.request(Method::GET, url).send().await?)
+                .await?;
 
-        let resp = reqwest::get(url)
-            .await
-            .map_err(|e| format!("{e}"))?
-            .json::<Response>()
-            .await
-            .map_err(|e| format!("{e}"))?;
-
-        let times = resp
-            .times
-            .iter()
-            .map(|rt| Times::from(rt.clone()))
-            .collect();
-
-        if resp.base.status != 0 {
-            Err(resp.base.text)
-        } else {
-            Ok(times)
-        }
+        Ok(times.into_iter().map(Times::from).collect())
     }
 
-    async fn create_times(&mut self, title: String) -> Result<Times, String> {
+    async fn create_times(
+        &mut self,
+        title: String,
+    ) -> Result<Times, StoreError> {
         let url = self.server.clone() + "/times";
 
         // debug!("Request HTTP Post to {}", url);
@@ -114,98 +326,114 @@ impl Store for RemoteStore {
             title: String,
         }
 
-        #[derive(Deserialize)]
-        struct CreateTimesResponse {
-            base: ResponseBase,
-            times: RemTimes,
-        }
-
         let data = CreateTimesRequest {
             title: title.to_string(),
         };
 
-        let client = reqwest::Client::new();
-        let result = client
-            .post(url)
-            .json(&data)
-            .send()
-            .await
-            .map_err(|e| format!("{e}"))?;
-
-        let resp = result
-            .json::<CreateTimesResponse>()
-            .await
-            .map_err(|e| format!("{e}"))?;
-
-        if resp.base.status != 0 {
-            Err(resp.base.text)
-        } else {
-            Ok(Times::from(resp.times))
-        }
+        let result = self.request(Method::POST, url).json(&data).send().await?;
+
+        let times: RemTimes = parse_response(result).await?;
+
+        Ok(Times::from(times))
     }
 
-    async fn delete_times(&mut self, tid: u64) -> Result<(), String> {
+    async fn delete_times(&mut self, tid: u64) -> Result<(), StoreError> {
         let url = format!("{}/times/{}", self.server, tid);
 
         // debug!("Request HTTP Delete to {}", self.server);
 
-        let client = reqwest::Client::new();
-        let result = client
-            .delete(url)
-            .send()
-            .await
-            .map_err(|e| format!("{e}"))?;
+        let result = self.request(Method::DELETE, url).send().await?;
+
+        check_response(result).await?;
+
+        Ok(())
+    }
 
-        let resp = result
-            .json::<ResponseBase>()
-            .await
-            .map_err(|e| format!("{e}"))?;
+    async fn update_times(
+        &mut self,
+        times: Times,
+    ) -> Result<Times, StoreError> {
+        let url = format!("{}/times/{}", self.server, times.id);
 
-        if resp.status != 0 {
-            return Err(format!("request error: {}", resp.text));
+        #[derive(Serialize)]
+        struct Request {
+            title: String,
         }
 
-        Ok(())
+        let data = Request { title: times.title };
+
+        let result = self.request(Method::PUT, url).json(&data).send().await?;
+
+        let times: RemTimes = parse_response(result).await?;
+
+        Ok(Times::from(times))
     }
 
-    async fn update_times(&mut self, _times: Times) -> Result<Times, String> {
-        unimplemented!();
+    async fn get_posts(&mut self, tid: u64) -> Result<Vec<Post>, StoreError> {
+        let mut posts = vec![];
+        let mut query = PostQuery::default();
+
+        // the server pages its answers, so follow the cursor to the end
+        loop {
+            let page = self.get_posts_page(tid, query).await?;
+            posts.extend(page.posts);
+
+            match page.next {
+                Some(cursor) => {
+                    query = PostQuery {
+                        cursor: Some(cursor),
+                        ..Default::default()
+                    }
+                }
+                None => return Ok(posts),
+            }
+        }
     }
 
-    async fn get_posts(&mut self, tid: u64) -> Result<Vec<Post>, String> {
-        let url = format!("{}/times/{}", self.server, tid);
+    async fn get_posts_page(
+        &mut self,
+        tid: u64,
+        query: PostQuery,
+    ) -> Result<PostPage, StoreError> {
+        let url = format!("{}/times/{}/posts", self.server, tid);
 
-        // debug!("Request HTTP Get to {}", url);
+        #[derive(Serialize)]
+        struct Request {
+            cursor: Option<u64>,
+            since: Option<DateTime<Utc>>,
+            until: Option<DateTime<Utc>>,
+            limit: Option<usize>,
+        }
 
         #[derive(Deserialize)]
         struct Response {
-            base: ResponseBase,
             posts: Vec<RemPost>,
+            next: Option<u64>,
         }
 
-        let resp = reqwest::get(url)
-            .await
-            .map_err(|e| format!("{e}"))?
-            .json::<Response>()
-            .await
-            .map_err(|e| format!("{e}"))?;
+        let data = Request {
+            cursor: query.cursor,
+            since: query.since,
+            until: query.until,
+            limit: query.limit,
+        };
 
-        let posts =
-            resp.posts.iter().map(|rp| Post::from(rp.clone())).collect();
+        let result = self.request(Method::GET, url).query(&data).send().await?;
 
-        if resp.base.status != 0 {
-            Err(resp.base.text)
-        } else {
-            Ok(posts)
-        }
+        let resp: Response = parse_response(result).await?;
+
+        Ok(PostPage {
+            posts: resp.posts.into_iter().map(Post::from).collect(),
+            next: resp.next,
+        })
     }
 
     async fn create_post(
         &mut self,
         tid: u64,
         post: String,
-    ) -> Result<Post, String> {
-        let url = format!("{}/times/{}", self.server, tid);
+    ) -> Result<Post, StoreError> {
+        let url = format!("{}/times/{}/posts", self.server, tid);
 
         // debug!("Request HTTP Post to {}", self.server);
 
@@ -214,67 +442,408 @@ impl Store for RemoteStore {
             post: String,
         }
 
-        #[derive(Deserialize)]
-        struct Response {
-            base: ResponseBase,
-            pid: u64,
-        }
-
         let data = Request {
             post: post.to_string(),
         };
 
-        let client = reqwest::Client::new();
-        let result = client
-            .post(url)
-            .json(&data)
-            .send()
-            .await
-            .map_err(|e| format!("{e}"))?;
+        let result = self.request(Method::POST, url).json(&data).send().await?;
 
-        let resp = result
-            .json::<Response>()
-            .await
-            .map_err(|e| format!("{e}"))?;
+        let post: RemPost = parse_response(result).await?;
 
-        if resp.base.status != 0 {
-            return Err(format!("request error: {}", resp.base.text));
-        }
-
-        Ok(Post {
-            id: resp.pid,
-            post: post.to_string(),
-            created_at: chrono::Utc::now().naive_utc(),
-            updated_at: None,
-        })
+        Ok(Post::from(post))
     }
 
     async fn delete_post(
         &mut self,
-        _tid: u64,
-        _pid: u64,
-    ) -> Result<(), String> {
-        unimplemented!();
+        tid: u64,
+        pid: u64,
+    ) -> Result<(), StoreError> {
+        let url = format!("{}/times/{}/posts/{}", self.server, tid, pid);
+
+        let result = self.request(Method::DELETE, url).send().await?;
+
+        check_response(result).await?;
+
+        Ok(())
     }
 
     async fn update_post(
         &mut self,
-        _tid: u64,
-        _post: Post,
-    ) -> Result<Post, String> {
-        unimplemented!();
+        tid: u64,
+        post: Post,
+    ) -> Result<Post, StoreError> {
+        let url = format!("{}/times/{}/posts/{}", self.server, tid, post.id);
+
+        #[derive(Serialize)]
+        struct Request {
+            post: String,
+        }
+
+        let data = Request { post: post.post };
+
+        let result = self.request(Method::PUT, url).json(&data).send().await?;
+
+        let post: RemPost = parse_response(result).await?;
+
+        Ok(Post::from(post))
     }
 
     async fn get_latest_post(
         &mut self,
         tid: u64,
-    ) -> Result<Option<Post>, String> {
-        let posts = self.get_posts(tid).await?;
+    ) -> Result<Option<Post>, StoreError> {
+        let url = format!("{}/times/{}/posts/latest", self.server, tid);
+
+        let resp = check_response(self.request(Method::GET, url).send().await?)
+            .await?;
+
+        // the times has no post yet
+        if resp.status() == reqwest::StatusCode::NO_CONTENT {
+            return Ok(None);
+        }
+
+        let post: RemPost = resp.json().await?;
+
+        Ok(Some(Post::from(post)))
+    }
+
+    async fn list_revisions(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<Vec<Revision>, StoreError> {
+        let url =
+            format!("{}/times/{}/posts/{}/revisions", self.server, tid, pid);
+
+        let revisions: Vec<RemRevision> =
+            parse_response(self.request(Method::GET, url).send().await?)
+                .await?;
+
+        Ok(revisions.into_iter().map(Revision::from).collect())
+    }
+
+    async fn get_revision(
+        &mut self,
+        tid: u64,
+        pid: u64,
+        rev: u64,
+    ) -> Result<Revision, StoreError> {
+        let url = format!(
+            "{}/times/{}/posts/{}/revisions/{}",
+            self.server, tid, pid, rev
+        );
+
+        let revision: RemRevision =
+            parse_response(self.request(Method::GET, url).send().await?)
+                .await?;
+
+        Ok(Revision::from(revision))
+    }
+
+    async fn revert_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+        rev: u64,
+    ) -> Result<Post, StoreError> {
+        let url = format!(
+            "{}/times/{}/posts/{}/revisions/{}/revert",
+            self.server, tid, pid, rev
+        );
+
+        let result = self.request(Method::POST, url).send().await?;
+
+        let post: RemPost = parse_response(result).await?;
+
+        Ok(Post::from(post))
+    }
+
+    async fn attach(
+        &mut self,
+        tid: u64,
+        pid: u64,
+        name: String,
+        mime: String,
+        data: Vec<u8>,
+    ) -> Result<Attachment, StoreError> {
+        let url =
+            format!("{}/times/{}/posts/{}/attachments", self.server, tid, pid);
+
+        let part = reqwest::multipart::Part::bytes(data)
+            .file_name(name)
+            .mime_str(&mime)
+            .map_err(|_| {
+                StoreError::InvalidArgument(format!(
+                    "invalid MIME type {mime:?}"
+                ))
+            })?;
+        let form = reqwest::multipart::Form::new().part("file", part);
+
+        let result = self
+            .request(Method::POST, url)
+            .multipart(form)
+            .send()
+            .await?;
+
+        let attachment: RemAttachment = parse_response(result).await?;
+
+        Ok(Attachment::from(attachment))
+    }
+
+    async fn list_attachments(
+        &mut self,
+        tid: u64,
+    ) -> Result<Vec<Attachment>, StoreError> {
+        let url = format!("{}/times/{}/attachments", self.server, tid);
+
+        let attachments: Vec<RemAttachment> =
+            parse_response(self.request(Method::GET, url).send().await?)
+                .await?;
+
+        Ok(attachments.into_iter().map(Attachment::from).collect())
+    }
+
+    async fn read_attachment(
+        &mut self,
+        tid: u64,
+        aid: u64,
+    ) -> Result<Vec<u8>, StoreError> {
+        let url = format!("{}/times/{}/attachments/{}", self.server, tid, aid);
+
+        let resp = check_response(self.request(Method::GET, url).send().await?)
+            .await?;
+
+        Ok(resp.bytes().await?.to_vec())
+    }
+
+    async fn detach(&mut self, tid: u64, aid: u64) -> Result<(), StoreError> {
+        let url = format!("{}/times/{}/attachments/{}", self.server, tid, aid);
+
+        let result = self.request(Method::DELETE, url).send().await?;
+
+        check_response(result).await?;
+
+        Ok(())
+    }
+
+    async fn search_posts(
+        &mut self,
+        query: String,
+        filter: SearchFilter,
+    ) -> Result<Vec<TimesPost>, StoreError> {
+        let url = format!("{}/search", self.server);
+
+        #[derive(Serialize)]
+        struct Request {
+            q: String,
+            tid: Option<u64>,
+            since: Option<DateTime<Utc>>,
+            until: Option<DateTime<Utc>>,
+            limit: Option<usize>,
+        }
+
+        let data = Request {
+            q: query,
+            tid: filter.tid,
+            since: filter.since,
+            until: filter.until,
+            limit: filter.limit,
+        };
+
+        let result = self.request(Method::GET, url).query(&data).send().await?;
+
+        let posts: Vec<RemTimesPost> = parse_response(result).await?;
+
+        Ok(posts.into_iter().map(TimesPost::from).collect())
+    }
+
+    async fn get_acl(&mut self, tid: u64) -> Result<Acl, StoreError> {
+        let url = format!("{}/times/{}/acl", self.server, tid);
+
+        let acl: RemAcl =
+            parse_response(self.request(Method::GET, url).send().await?)
+                .await?;
+
+        Ok(acl.into())
+    }
+
+    async fn set_acl(&mut self, tid: u64, acl: Acl) -> Result<Acl, StoreError> {
+        let url = format!("{}/times/{}/acl", self.server, tid);
+
+        #[derive(Serialize)]
+        struct Request {
+            readers: Vec<String>,
+            writers: Vec<String>,
+            public: bool,
+        }
+
+        let data = Request {
+            readers: acl.readers,
+            writers: acl.writers,
+            public: acl.public,
+        };
+
+        let result = self.request(Method::PUT, url).json(&data).send().await?;
+
+        let acl: RemAcl = parse_response(result).await?;
+
+        Ok(acl.into())
+    }
+
+    async fn list_tags(&mut self) -> Result<Vec<Tag>, StoreError> {
+        let url = format!("{}/tags", self.server);
+
+        let tags: Vec<RemTag> =
+            parse_response(self.request(Method::GET, url).send().await?)
+                .await?;
+
+        Ok(tags.into_iter().map(Tag::from).collect())
+    }
+
+    async fn get_posts_by_tag(
+        &mut self,
+        tag: String,
+    ) -> Result<Vec<TimesPost>, StoreError> {
+        let mut url = reqwest::Url::parse(&self.server)
+            .map_err(|e| StoreError::InvalidArgument(format!("{e}")))?;
+        url.path_segments_mut()
+            .map_err(|_| {
+                StoreError::InvalidArgument(format!(
+                    "{} cannot be a base url",
+                    self.server
+                ))
+            })?
+            .extend(["tags", &tag]);
+
+        let posts: Vec<RemTimesPost> =
+            parse_response(self.request(Method::GET, url).send().await?)
+                .await?;
+
+        Ok(posts.into_iter().map(TimesPost::from).collect())
+    }
+
+    async fn list_trash(&mut self) -> Result<Trash, StoreError> {
+        let url = format!("{}/trash", self.server);
+
+        let trash: RemTrash =
+            parse_response(self.request(Method::GET, url).send().await?)
+                .await?;
+
+        Ok(trash.into())
+    }
+
+    async fn restore_times(&mut self, tid: u64) -> Result<Times, StoreError> {
+        let url = format!("{}/trash/times/{}/restore", self.server, tid);
+
+        let result = self.request(Method::POST, url).send().await?;
+
+        let times: RemTimes = parse_response(result).await?;
+
+        Ok(times.into())
+    }
+
+    async fn restore_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<Post, StoreError> {
+        let url = format!(
+            "{}/trash/times/{}/posts/{}/restore",
+            self.server, tid, pid
+        );
+
+        let result = self.request(Method::POST, url).send().await?;
+
+        let post: RemPost = parse_response(result).await?;
+
+        Ok(post.into())
+    }
+
+    async fn purge_times(&mut self, tid: u64) -> Result<(), StoreError> {
+        let url = format!("{}/trash/times/{}", self.server, tid);
+
+        let result = self.request(Method::DELETE, url).send().await?;
+
+        check_response(result).await?;
+
+        Ok(())
+    }
+
+    async fn purge_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<(), StoreError> {
+        let url = format!("{}/trash/times/{}/posts/{}", self.server, tid, pid);
+
+        let result = self.request(Method::DELETE, url).send().await?;
+
+        check_response(result).await?;
 
-        if let Some(p) = posts.iter().max_by_key(|p| p.id) {
-            return Ok(Some(p.clone()));
+        Ok(())
+    }
+
+    async fn purge_trash(
+        &mut self,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<u64, StoreError> {
+        let url = format!("{}/trash", self.server);
+
+        #[derive(Serialize)]
+        struct Request {
+            before: Option<DateTime<Utc>>,
         }
 
-        Ok(None)
+        #[derive(Deserialize)]
+        struct Response {
+            purged: u64,
+        }
+
+        let result = self
+            .request(Method::DELETE, url)
+            .query(&Request { before })
+            .send()
+            .await?;
+
+        let resp: Response = parse_response(result).await?;
+
+        Ok(resp.purged)
+    }
+
+    async fn subscribe(
+        &mut self,
+    ) -> Result<broadcast::Receiver<Change>, StoreError> {
+        let url = format!("{}/events", self.server);
+
+        let result = self.request(Method::GET, url).send().await?;
+        let mut resp = check_response(result).await?;
+
+        let (tx, rx) = broadcast::channel(CHANGE_BUFFER);
+
+        // the server ends the stream when we fall behind, which closes the
+        // channel and tells the subscribers to subscribe again
+        tokio::spawn(async move {
+            let mut buf = Vec::new();
+            while let Ok(Some(chunk)) = resp.chunk().await {
+                buf.extend_from_slice(&chunk);
+
+                while let Some(end) = buf.iter().position(|b| *b == b'\n') {
+                    let line: Vec<u8> = buf.drain(..=end).collect();
+                    let Some(data) = line.strip_prefix(b"data:") else {
+                        continue;
+                    };
+                    let Ok(change) = serde_json::from_slice::<Change>(data)
+                    else {
+                        continue;
+                    };
+                    if tx.send(change).is_err() {
+                        // every subscriber is gone
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(rx)
     }
 }
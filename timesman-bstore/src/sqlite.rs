@@ -1,17 +1,38 @@
-use super::{Post, Store, Times};
+use super::{
+    check_attachment, check_user_name, content_hash, normalize_acl,
+    normalize_tag, search_terms, Post, PostPage, PostQuery, SearchFilter,
+    Store, StoreError, Times,
+};
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use sqlx;
-use sqlx::sqlite::SqlitePool;
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::{
+    Sqlite, SqliteConnectOptions, SqliteConnection, SqlitePool,
+};
+use sqlx::QueryBuilder;
+use timesman_type::{
+    Acl, Attachment, Revision, Tag, TimesPost, Trash, TrashedPost,
+    TrashedTimes, User,
+};
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+// Timestamps are stored as RFC 3339 text in UTC, the way sqlx encodes a
+// `DateTime<Utc>`, so they sort in order as strings. sqlx reads the
+// `datetime` columns as a `NaiveDateTime`, which is the same time in UTC.
 
 #[derive(Clone)]
 struct SqliteTimes {
     pub id: i64,
     pub title: String,
+    pub owner: Option<String>,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: Option<chrono::NaiveDateTime>,
-    pub deleted: i64,
 }
 
 impl From<SqliteTimes> for Times {
@@ -19,17 +40,19 @@ impl From<SqliteTimes> for Times {
         Times {
             id: value.id as u64,
             title: value.title,
-            created_at: value.created_at,
-            updated_at: value.updated_at,
+            owner: value.owner,
+            created_at: value.created_at.and_utc(),
+            updated_at: value.updated_at.map(|t| t.and_utc()),
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, sqlx::FromRow)]
 struct SqlitePost {
     pub id: i64,
     pub tid: i64,
     pub post: String,
+    pub author: Option<String>,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: Option<chrono::NaiveDateTime>,
 }
@@ -39,137 +62,1583 @@ impl From<SqlitePost> for Post {
         Self {
             id: value.id as u64,
             post: value.post,
-            created_at: value.created_at,
-            updated_at: value.updated_at,
+            author: value.author,
+            created_at: value.created_at.and_utc(),
+            updated_at: value.updated_at.map(|t| t.and_utc()),
+        }
+    }
+}
+
+impl From<sqlx::Error> for StoreError {
+    fn from(value: sqlx::Error) -> Self {
+        match value {
+            sqlx::Error::RowNotFound => {
+                StoreError::NotFound("no matching row".to_string())
+            }
+            e => StoreError::Backend(format!("{e}")),
         }
     }
 }
 
+struct SqliteTrashedTimes {
+    pub id: i64,
+    pub title: String,
+    pub owner: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: Option<chrono::NaiveDateTime>,
+    pub deleted_at: chrono::NaiveDateTime,
+}
+
+impl From<SqliteTrashedTimes> for TrashedTimes {
+    fn from(value: SqliteTrashedTimes) -> Self {
+        TrashedTimes {
+            times: Times {
+                id: value.id as u64,
+                title: value.title,
+                owner: value.owner,
+                created_at: value.created_at.and_utc(),
+                updated_at: value.updated_at.map(|t| t.and_utc()),
+            },
+            deleted_at: value.deleted_at.and_utc(),
+        }
+    }
+}
+
+struct SqliteTrashedPost {
+    pub id: i64,
+    pub tid: i64,
+    pub post: String,
+    pub author: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: Option<chrono::NaiveDateTime>,
+    pub deleted_at: chrono::NaiveDateTime,
+}
+
+impl From<SqliteTrashedPost> for TrashedPost {
+    fn from(value: SqliteTrashedPost) -> Self {
+        TrashedPost {
+            tid: value.tid as u64,
+            post: Post {
+                id: value.id as u64,
+                post: value.post,
+                author: value.author,
+                created_at: value.created_at.and_utc(),
+                updated_at: value.updated_at.map(|t| t.and_utc()),
+            },
+            deleted_at: value.deleted_at.and_utc(),
+        }
+    }
+}
+
+struct SqliteUser {
+    pub id: i64,
+    pub name: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl From<SqliteUser> for User {
+    fn from(value: SqliteUser) -> Self {
+        User {
+            id: value.id as u64,
+            name: value.name,
+            created_at: value.created_at.and_utc(),
+        }
+    }
+}
+
+struct SqliteTag {
+    pub name: String,
+    pub count: i64,
+}
+
+impl From<SqliteTag> for Tag {
+    fn from(value: SqliteTag) -> Self {
+        Tag {
+            name: value.name,
+            count: value.count as u64,
+        }
+    }
+}
+
+struct SqliteRevision {
+    pub rev: i64,
+    pub post: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl From<SqliteRevision> for Revision {
+    fn from(value: SqliteRevision) -> Self {
+        Revision {
+            rev: value.rev as u64,
+            post: value.post,
+            created_at: value.created_at.and_utc(),
+        }
+    }
+}
+
+struct SqliteAttachment {
+    pub id: i64,
+    pub pid: i64,
+    pub name: String,
+    pub mime: String,
+    pub size: i64,
+    pub hash: String,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl From<SqliteAttachment> for Attachment {
+    fn from(value: SqliteAttachment) -> Self {
+        Attachment {
+            id: value.id as u64,
+            pid: value.pid as u64,
+            name: value.name,
+            mime: value.mime,
+            size: value.size as u64,
+            hash: value.hash,
+            created_at: value.created_at.and_utc(),
+        }
+    }
+}
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// The migration which added `post_tags`.
+const TAGS_SCHEMA_VERSION: i64 = 20261018103758;
+
 pub struct SqliteStore {
     db: SqlitePool,
+    /// Keeps attachment content in files instead of the `blobs` table.
+    blob_dir: Option<PathBuf>,
 }
 
 pub struct SqliteStoreBuilder {
     dbfile: String,
+    blob_dir: Option<PathBuf>,
 }
 
 impl SqliteStoreBuilder {
     pub fn new(dbfile: &str) -> Self {
         Self {
             dbfile: dbfile.to_string(),
+            blob_dir: None,
         }
     }
 
-    pub async fn build(&self) -> Result<SqliteStore, String> {
-        let db = SqlitePool::connect(&self.dbfile)
+    /// Stores attachment content as files named by their hash under `dir`,
+    /// e.g. a directory beside the database, instead of in the database.
+    pub fn blob_dir(mut self, dir: &Path) -> Self {
+        self.blob_dir = Some(dir.to_path_buf());
+        self
+    }
+
+    pub async fn build(&self) -> Result<SqliteStore, StoreError> {
+        let options = SqliteConnectOptions::from_str(&self.dbfile)
+            .map_err(StoreError::from)?
+            .create_if_missing(true);
+
+        let db = SqlitePool::connect_with(options)
             .await
-            .map_err(|e| format!("{e}"))?;
+            .map_err(StoreError::from)?;
+
+        if let Some(dir) = &self.blob_dir {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                StoreError::Backend(format!(
+                    "failed to create {}: {e}",
+                    dir.display()
+                ))
+            })?;
+        }
+
+        let store = SqliteStore {
+            db,
+            blob_dir: self.blob_dir.clone(),
+        };
+        store.migrate().await?;
+
+        Ok(store)
+    }
+}
+
+impl SqliteStore {
+    /// The newest schema version this binary knows how to handle.
+    pub fn supported_schema_version() -> i64 {
+        MIGRATOR.iter().map(|m| m.version).max().unwrap_or(0)
+    }
+
+    /// The schema version recorded in the database, if it has been migrated.
+    pub async fn schema_version(&self) -> Result<Option<i64>, StoreError> {
+        let exists: bool = sqlx::query_scalar(
+            r#"select count(*) > 0 from sqlite_master
+                    where type = 'table' and name = '_sqlx_migrations'"#,
+        )
+        .fetch_one(&self.db)
+        .await
+        .map_err(StoreError::from)?;
+
+        if !exists {
+            return Ok(None);
+        }
+
+        sqlx::query_scalar(
+            r#"select max(version) from _sqlx_migrations where success = 1"#,
+        )
+        .fetch_one(&self.db)
+        .await
+        .map_err(StoreError::from)
+    }
+
+    async fn migrate(&self) -> Result<(), StoreError> {
+        let supported = Self::supported_schema_version();
+        if let Some(version) = self.schema_version().await? {
+            if version > supported {
+                return Err(StoreError::Backend(format!(
+                    "database schema version {version} is newer than \
+                     the supported version {supported}"
+                )));
+            }
+        }
+
+        let before = self.schema_version().await?;
+
+        MIGRATOR.run(&self.db).await.map_err(|e| {
+            StoreError::Backend(format!("failed to migrate the database: {e}"))
+        })?;
 
-        Ok(SqliteStore { db })
+        // tags can only be parsed here, so index the posts written before
+        if before.is_some_and(|v| v < TAGS_SCHEMA_VERSION) {
+            self.reindex_tags().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Rebuilds `post_tags` from the text of every post.
+    async fn reindex_tags(&self) -> Result<(), StoreError> {
+        let mut tx = self.db.begin().await?;
+
+        let posts = sqlx::query_as!(
+            SqlitePost,
+            r#"select id as "id!", tid, post, author, created_at, updated_at
+                    from posts"#
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for post in posts {
+            Self::set_tags(&mut tx, post.id, &post.post).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Replaces the tags recorded for a post with the ones in `text`.
+    async fn set_tags(
+        conn: &mut SqliteConnection,
+        pid: i64,
+        text: &str,
+    ) -> Result<(), StoreError> {
+        sqlx::query!(r#"delete from post_tags where pid = $1"#, pid)
+            .execute(&mut *conn)
+            .await?;
+
+        for tag in timesman_type::parse_tags(text) {
+            sqlx::query!(
+                r#"insert into post_tags(pid, tag) values ($1, $2)"#,
+                pid,
+                tag
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
     }
 }
 
 #[async_trait]
 impl Store for SqliteStore {
-    async fn check(&mut self) -> Result<(), String> {
+    async fn check(&mut self) -> Result<(), StoreError> {
         if !self.db.is_closed() {
             Ok(())
         } else {
-            Err("Closed".to_string())
+            Err(StoreError::Backend("database is closed".to_string()))
         }
     }
 
-    async fn get_times(&mut self) -> Result<Vec<Times>, String> {
+    async fn close(&mut self) -> Result<(), StoreError> {
+        // waits for the connections in use, so no transaction is cut short
+        self.db.close().await;
+        Ok(())
+    }
+
+    async fn get_times(&mut self) -> Result<Vec<Times>, StoreError> {
         let sql = sqlx::query_as!(
             SqliteTimes,
-            r#"select * from times where deleted = 0"#
+            r#"select id, title, owner, created_at, updated_at
+                    from times where deleted = 0 order by id"#
         )
         .fetch_all(&self.db);
 
-        let times = sql.await.map_err(|e| format!("{e}"))?;
+        let times = sql.await.map_err(StoreError::from)?;
 
         let result = times.iter().map(|st| Times::from(st.clone())).collect();
 
         Ok(result)
     }
 
-    async fn create_times(&mut self, title: String) -> Result<Times, String> {
+    async fn create_times(
+        &mut self,
+        title: String,
+    ) -> Result<Times, StoreError> {
+        self.insert_times(title, None).await
+    }
+
+    async fn update_times(
+        &mut self,
+        times: Times,
+    ) -> Result<Times, StoreError> {
+        let tid = times.id as i64;
+        let now = Utc::now();
         let sql = sqlx::query_as!(
             SqliteTimes,
-            r#"insert into times("title") values ($1) returning *"#,
-            title
+            r#"update times set title = $1, updated_at = $2
+                    where id = $3 and deleted = 0
+                    returning id, title, owner, created_at, updated_at"#,
+            times.title,
+            now,
+            tid
         )
-        .fetch_one(&self.db);
+        .fetch_optional(&self.db);
 
-        let times = sql.await.map_err(|e| format!("{}", e))?;
+        let times = sql.await.map_err(StoreError::from)?;
 
-        Ok(Times::from(times))
+        times
+            .map(Times::from)
+            .ok_or(StoreError::NotFound(format!("times {tid}")))
     }
 
-    async fn update_times(&mut self, _times: Times) -> Result<Times, String> {
-        unimplemented!();
-    }
+    async fn delete_times(&mut self, tid: u64) -> Result<(), StoreError> {
+        let tid = tid as i64;
+        let now = Utc::now();
+        let sql = sqlx::query!(
+            r#"update times set deleted = 1, deleted_at = $1
+                    where id = $2 and deleted = 0"#,
+            now,
+            tid
+        )
+        .execute(&self.db);
+
+        let result = sql.await.map_err(StoreError::from)?;
+
+        if result.rows_affected() == 0 {
+            return Err(StoreError::NotFound(format!("times {tid}")));
+        }
 
-    async fn delete_times(&mut self, _tid: u64) -> Result<(), String> {
-        unimplemented!();
+        Ok(())
     }
 
-    async fn get_posts(&mut self, tid: u64) -> Result<Vec<Post>, String> {
+    async fn get_posts(&mut self, tid: u64) -> Result<Vec<Post>, StoreError> {
+        self.find_times(tid).await?;
+
         let tid = tid as i64;
         let sql = sqlx::query_as!(
             SqlitePost,
-            r#"select * from posts where tid = $1"#,
+            r#"select id as "id!", tid, post, author, created_at, updated_at
+                    from posts where tid = $1 and deleted_at is null
+                    order by id"#,
             tid
         )
         .fetch_all(&self.db);
 
-        let posts = sql.await.map_err(|e| format!("{}", e))?;
+        let posts = sql.await.map_err(StoreError::from)?;
         let result = posts.iter().map(|sp| Post::from(sp.clone())).collect();
 
         Ok(result)
     }
 
+    async fn get_posts_page(
+        &mut self,
+        tid: u64,
+        query: PostQuery,
+    ) -> Result<PostPage, StoreError> {
+        if query.limit == Some(0) {
+            return Err(StoreError::InvalidArgument(
+                "page limit must not be zero".to_string(),
+            ));
+        }
+
+        self.find_times(tid).await?;
+
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            r#"select id, tid, post, author, created_at, updated_at from posts
+                where deleted_at is null and tid = "#,
+        );
+        qb.push_bind(tid as i64);
+
+        if let Some(cursor) = query.cursor {
+            qb.push(" and id < ").push_bind(cursor as i64);
+        }
+        if let Some(since) = query.since {
+            qb.push(" and created_at >= ").push_bind(since);
+        }
+        if let Some(until) = query.until {
+            qb.push(" and created_at < ").push_bind(until);
+        }
+
+        qb.push(" order by id desc");
+
+        // fetch one more row to find out whether an older page exists
+        if let Some(limit) = query.limit {
+            qb.push(" limit ").push_bind(limit as i64 + 1);
+        }
+
+        let mut posts: Vec<SqlitePost> =
+            qb.build_query_as().fetch_all(&self.db).await?;
+
+        let mut next = None;
+        if let Some(limit) = query.limit {
+            if posts.len() > limit {
+                posts.truncate(limit);
+                next = posts.last().map(|p| p.id as u64);
+            }
+        }
+
+        let posts = posts.into_iter().rev().map(Post::from).collect();
+
+        Ok(PostPage { posts, next })
+    }
+
     async fn create_post(
         &mut self,
         tid: u64,
         post: String,
-    ) -> Result<Post, String> {
+    ) -> Result<Post, StoreError> {
+        self.insert_post(tid, post, None).await
+    }
+
+    async fn delete_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<(), StoreError> {
+        self.find_post(tid, pid).await?;
+
+        let pid = pid as i64;
+        let now = Utc::now();
+        sqlx::query!(
+            r#"update posts set deleted_at = $1 where id = $2"#,
+            now,
+            pid
+        )
+        .execute(&self.db)
+        .await
+        .map_err(StoreError::from)?;
+
+        Ok(())
+    }
+
+    async fn update_post(
+        &mut self,
+        tid: u64,
+        post: Post,
+    ) -> Result<Post, StoreError> {
+        let old = self.find_post(tid, post.id).await?;
+
+        let mut tx = self.db.begin().await?;
+
+        let pid = post.id as i64;
+        if old.post != post.post {
+            let written_at = old.updated_at.unwrap_or(old.created_at).and_utc();
+            sqlx::query!(
+                r#"insert into post_revisions(pid, rev, post, created_at)
+                        select $1, coalesce(max(rev), 0) + 1, $2, $3
+                        from post_revisions where pid = $1"#,
+                pid,
+                old.post,
+                written_at
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let now = Utc::now();
+        let post = sqlx::query_as!(
+            SqlitePost,
+            r#"update posts set post = $1, updated_at = $2
+                    where id = $3
+                    returning id as "id!", tid, post, author, created_at,
+                        updated_at"#,
+            post.post,
+            now,
+            pid
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        Self::set_tags(&mut tx, post.id, &post.post).await?;
+        tx.commit().await?;
+
+        Ok(post.into())
+    }
+
+    async fn get_latest_post(
+        &mut self,
+        tid: u64,
+    ) -> Result<Option<Post>, StoreError> {
+        self.find_times(tid).await?;
+
         let tid = tid as i64;
         let sql = sqlx::query_as!(
             SqlitePost,
-            r#"insert into posts(tid, post)
-                    values ($1, $2)
-                    returning id as "id!", tid, post, created_at, updated_at"#,
-            tid,
-            post
+            r#"select id as "id!", tid, post, author, created_at, updated_at
+                    from posts where tid = $1 and deleted_at is null
+                    order by created_at desc, id desc limit 1"#,
+            tid
         )
-        .fetch_one(&self.db);
+        .fetch_optional(&self.db);
 
-        let post = sql.await.map_err(|e| format!("{}", e))?;
+        let post = sql.await.map_err(StoreError::from)?;
 
-        Ok(post.into())
+        Ok(post.map(Post::from))
     }
 
-    async fn delete_post(
+    async fn list_revisions(
         &mut self,
-        _tid: u64,
-        _pid: u64,
-    ) -> Result<(), String> {
-        unimplemented!();
+        tid: u64,
+        pid: u64,
+    ) -> Result<Vec<Revision>, StoreError> {
+        self.find_post(tid, pid).await?;
+
+        let pid = pid as i64;
+        let revisions = sqlx::query_as!(
+            SqliteRevision,
+            r#"select rev, post, created_at from post_revisions
+                    where pid = $1 order by rev"#,
+            pid
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(revisions.into_iter().map(Revision::from).collect())
     }
 
-    async fn update_post(
+    async fn get_revision(
         &mut self,
-        _tid: u64,
-        _post: Post,
-    ) -> Result<Post, String> {
-        unimplemented!();
+        tid: u64,
+        pid: u64,
+        rev: u64,
+    ) -> Result<Revision, StoreError> {
+        self.find_post(tid, pid).await?;
+
+        let (id, number) = (pid as i64, rev as i64);
+        let revision = sqlx::query_as!(
+            SqliteRevision,
+            r#"select rev, post, created_at from post_revisions
+                    where pid = $1 and rev = $2"#,
+            id,
+            number
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        revision
+            .map(Revision::from)
+            .ok_or(StoreError::NotFound(format!(
+                "revision {rev} of post {pid}"
+            )))
     }
 
-    async fn get_latest_post(
+    async fn attach(
+        &mut self,
+        tid: u64,
+        pid: u64,
+        name: String,
+        mime: String,
+        data: Vec<u8>,
+    ) -> Result<Attachment, StoreError> {
+        self.find_post(tid, pid).await?;
+        check_attachment(&name, &mime, &data)?;
+
+        let hash = content_hash(&data);
+        let (pid, size) = (pid as i64, data.len() as i64);
+
+        let mut tx = self.db.begin().await?;
+
+        match &self.blob_dir {
+            Some(dir) => Self::write_blob_file(dir, &hash, &data)?,
+            None => {
+                sqlx::query!(
+                    r#"insert into blobs(hash, data) values ($1, $2)
+                            on conflict(hash) do nothing"#,
+                    hash,
+                    data
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        let now = Utc::now();
+        let attachment = sqlx::query_as!(
+            SqliteAttachment,
+            r#"insert into attachments(pid, name, mime, size, hash, created_at)
+                    values ($1, $2, $3, $4, $5, $6)
+                    returning id as "id!", pid, name, mime, size, hash,
+                    created_at"#,
+            pid,
+            name,
+            mime,
+            size,
+            hash,
+            now
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(attachment.into())
+    }
+
+    async fn list_attachments(
+        &mut self,
+        tid: u64,
+    ) -> Result<Vec<Attachment>, StoreError> {
+        self.find_times(tid).await?;
+
+        let tid = tid as i64;
+        let attachments = sqlx::query_as!(
+            SqliteAttachment,
+            r#"select a.id as "id!", a.pid, a.name, a.mime, a.size, a.hash,
+                    a.created_at
+                    from attachments a join posts p on p.id = a.pid
+                    where p.tid = $1 and p.deleted_at is null
+                    order by a.id"#,
+            tid
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(attachments.into_iter().map(Attachment::from).collect())
+    }
+
+    async fn get_attachment(
+        &mut self,
+        tid: u64,
+        aid: u64,
+    ) -> Result<Attachment, StoreError> {
+        Ok(self.find_attachment(tid, aid).await?.into())
+    }
+
+    async fn read_attachment(
+        &mut self,
+        tid: u64,
+        aid: u64,
+    ) -> Result<Vec<u8>, StoreError> {
+        let attachment = self.find_attachment(tid, aid).await?;
+
+        let data = match &self.blob_dir {
+            Some(dir) => {
+                let path = Self::blob_path(dir, &attachment.hash);
+                std::fs::read(&path).ok()
+            }
+            None => {
+                sqlx::query_scalar!(
+                    r#"select data from blobs where hash = $1"#,
+                    attachment.hash
+                )
+                .fetch_optional(&self.db)
+                .await?
+            }
+        };
+
+        data.ok_or(StoreError::Backend(format!(
+            "content of attachment {aid} is missing"
+        )))
+    }
+
+    async fn detach(&mut self, tid: u64, aid: u64) -> Result<(), StoreError> {
+        self.find_attachment(tid, aid).await?;
+
+        let id = aid as i64;
+        sqlx::query!(r#"delete from attachments where id = $1"#, id)
+            .execute(&self.db)
+            .await?;
+
+        self.collect_blobs().await
+    }
+
+    async fn search_posts(
+        &mut self,
+        query: String,
+        filter: SearchFilter,
+    ) -> Result<Vec<TimesPost>, StoreError> {
+        let terms = search_terms(&query)?;
+
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            r#"select posts.id, posts.tid, posts.post,
+                    posts.author, posts.created_at, posts.updated_at
+                from posts_fts
+                join posts on posts.id = posts_fts.rowid
+                join times on times.id = posts.tid
+                where times.deleted = 0 and posts.deleted_at is null"#,
+        );
+
+        // the trigram index can only match terms of three or more characters
+        let (long, short): (Vec<&String>, Vec<&String>) =
+            terms.iter().partition(|t| t.chars().count() >= 3);
+
+        if !long.is_empty() {
+            let expr: Vec<String> = long
+                .iter()
+                .map(|t| format!("\"{}\"", t.replace('"', "\"\"")))
+                .collect();
+            qb.push(" and posts_fts match ")
+                .push_bind(expr.join(" AND "));
+        }
+
+        for t in short {
+            let pattern = t
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            qb.push(" and posts_fts.post like ")
+                .push_bind(format!("%{pattern}%"))
+                .push(r" escape '\'");
+        }
+
+        if let Some(tid) = filter.tid {
+            qb.push(" and posts.tid = ").push_bind(tid as i64);
+        }
+        if let Some(since) = filter.since {
+            qb.push(" and posts.created_at >= ").push_bind(since);
+        }
+        if let Some(until) = filter.until {
+            qb.push(" and posts.created_at < ").push_bind(until);
+        }
+
+        qb.push(" order by posts.created_at desc, posts.id desc");
+
+        if let Some(limit) = filter.limit {
+            qb.push(" limit ").push_bind(limit as i64);
+        }
+
+        let posts: Vec<SqlitePost> =
+            qb.build_query_as().fetch_all(&self.db).await?;
+
+        Ok(posts
+            .into_iter()
+            .map(|p| TimesPost {
+                tid: p.tid as u64,
+                post: p.into(),
+            })
+            .collect())
+    }
+
+    async fn list_tags(&mut self) -> Result<Vec<Tag>, StoreError> {
+        let tags = sqlx::query_as!(
+            SqliteTag,
+            r#"select post_tags.tag as name, count(*) as "count!: i64"
+                    from post_tags
+                    join posts on posts.id = post_tags.pid
+                    join times on times.id = posts.tid
+                    where times.deleted = 0 and posts.deleted_at is null
+                    group by post_tags.tag
+                    order by count(*) desc, post_tags.tag"#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(tags.into_iter().map(Tag::from).collect())
+    }
+
+    async fn get_posts_by_tag(
+        &mut self,
+        tag: String,
+    ) -> Result<Vec<TimesPost>, StoreError> {
+        let tag = normalize_tag(&tag)?;
+
+        let posts = sqlx::query_as!(
+            SqlitePost,
+            r#"select posts.id as "id!", posts.tid, posts.post,
+                    posts.author, posts.created_at, posts.updated_at
+                from post_tags
+                join posts on posts.id = post_tags.pid
+                join times on times.id = posts.tid
+                where times.deleted = 0 and posts.deleted_at is null
+                    and post_tags.tag = $1
+                order by posts.created_at desc, posts.id desc"#,
+            tag
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(posts
+            .into_iter()
+            .map(|p| TimesPost {
+                tid: p.tid as u64,
+                post: p.into(),
+            })
+            .collect())
+    }
+
+    async fn list_trash(&mut self) -> Result<Trash, StoreError> {
+        let times = sqlx::query_as!(
+            SqliteTrashedTimes,
+            r#"select id, title, owner, created_at, updated_at,
+                    deleted_at as "deleted_at!: chrono::NaiveDateTime"
+                from times where deleted = 1
+                order by deleted_at desc, id desc"#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let posts = sqlx::query_as!(
+            SqliteTrashedPost,
+            r#"select posts.id as "id!", posts.tid, posts.post,
+                    posts.author, posts.created_at, posts.updated_at,
+                    posts.deleted_at as "deleted_at!: chrono::NaiveDateTime"
+                from posts
+                join times on times.id = posts.tid
+                where times.deleted = 0 and posts.deleted_at is not null
+                order by posts.deleted_at desc, posts.id desc"#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(Trash {
+            times: times.into_iter().map(TrashedTimes::from).collect(),
+            posts: posts.into_iter().map(TrashedPost::from).collect(),
+        })
+    }
+
+    async fn restore_times(&mut self, tid: u64) -> Result<Times, StoreError> {
+        let id = tid as i64;
+        let times = sqlx::query_as!(
+            SqliteTimes,
+            r#"update times set deleted = 0, deleted_at = null
+                    where id = $1 and deleted = 1
+                    returning id, title, owner, created_at, updated_at"#,
+            id
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        times
+            .map(Times::from)
+            .ok_or(StoreError::NotFound(format!("times {tid} in the trash")))
+    }
+
+    async fn restore_post(
+        &mut self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<Post, StoreError> {
+        if self.is_trashed(tid).await? {
+            return Err(StoreError::Conflict(format!(
+                "times {tid} is in the trash"
+            )));
+        }
+
+        let (id, tid) = (pid as i64, tid as i64);
+        let post = sqlx::query_as!(
+            SqlitePost,
+            r#"update posts set deleted_at = null
+                    where id = $1 and tid = $2 and deleted_at is not null
+                    returning id as "id!", tid, post, author, created_at,
+                        updated_at"#,
+            id,
+            tid
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        post.map(Post::from)
+            .ok_or(StoreError::NotFound(format!("post {pid} in the trash")))
+    }
+
+    async fn purge_times(&mut self, tid: u64) -> Result<(), StoreError> {
+        if !self.is_trashed(tid).await? {
+            return Err(StoreError::NotFound(format!(
+                "times {tid} in the trash"
+            )));
+        }
+
+        let mut tx = self.db.begin().await?;
+
+        let id = tid as i64;
+        sqlx::query!(r#"delete from posts where tid = $1"#, id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(r#"delete from times where id = $1"#, id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        self.collect_blobs().await
+    }
+
+    async fn purge_post(
         &mut self,
         tid: u64,
-    ) -> Result<Option<Post>, String> {
-        Ok(None)
+        pid: u64,
+    ) -> Result<(), StoreError> {
+        self.find_times(tid).await?;
+
+        let (id, tid) = (pid as i64, tid as i64);
+        let result = sqlx::query!(
+            r#"delete from posts
+                    where id = $1 and tid = $2 and deleted_at is not null"#,
+            id,
+            tid
+        )
+        .execute(&self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(StoreError::NotFound(format!(
+                "post {pid} in the trash"
+            )));
+        }
+
+        self.collect_blobs().await
+    }
+
+    async fn purge_trash(
+        &mut self,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<u64, StoreError> {
+        let mut tx = self.db.begin().await?;
+
+        let posts = sqlx::query!(
+            r#"delete from posts
+                    where deleted_at is not null
+                    and ($1 is null or deleted_at < $1)
+                    and tid in (select id from times where deleted = 0)"#,
+            before
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"delete from posts where tid in (
+                    select id from times
+                    where deleted = 1 and ($1 is null or deleted_at < $1))"#,
+            before
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let times = sqlx::query!(
+            r#"delete from times
+                    where deleted = 1 and ($1 is null or deleted_at < $1)"#,
+            before
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        self.collect_blobs().await?;
+
+        Ok(posts.rows_affected() + times.rows_affected())
+    }
+
+    async fn create_user(&mut self, name: String) -> Result<User, StoreError> {
+        check_user_name(&name)?;
+
+        let now = Utc::now();
+        let user = sqlx::query_as!(
+            SqliteUser,
+            r#"insert into users(name, created_at) values ($1, $2)
+                    on conflict(name) do nothing
+                    returning id, name, created_at"#,
+            name,
+            now
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        user.map(User::from)
+            .ok_or(StoreError::Conflict(format!("user {name} exists")))
+    }
+
+    async fn list_users(&mut self) -> Result<Vec<User>, StoreError> {
+        let users = sqlx::query_as!(
+            SqliteUser,
+            r#"select id, name, created_at from users order by id"#
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(users.into_iter().map(User::from).collect())
+    }
+
+    async fn create_times_for(
+        &mut self,
+        owner: String,
+        title: String,
+    ) -> Result<Times, StoreError> {
+        self.find_user(&owner).await?;
+        self.insert_times(title, Some(owner)).await
+    }
+
+    async fn create_post_by(
+        &mut self,
+        tid: u64,
+        author: String,
+        post: String,
+    ) -> Result<Post, StoreError> {
+        self.find_user(&author).await?;
+        self.insert_post(tid, post, Some(author)).await
+    }
+
+    async fn get_visible_times(
+        &mut self,
+        user: String,
+    ) -> Result<Vec<Times>, StoreError> {
+        let times = sqlx::query_as!(
+            SqliteTimes,
+            r#"select id as "id!", title, owner, created_at, updated_at
+                    from times
+                    where deleted = 0
+                        and (owner is null or owner = $1 or public
+                            or exists (select 1 from times_acl
+                                where tid = times.id and user = $2))
+                    order by id"#,
+            user,
+            user
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(times.into_iter().map(Times::from).collect())
+    }
+
+    async fn get_acl(&mut self, tid: u64) -> Result<Acl, StoreError> {
+        let id = tid as i64;
+        let times = sqlx::query!(
+            r#"select owner, public as "public: bool" from times where id = $1"#,
+            id
+        )
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or(StoreError::NotFound(format!("times {tid}")))?;
+
+        let users = sqlx::query!(
+            r#"select user, writer as "writer: bool" from times_acl
+                    where tid = $1 order by user"#,
+            id
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let (writers, readers) =
+            users.into_iter().partition::<Vec<_>, _>(|u| u.writer);
+        Ok(Acl {
+            owner: times.owner,
+            readers: readers.into_iter().map(|u| u.user).collect(),
+            writers: writers.into_iter().map(|u| u.user).collect(),
+            public: times.public,
+        })
+    }
+
+    async fn set_acl(&mut self, tid: u64, acl: Acl) -> Result<Acl, StoreError> {
+        let owner = self.get_acl(tid).await?.owner;
+        let acl = normalize_acl(acl, owner)?;
+        for name in acl.readers.iter().chain(&acl.writers) {
+            self.find_user(name).await?;
+        }
+
+        let mut tx = self.db.begin().await?;
+
+        let id = tid as i64;
+        sqlx::query!(r#"delete from times_acl where tid = $1"#, id)
+            .execute(&mut *tx)
+            .await?;
+        let users = acl
+            .readers
+            .iter()
+            .map(|r| (r, false))
+            .chain(acl.writers.iter().map(|w| (w, true)));
+        for (user, writer) in users {
+            sqlx::query!(
+                r#"insert into times_acl(tid, user, writer) values ($1, $2, $3)"#,
+                id,
+                user,
+                writer
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        sqlx::query!(
+            r#"update times set public = $1 where id = $2"#,
+            acl.public,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(acl)
+    }
+}
+
+impl SqliteStore {
+    async fn insert_times(
+        &self,
+        title: String,
+        owner: Option<String>,
+    ) -> Result<Times, StoreError> {
+        let now = Utc::now();
+        let sql = sqlx::query_as!(
+            SqliteTimes,
+            r#"insert into times(title, owner, created_at) values ($1, $2, $3)
+                    returning id, title, owner, created_at, updated_at"#,
+            title,
+            owner,
+            now
+        )
+        .fetch_one(&self.db);
+
+        let times = sql.await.map_err(StoreError::from)?;
+
+        Ok(Times::from(times))
+    }
+
+    async fn insert_post(
+        &self,
+        tid: u64,
+        post: String,
+        author: Option<String>,
+    ) -> Result<Post, StoreError> {
+        self.find_times(tid).await?;
+
+        let mut tx = self.db.begin().await?;
+
+        let tid = tid as i64;
+        let now = Utc::now();
+        let post = sqlx::query_as!(
+            SqlitePost,
+            r#"insert into posts(tid, post, author, created_at)
+                    values ($1, $2, $3, $4)
+                    returning id as "id!", tid, post, author, created_at,
+                        updated_at"#,
+            tid,
+            post,
+            author,
+            now
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        Self::set_tags(&mut tx, post.id, &post.post).await?;
+        tx.commit().await?;
+
+        Ok(post.into())
+    }
+
+    async fn find_user(&self, name: &str) -> Result<(), StoreError> {
+        let user = sqlx::query_scalar!(
+            r#"select id from users where name = $1"#,
+            name
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        user.map(|_| ())
+            .ok_or(StoreError::NotFound(format!("user {name}")))
+    }
+
+    /// Where the content with `hash` is kept in the blob directory.
+    fn blob_path(dir: &Path, hash: &str) -> PathBuf {
+        dir.join(&hash[..2]).join(hash)
+    }
+
+    fn write_blob_file(
+        dir: &Path,
+        hash: &str,
+        data: &[u8],
+    ) -> Result<(), StoreError> {
+        let path = Self::blob_path(dir, hash);
+        if path.exists() {
+            return Ok(());
+        }
+
+        let write = || -> std::io::Result<()> {
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            // a partly written file must never be taken for the content
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, data)?;
+            std::fs::rename(&tmp, &path)
+        };
+
+        write().map_err(|e| {
+            StoreError::Backend(format!(
+                "failed to write {}: {e}",
+                path.display()
+            ))
+        })
+    }
+
+    /// Removes content which no attachment refers to anymore.
+    async fn collect_blobs(&self) -> Result<(), StoreError> {
+        let Some(dir) = &self.blob_dir else {
+            sqlx::query!(
+                r#"delete from blobs
+                        where hash not in (select hash from attachments)"#
+            )
+            .execute(&self.db)
+            .await?;
+            return Ok(());
+        };
+
+        let used: HashSet<String> =
+            sqlx::query_scalar!(r#"select distinct hash from attachments"#)
+                .fetch_all(&self.db)
+                .await?
+                .into_iter()
+                .collect();
+
+        let collect = || -> std::io::Result<()> {
+            for entry in std::fs::read_dir(dir)? {
+                let entry = entry?;
+                if !entry.file_type()?.is_dir() {
+                    continue;
+                }
+                for file in std::fs::read_dir(entry.path())? {
+                    let file = file?;
+                    let name = file.file_name();
+                    if !used.contains(name.to_string_lossy().as_ref()) {
+                        std::fs::remove_file(file.path())?;
+                    }
+                }
+            }
+            Ok(())
+        };
+
+        collect().map_err(|e| {
+            StoreError::Backend(format!(
+                "failed to clean up {}: {e}",
+                dir.display()
+            ))
+        })
+    }
+
+    /// Looks up an attachment of a live post in the times `tid`.
+    async fn find_attachment(
+        &self,
+        tid: u64,
+        aid: u64,
+    ) -> Result<SqliteAttachment, StoreError> {
+        self.find_times(tid).await?;
+
+        let (id, tid) = (aid as i64, tid as i64);
+        let attachment = sqlx::query_as!(
+            SqliteAttachment,
+            r#"select a.id as "id!", a.pid, a.name, a.mime, a.size, a.hash,
+                    a.created_at
+                    from attachments a join posts p on p.id = a.pid
+                    where a.id = $1 and p.tid = $2 and p.deleted_at is null"#,
+            id,
+            tid
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        attachment.ok_or(StoreError::NotFound(format!("attachment {aid}")))
+    }
+
+    /// Looks up a times which is not deleted.
+    async fn find_times(&self, tid: u64) -> Result<SqliteTimes, StoreError> {
+        let id = tid as i64;
+        let sql = sqlx::query_as!(
+            SqliteTimes,
+            r#"select id, title, owner, created_at, updated_at
+                    from times where id = $1 and deleted = 0"#,
+            id
+        )
+        .fetch_optional(&self.db);
+
+        sql.await?
+            .ok_or(StoreError::NotFound(format!("times {tid}")))
+    }
+
+    /// Whether a times is in the trash. Fails if it does not exist at all.
+    async fn is_trashed(&self, tid: u64) -> Result<bool, StoreError> {
+        let id = tid as i64;
+        let deleted = sqlx::query_scalar!(
+            r#"select deleted from times where id = $1"#,
+            id
+        )
+        .fetch_optional(&self.db)
+        .await?;
+
+        deleted
+            .map(|d| d != 0)
+            .ok_or(StoreError::NotFound(format!("times {tid}")))
+    }
+
+    /// Looks up a post and checks that it belongs to the times `tid`.
+    async fn find_post(
+        &self,
+        tid: u64,
+        pid: u64,
+    ) -> Result<SqlitePost, StoreError> {
+        self.find_times(tid).await?;
+
+        let id = pid as i64;
+        let sql = sqlx::query_as!(
+            SqlitePost,
+            r#"select id as "id!", tid, post, author, created_at, updated_at
+                    from posts where id = $1 and deleted_at is null"#,
+            id
+        )
+        .fetch_optional(&self.db);
+
+        let post = sql
+            .await
+            .map_err(StoreError::from)?
+            .ok_or(StoreError::NotFound(format!("post {pid}")))?;
+
+        if post.tid as u64 != tid {
+            return Err(StoreError::NotFound(format!(
+                "post {pid} in times {tid}"
+            )));
+        }
+
+        Ok(post)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn dbfile(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "timesman-{}-{}.db",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn build_creates_and_migrates_database() {
+        let path = dbfile("migrate");
+        let mut store = SqliteStoreBuilder::new(&path.to_string_lossy())
+            .build()
+            .await
+            .unwrap();
+
+        assert!(path.exists());
+        assert_eq!(
+            store.schema_version().await.unwrap(),
+            Some(SqliteStore::supported_schema_version())
+        );
+        assert!(store.get_times().await.unwrap().is_empty());
+
+        // opening an up-to-date database again is a no-op
+        SqliteStoreBuilder::new(&path.to_string_lossy())
+            .build()
+            .await
+            .unwrap();
+
+        store.close().await.unwrap();
+        assert!(store.check().await.is_err());
+        assert!(store.get_times().await.is_err());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn conformance() {
+        let path = dbfile("conformance");
+        let mut store = SqliteStoreBuilder::new(&path.to_string_lossy())
+            .build()
+            .await
+            .unwrap();
+
+        crate::testsuite::run(&mut store).await.assert_ok();
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn attachments_in_blob_dir() {
+        let path = dbfile("blobs");
+        let dir = path.with_extension("blobs");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut store = SqliteStoreBuilder::new(&path.to_string_lossy())
+            .blob_dir(&dir)
+            .build()
+            .await
+            .unwrap();
+
+        let times = store.create_times("blobs".to_string()).await.unwrap();
+        let post = store.create_post(times.id, "p".to_string()).await.unwrap();
+        let a = store
+            .attach(
+                times.id,
+                post.id,
+                "a.txt".to_string(),
+                "text/plain".to_string(),
+                b"content".to_vec(),
+            )
+            .await
+            .unwrap();
+
+        let file = dir.join(&a.hash[..2]).join(&a.hash);
+        assert_eq!(std::fs::read(&file).unwrap(), b"content");
+        assert_eq!(
+            store.read_attachment(times.id, a.id).await.unwrap(),
+            b"content"
+        );
+
+        store.detach(times.id, a.id).await.unwrap();
+        assert!(!file.exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn migration_indexes_existing_tags() {
+        let path = dbfile("tags");
+        let options = SqliteConnectOptions::from_str(&path.to_string_lossy())
+            .unwrap()
+            .create_if_missing(true);
+        let db = SqlitePool::connect_with(options).await.unwrap();
+
+        // a database from before post_tags existed
+        let old = Migrator {
+            migrations: MIGRATOR
+                .iter()
+                .filter(|m| m.version < TAGS_SCHEMA_VERSION)
+                .cloned()
+                .collect(),
+            ..Migrator::DEFAULT
+        };
+        old.run(&db).await.unwrap();
+        sqlx::query("insert into times(title) values ('old')")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query("insert into posts(tid, post) values (1, 'an #old post')")
+            .execute(&db)
+            .await
+            .unwrap();
+        db.close().await;
+
+        let mut store = SqliteStoreBuilder::new(&path.to_string_lossy())
+            .build()
+            .await
+            .unwrap();
+
+        let posts = store.get_posts_by_tag("old".to_string()).await.unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].post.post, "an #old post");
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn migration_stores_timestamps_in_utc() {
+        use chrono::TimeZone;
+
+        // the migration which stores the offset with the timestamps
+        const UTC_SCHEMA_VERSION: i64 = 20261018121530;
+
+        let path = dbfile("utc");
+        let options = SqliteConnectOptions::from_str(&path.to_string_lossy())
+            .unwrap()
+            .create_if_missing(true);
+        let db = SqlitePool::connect_with(options).await.unwrap();
+
+        let old = Migrator {
+            migrations: MIGRATOR
+                .iter()
+                .filter(|m| m.version < UTC_SCHEMA_VERSION)
+                .cloned()
+                .collect(),
+            ..Migrator::DEFAULT
+        };
+        old.run(&db).await.unwrap();
+        sqlx::query(
+            r#"insert into times(title, created_at)
+                values ('old', '2024-11-23 22:53:05')"#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        sqlx::query(
+            r#"insert into posts(tid, post, created_at)
+                values (1, 'old', '2024-11-23 23:00:00.5')"#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        db.close().await;
+
+        let mut store = SqliteStoreBuilder::new(&path.to_string_lossy())
+            .build()
+            .await
+            .unwrap();
+
+        let times = store.get_times().await.unwrap();
+        assert_eq!(
+            times[0].created_at,
+            Utc.with_ymd_and_hms(2024, 11, 23, 22, 53, 5).unwrap()
+        );
+
+        // old and new timestamps compare in order
+        let new = store.create_post(1, "new".to_string()).await.unwrap();
+        let query = PostQuery {
+            since: Some(Utc.with_ymd_and_hms(2024, 11, 23, 23, 0, 0).unwrap()),
+            until: Some(new.created_at),
+            ..Default::default()
+        };
+        let page = store.get_posts_page(1, query).await.unwrap();
+        assert_eq!(page.posts.len(), 1);
+        assert_eq!(page.posts[0].post, "old");
+        assert_eq!(page.posts[0].created_at.timestamp_subsec_millis(), 500);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn build_rejects_newer_schema() {
+        let path = dbfile("newer");
+        let store = SqliteStoreBuilder::new(&path.to_string_lossy())
+            .build()
+            .await
+            .unwrap();
+
+        let newer = SqliteStore::supported_schema_version() + 1;
+        sqlx::query(
+            r#"insert into _sqlx_migrations
+                (version, description, success, checksum, execution_time)
+                values ($1, 'from the future', 1, x'00', 0)"#,
+        )
+        .bind(newer)
+        .execute(&store.db)
+        .await
+        .unwrap();
+        store.db.close().await;
+
+        let err = SqliteStoreBuilder::new(&path.to_string_lossy())
+            .build()
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("newer"), "{err}");
+
+        let _ = std::fs::remove_file(&path);
     }
 }
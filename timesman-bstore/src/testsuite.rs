@@ -0,0 +1,1166 @@
+//! Conformance checks shared by every [`Store`] implementation.
+//!
+//! A backend plugs in by handing a store to [`run`] and asserting on the
+//! returned [`Report`]. Checks only touch the times they create, so the
+//! store does not need to be empty. Operations a backend reports as
+//! [`StoreError::Unsupported`] skip the check instead of failing it.
+
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use super::{PostQuery, SearchFilter, Store, StoreError};
+use timesman_type::{Acl, Change, Post, Tag};
+use tokio::sync::broadcast;
+
+/// An id no backend hands out during a test run.
+const UNKNOWN_ID: u64 = 1 << 40;
+
+enum Outcome {
+    Skipped(String),
+    Failed(String),
+}
+
+impl From<StoreError> for Outcome {
+    fn from(value: StoreError) -> Self {
+        match value {
+            StoreError::Unsupported(m) => Outcome::Skipped(m),
+            e => Outcome::Failed(format!("unexpected error: {e}")),
+        }
+    }
+}
+
+type CheckResult = Result<(), Outcome>;
+
+macro_rules! ensure {
+    ($cond:expr, $($arg:tt)*) => {
+        if !$cond {
+            return Err(Outcome::Failed(format!($($arg)*)));
+        }
+    };
+}
+
+/// Fails unless `result` is a [`StoreError::NotFound`].
+fn expect_not_found<T>(
+    result: Result<T, StoreError>,
+    what: &str,
+) -> CheckResult {
+    match result {
+        Err(StoreError::NotFound(_)) => Ok(()),
+        Err(StoreError::Unsupported(m)) => Err(Outcome::Skipped(m)),
+        Err(e) => Err(Outcome::Failed(format!(
+            "{what}: expected NotFound, got {e}"
+        ))),
+        Ok(_) => Err(Outcome::Failed(format!(
+            "{what}: expected NotFound, but it succeeded"
+        ))),
+    }
+}
+
+/// Fails unless `result` is a [`StoreError::InvalidArgument`].
+fn expect_invalid_argument<T>(
+    result: Result<T, StoreError>,
+    what: &str,
+) -> CheckResult {
+    match result {
+        Err(StoreError::InvalidArgument(_)) => Ok(()),
+        Err(StoreError::Unsupported(m)) => Err(Outcome::Skipped(m)),
+        Err(e) => Err(Outcome::Failed(format!(
+            "{what}: expected InvalidArgument, got {e}"
+        ))),
+        Ok(_) => Err(Outcome::Failed(format!(
+            "{what}: expected InvalidArgument, but it succeeded"
+        ))),
+    }
+}
+
+/// How long a published change may take to reach a subscriber.
+const CHANGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Timestamps must be close to the current instant, so a backend which
+/// shifts them by a timezone offset fails.
+fn is_recent(t: &DateTime<Utc>) -> bool {
+    (*t - Utc::now()).abs() < TimeDelta::minutes(1)
+}
+
+/// The outcome of running the suite against one store.
+#[derive(Default, Debug)]
+pub struct Report {
+    pub passed: Vec<&'static str>,
+    pub skipped: Vec<(&'static str, String)>,
+    pub failed: Vec<(&'static str, String)>,
+}
+
+impl Report {
+    /// Panics with every failed check. Skipped checks are only printed.
+    pub fn assert_ok(&self) {
+        for (name, msg) in &self.skipped {
+            eprintln!("skipped {name}: {msg}");
+        }
+
+        if !self.failed.is_empty() {
+            let msgs: Vec<String> = self
+                .failed
+                .iter()
+                .map(|(name, msg)| format!("{name}: {msg}"))
+                .collect();
+            panic!("store conformance failed:\n{}", msgs.join("\n"));
+        }
+    }
+}
+
+/// Runs every check against `store`.
+pub async fn run(store: &mut dyn Store) -> Report {
+    let mut report = Report::default();
+
+    macro_rules! check {
+        ($name:ident) => {
+            match $name(store).await {
+                Ok(()) => report.passed.push(stringify!($name)),
+                Err(Outcome::Skipped(m)) => {
+                    report.skipped.push((stringify!($name), m))
+                }
+                Err(Outcome::Failed(m)) => {
+                    report.failed.push((stringify!($name), m))
+                }
+            }
+        };
+    }
+
+    check!(check_store);
+    check!(create_and_list_times);
+    check!(update_times);
+    check!(delete_times);
+    check!(unknown_times);
+    check!(create_and_list_posts);
+    check!(posts_are_per_times);
+    check!(latest_post);
+    check!(update_post);
+    check!(delete_post);
+    check!(unknown_post);
+    check!(post_pages);
+    check!(search_posts);
+    check!(tags);
+    check!(trash);
+    check!(revisions);
+    check!(attachments);
+    check!(users);
+    check!(acl);
+    check!(changes);
+
+    report
+}
+
+async fn check_store(store: &mut dyn Store) -> CheckResult {
+    store.check().await?;
+    Ok(())
+}
+
+async fn create_and_list_times(store: &mut dyn Store) -> CheckResult {
+    let a = store.create_times("conformance a".to_string()).await?;
+    let b = store.create_times("conformance b".to_string()).await?;
+
+    ensure!(a.title == "conformance a", "title is {:?}", a.title);
+    ensure!(a.id != b.id, "two times share the id {}", a.id);
+    ensure!(a.updated_at.is_none(), "new times has updated_at");
+    ensure!(is_recent(&a.created_at), "created_at is {}", a.created_at);
+
+    let times = store.get_times().await?;
+    let pos_a = times.iter().position(|t| t.id == a.id);
+    let pos_b = times.iter().position(|t| t.id == b.id);
+    ensure!(pos_a.is_some(), "get_times misses times {}", a.id);
+    ensure!(pos_b.is_some(), "get_times misses times {}", b.id);
+    ensure!(pos_a < pos_b, "get_times is not in creation order");
+
+    let listed = &times[pos_a.unwrap()];
+    ensure!(
+        listed.title == a.title && listed.created_at == a.created_at,
+        "get_times returns {} instead of {}",
+        listed,
+        a
+    );
+
+    Ok(())
+}
+
+async fn update_times(store: &mut dyn Store) -> CheckResult {
+    let mut times = store.create_times("before".to_string()).await?;
+    times.title = "after".to_string();
+
+    let updated = store.update_times(times.clone()).await?;
+    ensure!(updated.id == times.id, "update_times changed the id");
+    ensure!(updated.title == "after", "title is {:?}", updated.title);
+    ensure!(
+        updated.created_at == times.created_at,
+        "update_times changed created_at"
+    );
+    match updated.updated_at {
+        Some(u) => ensure!(u >= updated.created_at, "updated_at is too old"),
+        None => ensure!(false, "update_times did not set updated_at"),
+    }
+
+    let listed = store.get_times().await?;
+    let listed = listed.iter().find(|t| t.id == times.id);
+    ensure!(
+        listed.is_some_and(|t| t.title == "after"),
+        "get_times does not reflect the update"
+    );
+
+    Ok(())
+}
+
+async fn delete_times(store: &mut dyn Store) -> CheckResult {
+    let times = store.create_times("to be deleted".to_string()).await?;
+    store.create_post(times.id, "post".to_string()).await?;
+
+    store.delete_times(times.id).await?;
+
+    let listed = store.get_times().await?;
+    ensure!(
+        !listed.iter().any(|t| t.id == times.id),
+        "get_times still lists the deleted times"
+    );
+
+    expect_not_found(store.get_posts(times.id).await, "get_posts")?;
+    expect_not_found(store.delete_times(times.id).await, "delete again")?;
+
+    Ok(())
+}
+
+async fn unknown_times(store: &mut dyn Store) -> CheckResult {
+    expect_not_found(store.get_posts(UNKNOWN_ID).await, "get_posts")?;
+    expect_not_found(
+        store.create_post(UNKNOWN_ID, "post".to_string()).await,
+        "create_post",
+    )?;
+    expect_not_found(store.delete_times(UNKNOWN_ID).await, "delete_times")?;
+    expect_not_found(
+        store.get_latest_post(UNKNOWN_ID).await,
+        "get_latest_post",
+    )?;
+
+    let mut times = store.create_times("unknown".to_string()).await?;
+    times.id = UNKNOWN_ID;
+    expect_not_found(store.update_times(times).await, "update_times")?;
+
+    Ok(())
+}
+
+async fn create_and_list_posts(store: &mut dyn Store) -> CheckResult {
+    let times = store.create_times("posts".to_string()).await?;
+
+    let mut created = vec![];
+    for text in ["first", "second", "third"] {
+        let post = store.create_post(times.id, text.to_string()).await?;
+        ensure!(post.post == text, "post is {:?}", post.post);
+        ensure!(post.updated_at.is_none(), "new post has updated_at");
+        ensure!(
+            is_recent(&post.created_at),
+            "created_at is {}",
+            post.created_at
+        );
+        created.push(post);
+    }
+
+    let posts = store.get_posts(times.id).await?;
+    ensure!(posts.len() == 3, "get_posts returns {} posts", posts.len());
+
+    for (listed, created) in posts.iter().zip(&created) {
+        ensure!(
+            listed.id == created.id,
+            "get_posts is not in creation order"
+        );
+        ensure!(
+            listed.post == created.post
+                && listed.created_at == created.created_at,
+            "post {} differs from what create_post returned",
+            listed.id
+        );
+    }
+
+    ensure!(
+        posts.windows(2).all(|w| w[0].created_at <= w[1].created_at),
+        "created_at goes backwards"
+    );
+
+    Ok(())
+}
+
+async fn posts_are_per_times(store: &mut dyn Store) -> CheckResult {
+    let a = store.create_times("a".to_string()).await?;
+    let b = store.create_times("b".to_string()).await?;
+
+    let post = store.create_post(a.id, "in a".to_string()).await?;
+
+    ensure!(
+        store.get_posts(b.id).await?.is_empty(),
+        "a post of times {} shows up in times {}",
+        a.id,
+        b.id
+    );
+
+    expect_not_found(
+        store.delete_post(b.id, post.id).await,
+        "delete_post with the wrong tid",
+    )?;
+    expect_not_found(
+        store.update_post(b.id, post.clone()).await,
+        "update_post with the wrong tid",
+    )?;
+
+    let posts = store.get_posts(a.id).await?;
+    ensure!(
+        posts.iter().any(|p| p.id == post.id && p.post == "in a"),
+        "a request for the wrong tid changed the post"
+    );
+
+    Ok(())
+}
+
+async fn latest_post(store: &mut dyn Store) -> CheckResult {
+    let times = store.create_times("latest".to_string()).await?;
+
+    let latest = store.get_latest_post(times.id).await?;
+    ensure!(latest.is_none(), "empty times has a latest post");
+
+    store.create_post(times.id, "older".to_string()).await?;
+    let newer = store.create_post(times.id, "newer".to_string()).await?;
+
+    let latest = store.get_latest_post(times.id).await?;
+    ensure!(
+        latest.as_ref().is_some_and(|p| p.id == newer.id),
+        "latest post is {:?}",
+        latest.map(|p| p.post)
+    );
+
+    Ok(())
+}
+
+async fn update_post(store: &mut dyn Store) -> CheckResult {
+    let times = store.create_times("update post".to_string()).await?;
+    let mut post = store.create_post(times.id, "before".to_string()).await?;
+
+    post.post = "after".to_string();
+    let updated = store.update_post(times.id, post.clone()).await?;
+
+    ensure!(updated.id == post.id, "update_post changed the id");
+    ensure!(updated.post == "after", "post is {:?}", updated.post);
+    ensure!(
+        updated.created_at == post.created_at,
+        "update_post changed created_at"
+    );
+    match updated.updated_at {
+        Some(u) => ensure!(u >= updated.created_at, "updated_at is too old"),
+        None => ensure!(false, "update_post did not set updated_at"),
+    }
+
+    let posts = store.get_posts(times.id).await?;
+    ensure!(
+        posts.len() == 1 && posts[0].post == "after",
+        "get_posts does not reflect the update"
+    );
+
+    Ok(())
+}
+
+async fn delete_post(store: &mut dyn Store) -> CheckResult {
+    let times = store.create_times("delete post".to_string()).await?;
+    let keep = store.create_post(times.id, "keep".to_string()).await?;
+    let drop = store.create_post(times.id, "drop".to_string()).await?;
+
+    store.delete_post(times.id, drop.id).await?;
+
+    let posts = store.get_posts(times.id).await?;
+    ensure!(
+        posts.len() == 1 && posts[0].id == keep.id,
+        "get_posts returns {} posts after delete",
+        posts.len()
+    );
+
+    let latest = store.get_latest_post(times.id).await?;
+    ensure!(
+        latest.is_some_and(|p| p.id == keep.id),
+        "latest post is the deleted one"
+    );
+
+    expect_not_found(
+        store.delete_post(times.id, drop.id).await,
+        "delete_post again",
+    )?;
+
+    Ok(())
+}
+
+async fn unknown_post(store: &mut dyn Store) -> CheckResult {
+    let times = store.create_times("unknown post".to_string()).await?;
+    let mut post = store.create_post(times.id, "post".to_string()).await?;
+
+    expect_not_found(
+        store.delete_post(times.id, UNKNOWN_ID).await,
+        "delete_post",
+    )?;
+
+    post.id = UNKNOWN_ID;
+    expect_not_found(store.update_post(times.id, post).await, "update_post")?;
+
+    Ok(())
+}
+
+async fn post_pages(store: &mut dyn Store) -> CheckResult {
+    let times = store.create_times("pages".to_string()).await?;
+
+    let mut ids = vec![];
+    for i in 0..5 {
+        ids.push(store.create_post(times.id, format!("{i}")).await?.id);
+    }
+
+    let all = store.get_posts_page(times.id, PostQuery::default()).await?;
+    let got: Vec<u64> = all.posts.iter().map(|p| p.id).collect();
+    ensure!(got == ids, "unlimited page is {got:?}, expected {ids:?}");
+    ensure!(all.next.is_none(), "unlimited page has a next cursor");
+
+    // walk backwards from the newest page
+    let mut query = PostQuery {
+        limit: Some(2),
+        ..Default::default()
+    };
+    let mut pages = vec![];
+    loop {
+        let page = store.get_posts_page(times.id, query.clone()).await?;
+        pages.push(page.posts.iter().map(|p| p.id).collect::<Vec<u64>>());
+
+        match page.next {
+            Some(next) => query.cursor = Some(next),
+            None => break,
+        }
+        ensure!(pages.len() <= ids.len(), "paging does not terminate");
+    }
+
+    let expected =
+        vec![vec![ids[3], ids[4]], vec![ids[1], ids[2]], vec![ids[0]]];
+    ensure!(
+        pages == expected,
+        "pages are {pages:?}, expected {expected:?}"
+    );
+
+    let future = PostQuery {
+        since: Some(Utc::now() + TimeDelta::days(2)),
+        ..Default::default()
+    };
+    let page = store.get_posts_page(times.id, future).await?;
+    ensure!(page.posts.is_empty(), "page ignores the since filter");
+
+    let past = PostQuery {
+        until: Some(Utc::now() - TimeDelta::days(2)),
+        ..Default::default()
+    };
+    let page = store.get_posts_page(times.id, past).await?;
+    ensure!(page.posts.is_empty(), "page ignores the until filter");
+
+    let empty = PostQuery {
+        limit: Some(0),
+        ..Default::default()
+    };
+    expect_invalid_argument(
+        store.get_posts_page(times.id, empty).await,
+        "get_posts_page with a zero limit",
+    )?;
+
+    expect_not_found(
+        store.get_posts_page(UNKNOWN_ID, PostQuery::default()).await,
+        "get_posts_page",
+    )
+}
+
+async fn search_posts(store: &mut dyn Store) -> CheckResult {
+    let a = store.create_times("search a".to_string()).await?;
+    let b = store.create_times("search b".to_string()).await?;
+    let gone = store.create_times("search deleted".to_string()).await?;
+
+    // a token which no other check writes
+    let token = format!("needle{}x", a.id);
+
+    let pie = store
+        .create_post(a.id, format!("{token} apple pie"))
+        .await?;
+    store
+        .create_post(a.id, format!("{token} banana split"))
+        .await?;
+    let juice = store
+        .create_post(b.id, format!("{token} Apple juice"))
+        .await?;
+    store
+        .create_post(gone.id, format!("{token} apple tart"))
+        .await?;
+    store.delete_times(gone.id).await?;
+
+    let all = SearchFilter::default();
+    let hits = store
+        .search_posts(format!("{token} apple"), all.clone())
+        .await?;
+    ensure!(hits.len() == 2, "search returns {} posts", hits.len());
+    ensure!(
+        hits[0].tid == b.id && hits[0].post.id == juice.id,
+        "search results are not newest first"
+    );
+    ensure!(
+        hits[1].tid == a.id && hits[1].post.id == pie.id,
+        "search results carry the wrong post or tid"
+    );
+
+    let only_a = SearchFilter {
+        tid: Some(a.id),
+        ..Default::default()
+    };
+    let hits = store
+        .search_posts(format!("{token} APPLE"), only_a.clone())
+        .await?;
+    ensure!(
+        hits.len() == 1 && hits[0].post.id == pie.id,
+        "search ignores the tid filter or is case sensitive"
+    );
+
+    let hits = store
+        .search_posts(format!("{token} pi"), only_a.clone())
+        .await?;
+    ensure!(
+        hits.len() == 1 && hits[0].post.id == pie.id,
+        "search does not match short terms"
+    );
+
+    let one = SearchFilter {
+        limit: Some(1),
+        ..Default::default()
+    };
+    let hits = store.search_posts(token.clone(), one).await?;
+    ensure!(
+        hits.len() == 1 && hits[0].post.id == juice.id,
+        "search ignores the limit"
+    );
+
+    let future = SearchFilter {
+        since: Some(Utc::now() + TimeDelta::days(2)),
+        ..Default::default()
+    };
+    let hits = store.search_posts(token.clone(), future).await?;
+    ensure!(hits.is_empty(), "search ignores the since filter");
+
+    expect_invalid_argument(
+        store.search_posts("  ".to_string(), all).await,
+        "search with an empty query",
+    )
+}
+
+async fn tags(store: &mut dyn Store) -> CheckResult {
+    let a = store.create_times("tags a".to_string()).await?;
+    let b = store.create_times("tags b".to_string()).await?;
+    let gone = store.create_times("tags deleted".to_string()).await?;
+
+    // tags which no other check writes
+    let meet = format!("meet{}x", a.id);
+    let bug = format!("bug{}x", a.id);
+
+    let first = store
+        .create_post(a.id, format!("#{meet} about #{bug} and #{meet}"))
+        .await?;
+    let mut second = store
+        .create_post(a.id, format!("#{}", meet.to_uppercase()))
+        .await?;
+    let third = store.create_post(b.id, format!("#{meet} again")).await?;
+    store
+        .create_post(gone.id, format!("#{meet} #{bug}"))
+        .await?;
+    store.delete_times(gone.id).await?;
+
+    let count = |tags: &[Tag], name: &str| {
+        tags.iter().find(|t| t.name == name).map(|t| t.count)
+    };
+
+    let tags = store.list_tags().await?;
+    ensure!(
+        count(&tags, &meet) == Some(3),
+        "#{meet} is counted {:?} times",
+        count(&tags, &meet)
+    );
+    ensure!(
+        count(&tags, &bug) == Some(1),
+        "#{bug} is counted {:?} times",
+        count(&tags, &bug)
+    );
+
+    let posts = store.get_posts_by_tag(format!("#{meet}")).await?;
+    let got: Vec<(u64, u64)> =
+        posts.iter().map(|tp| (tp.tid, tp.post.id)).collect();
+    let expected = vec![(b.id, third.id), (a.id, second.id), (a.id, first.id)];
+    ensure!(
+        got == expected,
+        "tagged posts are {got:?}, not {expected:?}"
+    );
+
+    // tags follow updates and deletions
+    second.post = format!("now #{bug}");
+    store.update_post(a.id, second.clone()).await?;
+    store.delete_post(b.id, third.id).await?;
+
+    let tags = store.list_tags().await?;
+    ensure!(
+        count(&tags, &meet) == Some(1) && count(&tags, &bug) == Some(2),
+        "tags are not updated with their posts"
+    );
+
+    let posts = store.get_posts_by_tag(bug.to_uppercase()).await?;
+    ensure!(
+        posts.len() == 2 && posts[0].post.id == second.id,
+        "get_posts_by_tag does not reflect the update"
+    );
+
+    store.delete_post(a.id, first.id).await?;
+    let posts = store.get_posts_by_tag(meet.clone()).await?;
+    ensure!(posts.is_empty(), "deleted posts are still tagged");
+    let tags = store.list_tags().await?;
+    ensure!(
+        count(&tags, &meet).is_none(),
+        "unused tags are still listed"
+    );
+
+    expect_invalid_argument(
+        store.get_posts_by_tag("#".to_string()).await,
+        "get_posts_by_tag with an empty tag",
+    )
+}
+
+async fn trash(store: &mut dyn Store) -> CheckResult {
+    let times = store.create_times("trash".to_string()).await?;
+    let first = store.create_post(times.id, "first".to_string()).await?;
+    let second = store.create_post(times.id, "second".to_string()).await?;
+
+    let ids = |posts: Vec<Post>| posts.iter().map(|p| p.id).collect::<Vec<_>>();
+
+    // a deleted post can be restored
+    store.delete_post(times.id, first.id).await?;
+    let trash = store.list_trash().await?;
+    let trashed = trash
+        .posts
+        .iter()
+        .find(|p| p.tid == times.id && p.post.id == first.id);
+    ensure!(trashed.is_some(), "deleted post is not in the trash");
+    ensure!(
+        trashed.is_some_and(|p| is_recent(&p.deleted_at)),
+        "deleted_at of the post is not recent"
+    );
+
+    let restored = store.restore_post(times.id, first.id).await?;
+    ensure!(
+        restored.post == "first",
+        "restored post is {:?}",
+        restored.post
+    );
+    let posts = ids(store.get_posts(times.id).await?);
+    ensure!(
+        posts == vec![first.id, second.id],
+        "posts after restoring are {posts:?}"
+    );
+    let trash = store.list_trash().await?;
+    ensure!(
+        !trash
+            .posts
+            .iter()
+            .any(|p| p.post.id == first.id && p.tid == times.id),
+        "restored post is still in the trash"
+    );
+
+    // a deleted times can be restored with its posts
+    store.delete_times(times.id).await?;
+    let trash = store.list_trash().await?;
+    ensure!(
+        trash.times.iter().any(|t| t.times.id == times.id),
+        "deleted times is not in the trash"
+    );
+    let restored = store.restore_times(times.id).await?;
+    ensure!(restored.title == "trash", "restored times is {restored}");
+    let posts = ids(store.get_posts(times.id).await?);
+    ensure!(
+        posts == vec![first.id, second.id],
+        "posts after restoring the times are {posts:?}"
+    );
+
+    // a post cannot come back while its times is in the trash
+    store.delete_post(times.id, second.id).await?;
+    store.delete_times(times.id).await?;
+    match store.restore_post(times.id, second.id).await {
+        Err(StoreError::Conflict(_)) => {}
+        Err(e) => ensure!(false, "restore_post: expected Conflict, got {e}"),
+        Ok(_) => ensure!(false, "restored a post of a trashed times"),
+    }
+
+    store.purge_times(times.id).await?;
+    let trash = store.list_trash().await?;
+    ensure!(
+        !trash.times.iter().any(|t| t.times.id == times.id),
+        "purged times is still in the trash"
+    );
+    expect_not_found(store.restore_times(times.id).await, "restore_times")?;
+
+    // a purged post is gone for good
+    let other = store.create_times("trash other".to_string()).await?;
+    let post = store.create_post(other.id, "purge me".to_string()).await?;
+    expect_not_found(
+        store.purge_post(other.id, post.id).await,
+        "purge_post of a live post",
+    )?;
+    store.delete_post(other.id, post.id).await?;
+    store.purge_post(other.id, post.id).await?;
+    expect_not_found(
+        store.restore_post(other.id, post.id).await,
+        "restore_post",
+    )?;
+
+    // purging by age keeps what was deleted recently
+    store.delete_times(other.id).await?;
+    let old = Utc::now() - TimeDelta::days(2);
+    store.purge_trash(Some(old)).await?;
+    let trash = store.list_trash().await?;
+    ensure!(
+        trash.times.iter().any(|t| t.times.id == other.id),
+        "purge_trash removed a recently deleted times"
+    );
+
+    let future = Utc::now() + TimeDelta::days(2);
+    let purged = store.purge_trash(Some(future)).await?;
+    ensure!(purged >= 1, "purge_trash purged {purged} items");
+    let trash = store.list_trash().await?;
+    ensure!(
+        trash.times.is_empty() && trash.posts.is_empty(),
+        "trash is not empty after purging everything"
+    );
+
+    Ok(())
+}
+
+async fn revisions(store: &mut dyn Store) -> CheckResult {
+    let times = store.create_times("revisions".to_string()).await?;
+    let mut post = store.create_post(times.id, "one".to_string()).await?;
+
+    let revs = store.list_revisions(times.id, post.id).await?;
+    ensure!(revs.is_empty(), "new post has {} revisions", revs.len());
+
+    post.post = "two".to_string();
+    let mut post = store.update_post(times.id, post).await?;
+    post.post = "three".to_string();
+    store.update_post(times.id, post.clone()).await?;
+
+    let revs = store.list_revisions(times.id, post.id).await?;
+    let texts: Vec<(u64, &str)> =
+        revs.iter().map(|r| (r.rev, r.post.as_str())).collect();
+    ensure!(
+        texts == vec![(1, "one"), (2, "two")],
+        "revisions are {texts:?}"
+    );
+    ensure!(
+        revs.iter().all(|r| is_recent(&r.created_at)),
+        "created_at of a revision is not recent"
+    );
+
+    let rev = store.get_revision(times.id, post.id, 1).await?;
+    ensure!(rev.post == "one", "revision 1 is {:?}", rev.post);
+    expect_not_found(
+        store.get_revision(times.id, post.id, 3).await,
+        "get_revision of the current text",
+    )?;
+
+    // reverting is an edit, so the replaced text is kept as well
+    let reverted = store.revert_post(times.id, post.id, 1).await?;
+    ensure!(
+        reverted.post == "one",
+        "reverted post is {:?}",
+        reverted.post
+    );
+    let posts = store.get_posts(times.id).await?;
+    ensure!(
+        posts.len() == 1 && posts[0].post == "one",
+        "get_posts does not reflect the revert"
+    );
+    let revs = store.list_revisions(times.id, post.id).await?;
+    ensure!(
+        revs.last().is_some_and(|r| r.rev == 3 && r.post == "three"),
+        "revert did not record the replaced text"
+    );
+
+    expect_not_found(
+        store.list_revisions(times.id, UNKNOWN_ID).await,
+        "list_revisions of an unknown post",
+    )?;
+    expect_not_found(
+        store.revert_post(times.id, post.id, UNKNOWN_ID).await,
+        "revert_post to an unknown revision",
+    )?;
+
+    Ok(())
+}
+
+async fn attachments(store: &mut dyn Store) -> CheckResult {
+    let times = store.create_times("attachments".to_string()).await?;
+    let post = store
+        .create_post(times.id, "with files".to_string())
+        .await?;
+
+    let attached = store.list_attachments(times.id).await?;
+    ensure!(
+        attached.is_empty(),
+        "new times has {} attachments",
+        attached.len()
+    );
+
+    let hello = b"hello".to_vec();
+    let a = store
+        .attach(
+            times.id,
+            post.id,
+            "a.txt".to_string(),
+            "text/plain".to_string(),
+            hello.clone(),
+        )
+        .await?;
+    ensure!(a.pid == post.id, "attached to post {}", a.pid);
+    ensure!(
+        a.name == "a.txt" && a.mime == "text/plain",
+        "record is wrong"
+    );
+    ensure!(a.size == 5, "size is {}", a.size);
+    ensure!(
+        a.hash
+            == "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824",
+        "hash is {}",
+        a.hash
+    );
+    ensure!(is_recent(&a.created_at), "created_at is not recent");
+
+    // the same content twice is stored once but listed twice
+    let b = store
+        .attach(
+            times.id,
+            post.id,
+            "b.txt".to_string(),
+            "text/plain".to_string(),
+            hello.clone(),
+        )
+        .await?;
+    ensure!(b.hash == a.hash, "same content has different hashes");
+
+    let ids: Vec<u64> = store
+        .list_attachments(times.id)
+        .await?
+        .iter()
+        .map(|a| a.id)
+        .collect();
+    ensure!(ids == vec![a.id, b.id], "attachments are {ids:?}");
+
+    let data = store.read_attachment(times.id, a.id).await?;
+    ensure!(data == hello, "read {} bytes back", data.len());
+    let got = store.get_attachment(times.id, b.id).await?;
+    ensure!(got.name == "b.txt", "get_attachment returned {}", got.name);
+
+    expect_invalid_argument(
+        store
+            .attach(
+                times.id,
+                post.id,
+                "".to_string(),
+                "text/plain".to_string(),
+                vec![],
+            )
+            .await,
+        "attach without a name",
+    )?;
+    expect_invalid_argument(
+        store
+            .attach(
+                times.id,
+                post.id,
+                "c".to_string(),
+                "text".to_string(),
+                vec![],
+            )
+            .await,
+        "attach with a bad MIME type",
+    )?;
+    expect_not_found(
+        store
+            .attach(
+                times.id,
+                UNKNOWN_ID,
+                "c".to_string(),
+                "text/plain".to_string(),
+                vec![],
+            )
+            .await,
+        "attach to an unknown post",
+    )?;
+
+    // detaching one keeps the shared content of the other
+    store.detach(times.id, a.id).await?;
+    expect_not_found(
+        store.get_attachment(times.id, a.id).await,
+        "get_attachment after detach",
+    )?;
+    let data = store.read_attachment(times.id, b.id).await?;
+    ensure!(data == hello, "detach removed shared content");
+
+    // attachments follow their post into the trash and back
+    store.delete_post(times.id, post.id).await?;
+    let attached = store.list_attachments(times.id).await?;
+    ensure!(
+        attached.is_empty(),
+        "attachments of a trashed post are listed"
+    );
+    expect_not_found(
+        store.read_attachment(times.id, b.id).await,
+        "read_attachment of a trashed post",
+    )?;
+    store.restore_post(times.id, post.id).await?;
+    let data = store.read_attachment(times.id, b.id).await?;
+    ensure!(data == hello, "restored post lost its attachment");
+
+    Ok(())
+}
+
+/// Waits for the next change to the times `tid`, skipping other times.
+async fn next_change(
+    rx: &mut broadcast::Receiver<Change>,
+    tid: u64,
+) -> Result<Change, Outcome> {
+    loop {
+        match tokio::time::timeout(CHANGE_TIMEOUT, rx.recv()).await {
+            Ok(Ok(change)) if change.tid() == tid => return Ok(change),
+            Ok(Ok(_)) => continue,
+            Ok(Err(e)) => {
+                return Err(Outcome::Failed(format!("subscription: {e}")))
+            }
+            Err(_) => {
+                return Err(Outcome::Failed(format!(
+                    "no change to times {tid} was published"
+                )))
+            }
+        }
+    }
+}
+
+async fn users(store: &mut dyn Store) -> CheckResult {
+    // user names are unique, so make them unique to this run
+    let suffix = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+    let alice = store.create_user(format!("alice{suffix}")).await?;
+    let bob = store.create_user(format!("bob{suffix}")).await?;
+    ensure!(alice.id != bob.id, "two users share the id {}", alice.id);
+    ensure!(
+        is_recent(&alice.created_at),
+        "created_at is {}",
+        alice.created_at
+    );
+
+    let users = store.list_users().await?;
+    ensure!(
+        users.iter().any(|u| u.name == alice.name)
+            && users.iter().any(|u| u.name == bob.name),
+        "list_users misses the created users"
+    );
+
+    match store.create_user(alice.name.clone()).await {
+        Err(StoreError::Conflict(_)) => {}
+        r => ensure!(false, "create the same user: got {:?}", r.err()),
+    }
+    expect_invalid_argument(
+        store.create_user("a b".to_string()).await,
+        "create_user with a space",
+    )?;
+    expect_not_found(
+        store
+            .create_times_for(format!("nobody{suffix}"), "t".to_string())
+            .await,
+        "create_times_for an unknown user",
+    )?;
+
+    let mine = store
+        .create_times_for(alice.name.clone(), "alice's".to_string())
+        .await?;
+    let theirs = store
+        .create_times_for(bob.name.clone(), "bob's".to_string())
+        .await?;
+    let shared = store.create_times("shared".to_string()).await?;
+    ensure!(
+        mine.owner.as_ref() == Some(&alice.name),
+        "owner is {:?}",
+        mine.owner
+    );
+    ensure!(shared.owner.is_none(), "owner is {:?}", shared.owner);
+
+    let visible = store.get_visible_times(alice.name.clone()).await?;
+    ensure!(
+        visible.iter().any(|t| t.id == mine.id),
+        "the owner does not see their times"
+    );
+    ensure!(
+        visible.iter().any(|t| t.id == shared.id),
+        "the times without an owner is not visible"
+    );
+    ensure!(
+        !visible.iter().any(|t| t.id == theirs.id),
+        "the times of another user is visible"
+    );
+
+    let post = store
+        .create_post_by(shared.id, bob.name.clone(), "hi".to_string())
+        .await?;
+    ensure!(
+        post.author.as_ref() == Some(&bob.name),
+        "author is {:?}",
+        post.author
+    );
+    let posts = store.get_posts(shared.id).await?;
+    ensure!(
+        posts.iter().any(|p| p.author.as_ref() == Some(&bob.name)),
+        "get_posts loses the author"
+    );
+
+    let mut renamed = mine.clone();
+    renamed.title = "renamed".to_string();
+    renamed.owner = None;
+    let renamed = store.update_times(renamed).await?;
+    ensure!(
+        renamed.owner.as_ref() == Some(&alice.name),
+        "update_times changed the owner"
+    );
+
+    Ok(())
+}
+
+async fn acl(store: &mut dyn Store) -> CheckResult {
+    let suffix = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+    let owner = store.create_user(format!("owner{suffix}")).await?.name;
+    let reader = store.create_user(format!("reader{suffix}")).await?.name;
+    let writer = store.create_user(format!("writer{suffix}")).await?.name;
+
+    let times = store
+        .create_times_for(owner.clone(), "acl".to_string())
+        .await?;
+    let acl = store.get_acl(times.id).await?;
+    ensure!(
+        acl == Acl {
+            owner: Some(owner.clone()),
+            ..Acl::default()
+        },
+        "a new times has the ACL {:?}",
+        acl
+    );
+
+    let given = Acl {
+        owner: Some(reader.clone()),
+        readers: vec![reader.clone(), writer.clone(), reader.clone()],
+        writers: vec![writer.clone()],
+        public: false,
+    };
+    let set = store.set_acl(times.id, given).await?;
+    let expected = Acl {
+        owner: Some(owner.clone()),
+        readers: vec![reader.clone()],
+        writers: vec![writer.clone()],
+        public: false,
+    };
+    ensure!(set == expected, "set_acl returns {:?}", set);
+    let got = store.get_acl(times.id).await?;
+    ensure!(got == expected, "get_acl returns {:?}", got);
+
+    let visible = store.get_visible_times(reader.clone()).await?;
+    ensure!(
+        visible.iter().any(|t| t.id == times.id),
+        "a reader does not see the times"
+    );
+    let other = store.create_user(format!("other{suffix}")).await?.name;
+    let visible = store.get_visible_times(other.clone()).await?;
+    ensure!(
+        !visible.iter().any(|t| t.id == times.id),
+        "a user without access sees the times"
+    );
+
+    let public = Acl {
+        public: true,
+        ..Acl::default()
+    };
+    store.set_acl(times.id, public).await?;
+    let visible = store.get_visible_times(other).await?;
+    ensure!(
+        visible.iter().any(|t| t.id == times.id),
+        "a public times is not visible"
+    );
+
+    let unknown = Acl {
+        readers: vec![format!("nobody{suffix}")],
+        ..Acl::default()
+    };
+    expect_not_found(
+        store.set_acl(times.id, unknown).await,
+        "set_acl with an unknown user",
+    )?;
+    expect_not_found(store.get_acl(UNKNOWN_ID).await, "get_acl")?;
+
+    // the ACL of a times in the trash decides who may restore it
+    store.delete_times(times.id).await?;
+    let trashed = store.get_acl(times.id).await?;
+    ensure!(trashed.public, "the trash loses the ACL");
+
+    Ok(())
+}
+
+async fn changes(store: &mut dyn Store) -> CheckResult {
+    let mut rx = store.subscribe().await?;
+
+    let times = store.create_times("changes".to_string()).await?;
+    let tid = times.id;
+    ensure!(
+        matches!(next_change(&mut rx, tid).await?,
+            Change::TimesCreated { times: t } if t.title == "changes"),
+        "creating a times was not published"
+    );
+
+    let mut times = times;
+    times.title = "changed".to_string();
+    store.update_times(times).await?;
+    ensure!(
+        matches!(next_change(&mut rx, tid).await?,
+            Change::TimesUpdated { times: t } if t.title == "changed"),
+        "updating a times was not published"
+    );
+
+    let mut post = store.create_post(tid, "one".to_string()).await?;
+    let pid = post.id;
+    ensure!(
+        matches!(next_change(&mut rx, tid).await?,
+            Change::PostCreated { post: p, .. } if p.id == pid),
+        "creating a post was not published"
+    );
+
+    post.post = "two".to_string();
+    store.update_post(tid, post).await?;
+    ensure!(
+        matches!(next_change(&mut rx, tid).await?,
+            Change::PostUpdated { post: p, .. } if p.post == "two"),
+        "updating a post was not published"
+    );
+
+    store.delete_post(tid, pid).await?;
+    ensure!(
+        matches!(next_change(&mut rx, tid).await?,
+            Change::PostDeleted { pid: p, .. } if p == pid),
+        "deleting a post was not published"
+    );
+
+    store.delete_times(tid).await?;
+    ensure!(
+        matches!(
+            next_change(&mut rx, tid).await?,
+            Change::TimesDeleted { .. }
+        ),
+        "deleting a times was not published"
+    );
+
+    Ok(())
+}
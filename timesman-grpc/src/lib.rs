@@ -1,135 +1,589 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use tonic::transport::{Certificate, ClientTlsConfig, Identity};
+
 pub mod grpc {
     tonic::include_proto!("timesman");
 }
 
-impl Into<timesman_type::Times> for grpc::Times {
-    fn into(self) -> timesman_type::Times {
-        let c = self.created_at.unwrap();
-        let ctime = chrono::DateTime::from_timestamp(c.seconds, c.nanos as u32)
-            .unwrap();
-
-        let utime = if let Some(u) = self.updated_at {
-            Some(
-                chrono::DateTime::from_timestamp(u.seconds, u.nanos as u32)
-                    .unwrap()
-                    .naive_local(),
-            )
-        } else {
-            None
-        };
+/// Why a message cannot be converted to a `timesman_type` value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConvertError {
+    /// A field every message of the type has is not set.
+    MissingField(&'static str),
+    /// A timestamp outside the range `chrono` can represent.
+    InvalidTimestamp(prost_types::Timestamp),
+}
 
-        timesman_type::Times {
-            id: self.id,
-            title: self.title,
-            created_at: ctime.naive_local(),
-            updated_at: utime,
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertError::MissingField(field) => {
+                write!(f, "missing field {field}")
+            }
+            ConvertError::InvalidTimestamp(t) => {
+                write!(f, "invalid timestamp {t}")
+            }
         }
     }
 }
 
-use chrono::{Datelike, Timelike};
+impl std::error::Error for ConvertError {}
+
+impl From<ConvertError> for tonic::Status {
+    fn from(value: ConvertError) -> Self {
+        tonic::Status::invalid_argument(value.to_string())
+    }
+}
+
+/// Unwraps a message field which must be set.
+fn required<T>(
+    value: Option<T>,
+    field: &'static str,
+) -> Result<T, ConvertError> {
+    value.ok_or(ConvertError::MissingField(field))
+}
+
+impl TryFrom<grpc::Times> for timesman_type::Times {
+    type Error = ConvertError;
+
+    fn try_from(value: grpc::Times) -> Result<Self, Self::Error> {
+        Ok(timesman_type::Times {
+            id: value.id,
+            title: value.title,
+            owner: value.owner,
+            created_at: date_time(required(value.created_at, "created_at")?)?,
+            updated_at: optional_date_time(value.updated_at)?,
+        })
+    }
+}
 
 impl From<timesman_type::Times> for grpc::Times {
     fn from(value: timesman_type::Times) -> Self {
-        let ctime = value.created_at;
-        let ctime = {
-            let c = value.created_at;
-            prost_types::Timestamp::date_time(
-                c.year() as i64,
-                c.month() as u8,
-                c.day() as u8,
-                c.hour() as u8,
-                c.minute() as u8,
-                c.second() as u8,
-            )
-            .unwrap()
-        };
+        Self {
+            id: value.id,
+            title: value.title,
+            owner: value.owner,
+            created_at: Some(timestamp(value.created_at)),
+            updated_at: value.updated_at.map(timestamp),
+        }
+    }
+}
 
-        let utime = if let Some(u) = value.updated_at {
-            let t = prost_types::Timestamp::date_time(
-                u.year() as i64,
-                u.month() as u8,
-                u.day() as u8,
-                u.hour() as u8,
-                u.minute() as u8,
-                u.second() as u8,
-            )
-            .unwrap();
-            Some(t)
-        } else {
-            None
-        };
+impl TryFrom<grpc::Post> for timesman_type::Post {
+    type Error = ConvertError;
+
+    fn try_from(value: grpc::Post) -> Result<Self, Self::Error> {
+        Ok(timesman_type::Post {
+            id: value.id,
+            post: value.post,
+            author: value.author,
+            created_at: date_time(required(value.created_at, "created_at")?)?,
+            updated_at: optional_date_time(value.updated_at)?,
+        })
+    }
+}
 
+impl From<timesman_type::Post> for grpc::Post {
+    fn from(value: timesman_type::Post) -> Self {
         Self {
-            id: value.id as u64,
-            title: value.title,
-            created_at: Some(ctime),
-            updated_at: utime,
+            id: value.id,
+            post: value.post,
+            author: value.author,
+            created_at: Some(timestamp(value.created_at)),
+            updated_at: value.updated_at.map(timestamp),
         }
     }
 }
 
-impl Into<timesman_type::Post> for grpc::Post {
-    fn into(self) -> timesman_type::Post {
-        let c = self.created_at.unwrap();
-        let ctime = chrono::DateTime::from_timestamp(c.seconds, c.nanos as u32)
-            .unwrap()
-            .naive_local();
+/// The size of the content chunks in attachment uploads and downloads.
+pub const CHUNK_SIZE: usize = 64 << 10;
 
-        let utime = if let Some(u) = self.updated_at {
-            Some(
-                chrono::DateTime::from_timestamp(u.seconds, u.nanos as u32)
-                    .unwrap()
-                    .naive_local(),
-            )
-        } else {
-            None
-        };
+/// Adds the API token, if any, to every request of a client.
+#[derive(Clone, Default)]
+pub struct TokenInterceptor {
+    token: Option<tonic::metadata::AsciiMetadataValue>,
+}
+
+impl TokenInterceptor {
+    pub fn new(
+        token: Option<&str>,
+    ) -> Result<Self, tonic::metadata::errors::InvalidMetadataValue> {
+        let token = token
+            .map(|t| format!("Bearer {t}").try_into())
+            .transpose()?;
+        Ok(Self { token })
+    }
+}
 
-        timesman_type::Post {
-            id: self.id,
-            post: self.post,
-            created_at: ctime,
-            updated_at: utime,
+impl tonic::service::Interceptor for TokenInterceptor {
+    fn call(
+        &mut self,
+        mut request: tonic::Request<()>,
+    ) -> Result<tonic::Request<()>, tonic::Status> {
+        if let Some(token) = &self.token {
+            request
+                .metadata_mut()
+                .insert("authorization", token.clone());
         }
+        Ok(request)
     }
 }
 
-impl From<timesman_type::Post> for grpc::Post {
-    fn from(value: timesman_type::Post) -> Self {
-        let ctime = {
-            let c = value.created_at;
-            prost_types::Timestamp::date_time(
-                c.year() as i64,
-                c.month() as u8,
-                c.day() as u8,
-                c.hour() as u8,
-                c.minute() as u8,
-                c.second() as u8,
-            )
-            .unwrap()
-        };
+/// The token of a request sent by a [`TokenInterceptor`].
+pub fn bearer_token(metadata: &tonic::metadata::MetadataMap) -> Option<&str> {
+    metadata
+        .get("authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+/// The TLS settings of a client, as PEM files.
+#[derive(Clone, Debug, Default)]
+pub struct ClientTls {
+    /// The CAs trusted to sign the certificate of the server. The system
+    /// roots are trusted without it.
+    pub ca: Option<PathBuf>,
+    /// The certificate presented to servers which require one.
+    pub cert: Option<PathBuf>,
+    /// The private key of `cert`.
+    pub key: Option<PathBuf>,
+}
 
-        let utime = if let Some(u) = value.updated_at {
-            let t = prost_types::Timestamp::date_time(
-                u.year() as i64,
-                u.month() as u8,
-                u.day() as u8,
-                u.hour() as u8,
-                u.minute() as u8,
-                u.second() as u8,
-            )
-            .unwrap();
-            Some(t)
-        } else {
-            None
+impl ClientTls {
+    /// Reads the files into the configuration of a channel.
+    pub fn config(&self) -> Result<ClientTlsConfig, String> {
+        let mut config = match &self.ca {
+            Some(ca) => ClientTlsConfig::new()
+                .ca_certificate(Certificate::from_pem(read_pem(ca)?)),
+            None => ClientTlsConfig::new().with_native_roots(),
         };
 
+        match (&self.cert, &self.key) {
+            (Some(cert), Some(key)) => {
+                config = config.identity(Identity::from_pem(
+                    read_pem(cert)?,
+                    read_pem(key)?,
+                ));
+            }
+            (None, None) => {}
+            _ => {
+                return Err(
+                    "a client certificate needs both cert and key".to_string()
+                )
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+fn read_pem(path: &Path) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Converts a timestamp to the UTC time used by `timesman_type`.
+pub fn date_time(
+    t: prost_types::Timestamp,
+) -> Result<DateTime<Utc>, ConvertError> {
+    let nanos = u32::try_from(t.nanos)
+        .ok()
+        .filter(|n| *n < 1_000_000_000)
+        .ok_or(ConvertError::InvalidTimestamp(t))?;
+
+    DateTime::from_timestamp(t.seconds, nanos)
+        .ok_or(ConvertError::InvalidTimestamp(t))
+}
+
+/// Converts an optional timestamp, keeping `None`.
+fn optional_date_time(
+    t: Option<prost_types::Timestamp>,
+) -> Result<Option<DateTime<Utc>>, ConvertError> {
+    t.map(date_time).transpose()
+}
+
+/// Converts a UTC time from `timesman_type` to a timestamp.
+pub fn timestamp(t: DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: t.timestamp(),
+        nanos: t.timestamp_subsec_nanos() as i32,
+    }
+}
+
+impl TryFrom<grpc::TimesPost> for timesman_type::TimesPost {
+    type Error = ConvertError;
+
+    fn try_from(value: grpc::TimesPost) -> Result<Self, Self::Error> {
+        Ok(timesman_type::TimesPost {
+            tid: value.tid,
+            post: required(value.post, "post")?.try_into()?,
+        })
+    }
+}
+
+impl From<timesman_type::TimesPost> for grpc::TimesPost {
+    fn from(value: timesman_type::TimesPost) -> Self {
         Self {
-            id: value.id,
+            tid: value.tid,
+            post: Some(value.post.into()),
+        }
+    }
+}
+
+impl From<grpc::Tag> for timesman_type::Tag {
+    fn from(value: grpc::Tag) -> Self {
+        timesman_type::Tag {
+            name: value.name,
+            count: value.count,
+        }
+    }
+}
+
+impl From<timesman_type::Tag> for grpc::Tag {
+    fn from(value: timesman_type::Tag) -> Self {
+        Self {
+            name: value.name,
+            count: value.count,
+        }
+    }
+}
+
+impl From<grpc::Acl> for timesman_type::Acl {
+    fn from(value: grpc::Acl) -> Self {
+        timesman_type::Acl {
+            owner: value.owner,
+            readers: value.readers,
+            writers: value.writers,
+            public: value.public,
+        }
+    }
+}
+
+impl From<timesman_type::Acl> for grpc::Acl {
+    fn from(value: timesman_type::Acl) -> Self {
+        Self {
+            owner: value.owner,
+            readers: value.readers,
+            writers: value.writers,
+            public: value.public,
+        }
+    }
+}
+
+impl TryFrom<grpc::TrashedTimes> for timesman_type::TrashedTimes {
+    type Error = ConvertError;
+
+    fn try_from(value: grpc::TrashedTimes) -> Result<Self, Self::Error> {
+        Ok(timesman_type::TrashedTimes {
+            times: required(value.times, "times")?.try_into()?,
+            deleted_at: date_time(required(value.deleted_at, "deleted_at")?)?,
+        })
+    }
+}
+
+impl From<timesman_type::TrashedTimes> for grpc::TrashedTimes {
+    fn from(value: timesman_type::TrashedTimes) -> Self {
+        Self {
+            times: Some(value.times.into()),
+            deleted_at: Some(timestamp(value.deleted_at)),
+        }
+    }
+}
+
+impl TryFrom<grpc::TrashedPost> for timesman_type::TrashedPost {
+    type Error = ConvertError;
+
+    fn try_from(value: grpc::TrashedPost) -> Result<Self, Self::Error> {
+        Ok(timesman_type::TrashedPost {
+            tid: value.tid,
+            post: required(value.post, "post")?.try_into()?,
+            deleted_at: date_time(required(value.deleted_at, "deleted_at")?)?,
+        })
+    }
+}
+
+impl From<timesman_type::TrashedPost> for grpc::TrashedPost {
+    fn from(value: timesman_type::TrashedPost) -> Self {
+        Self {
+            tid: value.tid,
+            post: Some(value.post.into()),
+            deleted_at: Some(timestamp(value.deleted_at)),
+        }
+    }
+}
+
+impl TryFrom<grpc::Trash> for timesman_type::Trash {
+    type Error = ConvertError;
+
+    fn try_from(value: grpc::Trash) -> Result<Self, Self::Error> {
+        Ok(timesman_type::Trash {
+            times: value
+                .times
+                .into_iter()
+                .map(|t| t.try_into())
+                .collect::<Result<_, _>>()?,
+            posts: value
+                .posts
+                .into_iter()
+                .map(|p| p.try_into())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl From<timesman_type::Trash> for grpc::Trash {
+    fn from(value: timesman_type::Trash) -> Self {
+        Self {
+            times: value.times.into_iter().map(|t| t.into()).collect(),
+            posts: value.posts.into_iter().map(|p| p.into()).collect(),
+        }
+    }
+}
+
+impl TryFrom<grpc::Revision> for timesman_type::Revision {
+    type Error = ConvertError;
+
+    fn try_from(value: grpc::Revision) -> Result<Self, Self::Error> {
+        Ok(timesman_type::Revision {
+            rev: value.rev,
             post: value.post,
-            created_at: Some(ctime),
-            updated_at: utime,
+            created_at: date_time(required(value.created_at, "created_at")?)?,
+        })
+    }
+}
+
+impl From<timesman_type::Revision> for grpc::Revision {
+    fn from(value: timesman_type::Revision) -> Self {
+        Self {
+            rev: value.rev,
+            post: value.post,
+            created_at: Some(timestamp(value.created_at)),
+        }
+    }
+}
+
+impl TryFrom<grpc::Attachment> for timesman_type::Attachment {
+    type Error = ConvertError;
+
+    fn try_from(value: grpc::Attachment) -> Result<Self, Self::Error> {
+        Ok(timesman_type::Attachment {
+            id: value.id,
+            pid: value.pid,
+            name: value.name,
+            mime: value.mime,
+            size: value.size,
+            hash: value.hash,
+            created_at: date_time(required(value.created_at, "created_at")?)?,
+        })
+    }
+}
+
+impl From<timesman_type::Attachment> for grpc::Attachment {
+    fn from(value: timesman_type::Attachment) -> Self {
+        Self {
+            id: value.id,
+            pid: value.pid,
+            name: value.name,
+            mime: value.mime,
+            size: value.size,
+            hash: value.hash,
+            created_at: Some(timestamp(value.created_at)),
         }
     }
 }
+
+impl grpc::TimesEvent {
+    /// The event a times watch reports for `change`, if it is about a times.
+    pub fn from_change(
+        token: u64,
+        change: timesman_type::Change,
+    ) -> Option<Self> {
+        use grpc::times_event::Event;
+        use timesman_type::Change;
+
+        let event = match change {
+            Change::TimesCreated { times } => Event::Created(times.into()),
+            Change::TimesUpdated { times } => Event::Updated(times.into()),
+            Change::TimesDeleted { tid } => Event::Deleted(tid),
+            _ => return None,
+        };
+
+        Some(Self {
+            token,
+            event: Some(event),
+        })
+    }
+
+    /// The change the event reports. The first event of a watch has none.
+    pub fn into_change(
+        self,
+    ) -> Result<Option<timesman_type::Change>, ConvertError> {
+        use grpc::times_event::Event;
+        use timesman_type::Change;
+
+        let Some(event) = self.event else {
+            return Ok(None);
+        };
+
+        Ok(Some(match event {
+            Event::Created(times) => Change::TimesCreated {
+                times: times.try_into()?,
+            },
+            Event::Updated(times) => Change::TimesUpdated {
+                times: times.try_into()?,
+            },
+            Event::Deleted(tid) => Change::TimesDeleted { tid },
+        }))
+    }
+}
+
+impl grpc::PostEvent {
+    /// The event a post watch reports for `change`, if it is about a post.
+    pub fn from_change(
+        token: u64,
+        change: timesman_type::Change,
+    ) -> Option<Self> {
+        use grpc::post_event::Event;
+        use timesman_type::Change;
+
+        let (tid, event) = match change {
+            Change::PostCreated { tid, post } => {
+                (tid, Event::Created(post.into()))
+            }
+            Change::PostUpdated { tid, post } => {
+                (tid, Event::Updated(post.into()))
+            }
+            Change::PostDeleted { tid, pid } => (tid, Event::Deleted(pid)),
+            _ => return None,
+        };
+
+        Some(Self {
+            token,
+            tid,
+            event: Some(event),
+        })
+    }
+
+    /// The change the event reports. The first event of a watch has none.
+    pub fn into_change(
+        self,
+    ) -> Result<Option<timesman_type::Change>, ConvertError> {
+        use grpc::post_event::Event;
+        use timesman_type::Change;
+
+        let tid = self.tid;
+        let Some(event) = self.event else {
+            return Ok(None);
+        };
+
+        Ok(Some(match event {
+            Event::Created(post) => Change::PostCreated {
+                tid,
+                post: post.try_into()?,
+            },
+            Event::Updated(post) => Change::PostUpdated {
+                tid,
+                post: post.try_into()?,
+            },
+            Event::Deleted(pid) => Change::PostDeleted { tid, pid },
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at_nanos() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 2, 29, 23, 59, 59).unwrap()
+            + chrono::TimeDelta::nanoseconds(123_456_789)
+    }
+
+    #[test]
+    fn round_trips_times() {
+        let times = timesman_type::Times {
+            id: 1,
+            title: "t".to_string(),
+            created_at: at_nanos(),
+            updated_at: Some(at_nanos() + chrono::TimeDelta::nanoseconds(1)),
+            owner: Some("alice".to_string()),
+        };
+
+        let back: timesman_type::Times =
+            grpc::Times::from(times.clone()).try_into().unwrap();
+        assert_eq!(back.id, times.id);
+        assert_eq!(back.title, times.title);
+        assert_eq!(back.created_at, times.created_at);
+        assert_eq!(back.updated_at, times.updated_at);
+        assert_eq!(back.owner, times.owner);
+    }
+
+    #[test]
+    fn round_trips_posts() {
+        let post = timesman_type::Post {
+            id: 2,
+            post: "p".to_string(),
+            created_at: at_nanos(),
+            updated_at: None,
+            author: None,
+        };
+
+        let back: timesman_type::Post =
+            grpc::Post::from(post.clone()).try_into().unwrap();
+        assert_eq!(back.id, post.id);
+        assert_eq!(back.post, post.post);
+        assert_eq!(back.created_at, post.created_at);
+        assert_eq!(back.updated_at, None);
+        assert_eq!(back.author, None);
+    }
+
+    #[test]
+    fn round_trips_timestamps() {
+        let t = timestamp(at_nanos());
+        assert_eq!(t.nanos, 123_456_789);
+        assert_eq!(timestamp(date_time(t).unwrap()), t);
+    }
+
+    #[test]
+    fn rejects_missing_fields() {
+        let times = grpc::Times {
+            id: 1,
+            title: "t".to_string(),
+            created_at: None,
+            updated_at: None,
+            owner: None,
+        };
+
+        assert_eq!(
+            timesman_type::Times::try_from(times).err(),
+            Some(ConvertError::MissingField("created_at"))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_timestamps() {
+        for t in [
+            prost_types::Timestamp {
+                seconds: 0,
+                nanos: -1,
+            },
+            prost_types::Timestamp {
+                seconds: 0,
+                nanos: 1_000_000_000,
+            },
+            prost_types::Timestamp {
+                seconds: i64::MAX,
+                nanos: 0,
+            },
+        ] {
+            assert_eq!(date_time(t), Err(ConvertError::InvalidTimestamp(t)));
+        }
+
+        let status = tonic::Status::from(ConvertError::MissingField("post"));
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,198 @@
+//! Enforcement of the ACL of each times.
+//!
+//! The front-ends check the ACL before calling into the store. Requests
+//! acting for no user, made without tokens or with a token which has no
+//! user, are not restricted. Times a user may not read are reported as not
+//! found, so their existence does not leak.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use timesman_bstore::{SearchFilter, Store, StoreError};
+use timesman_type::{Tag, TimesPost, Trash};
+
+/// What a request does to a times.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+    /// Looks at the times and its posts.
+    Read,
+    /// Adds, edits or deletes posts.
+    Write,
+    /// Changes the times itself or its ACL.
+    Manage,
+}
+
+/// Fails unless `user` may access the times `tid` as `access`.
+pub async fn authorize(
+    store: &mut (dyn Store + Send + Sync),
+    user: Option<&str>,
+    tid: u64,
+    access: Access,
+) -> Result<(), StoreError> {
+    let Some(user) = user else {
+        return Ok(());
+    };
+
+    let acl = store.get_acl(tid).await?;
+    if !acl.can_read(user) {
+        return Err(StoreError::NotFound(format!("times {tid}")));
+    }
+
+    match access {
+        Access::Read => Ok(()),
+        Access::Write if acl.can_write(user) => Ok(()),
+        Access::Write => Err(StoreError::PermissionDenied(format!(
+            "{user} may only read times {tid}"
+        ))),
+        Access::Manage if acl.can_manage(user) => Ok(()),
+        Access::Manage if acl.owner.is_none() => {
+            Err(StoreError::PermissionDenied(format!(
+                "times {tid} has no owner, so only requests without a user \
+                 may change it"
+            )))
+        }
+        Access::Manage => Err(StoreError::PermissionDenied(format!(
+            "only the owner may change times {tid}"
+        ))),
+    }
+}
+
+/// Keeps the items in the times `user` may read. `tid` tells the times of
+/// an item.
+pub async fn retain_readable<T>(
+    store: &mut (dyn Store + Send + Sync),
+    user: Option<&str>,
+    items: Vec<T>,
+    tid: impl Fn(&T) -> u64,
+) -> Result<Vec<T>, StoreError> {
+    let Some(user) = user else {
+        return Ok(items);
+    };
+
+    let mut readable = HashMap::new();
+    let mut kept = vec![];
+    for item in items {
+        let tid = tid(&item);
+        let can_read = match readable.get(&tid) {
+            Some(can_read) => *can_read,
+            None => {
+                let can_read = store.get_acl(tid).await?.can_read(user);
+                readable.insert(tid, can_read);
+                can_read
+            }
+        };
+
+        if can_read {
+            kept.push(item);
+        }
+    }
+
+    Ok(kept)
+}
+
+/// The tags counted over the posts `user` may read.
+pub async fn readable_tags(
+    store: &mut (dyn Store + Send + Sync),
+    user: Option<&str>,
+) -> Result<Vec<Tag>, StoreError> {
+    let tags = store.list_tags().await?;
+    if user.is_none() {
+        return Ok(tags);
+    }
+
+    let mut counted = vec![];
+    for tag in tags {
+        let posts = store.get_posts_by_tag(tag.name.clone()).await?;
+        let posts = retain_readable(store, user, posts, |p| p.tid).await?;
+        if !posts.is_empty() {
+            counted.push(Tag {
+                name: tag.name,
+                count: posts.len() as u64,
+            });
+        }
+    }
+    counted.sort_by(|a, b| b.count.cmp(&a.count).then(a.name.cmp(&b.name)));
+
+    Ok(counted)
+}
+
+/// Searches the posts `user` may read. The limit of `filter` applies after
+/// the unreadable hits are left out, so they do not take up its room.
+pub async fn readable_search(
+    store: &mut (dyn Store + Send + Sync),
+    user: Option<&str>,
+    query: String,
+    filter: SearchFilter,
+) -> Result<Vec<TimesPost>, StoreError> {
+    if user.is_none() {
+        return store.search_posts(query, filter).await;
+    }
+
+    let limit = filter.limit;
+    let unlimited = SearchFilter {
+        limit: None,
+        ..filter
+    };
+    let posts = store.search_posts(query, unlimited).await?;
+    let mut posts = retain_readable(store, user, posts, |p| p.tid).await?;
+    if let Some(limit) = limit {
+        posts.truncate(limit);
+    }
+
+    Ok(posts)
+}
+
+/// The trash of the times `user` may read.
+pub async fn readable_trash(
+    store: &mut (dyn Store + Send + Sync),
+    user: Option<&str>,
+) -> Result<Trash, StoreError> {
+    let trash = store.list_trash().await?;
+
+    Ok(Trash {
+        times: retain_readable(store, user, trash.times, |t| t.times.id)
+            .await?,
+        posts: retain_readable(store, user, trash.posts, |p| p.tid).await?,
+    })
+}
+
+/// Only requests without a user may empty the whole trash, since it holds
+/// the times of every user.
+pub fn authorize_purge_trash(user: Option<&str>) -> Result<(), StoreError> {
+    match user {
+        Some(user) => Err(StoreError::PermissionDenied(format!(
+            "{user} may only purge single times and posts"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Tells which changes of a stream a user may see.
+#[derive(Clone)]
+pub struct Reader {
+    store: Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
+    user: Option<String>,
+}
+
+impl Reader {
+    pub fn new(
+        store: Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
+        user: Option<String>,
+    ) -> Self {
+        Self { store, user }
+    }
+
+    /// Whether the user may read the times `tid` now. The ACL is looked up
+    /// every time, so changes to it apply to running streams.
+    pub async fn can_read(&self, tid: u64) -> bool {
+        let Some(user) = &self.user else {
+            return true;
+        };
+
+        let mut store = self.store.lock().await;
+        authorize(&mut **store, Some(user), tid, Access::Read)
+            .await
+            .is_ok()
+    }
+}
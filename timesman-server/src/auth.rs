@@ -0,0 +1,177 @@
+//! API tokens of the front-ends.
+//!
+//! Only the SHA-256 of a token is kept, in a JSON file the `token` admin
+//! command manages. The servers reload the file when it changes, so new and
+//! revoked tokens take effect without a restart.
+//!
+//! A token may act for a user of the store. Requests made with it see that
+//! user's times, and what they create is owned by the user.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Prefixes every token so a leaked one is easy to recognize.
+const TOKEN_PREFIX: &str = "tm_";
+
+/// An API token as stored by the server.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct TokenRecord {
+    pub name: String,
+    /// SHA-256 of the token, hex encoded.
+    pub hash: String,
+    pub created_at: DateTime<Utc>,
+    /// The user the token acts for. Tokens without one see every times.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+/// Who made a request, as told by its token.
+#[derive(Clone, Debug, Default)]
+pub struct Caller {
+    pub user: Option<String>,
+}
+
+struct Cache {
+    modified: Option<SystemTime>,
+    /// The callers by the hashes of their tokens.
+    callers: HashMap<String, Caller>,
+}
+
+/// The API tokens kept in a file.
+pub struct Tokens {
+    path: PathBuf,
+    cache: RwLock<Cache>,
+}
+
+fn hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn generate() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    bytes.iter().fold(TOKEN_PREFIX.to_string(), |mut s, b| {
+        s.push_str(&format!("{b:02x}"));
+        s
+    })
+}
+
+impl Tokens {
+    pub fn open(path: PathBuf) -> Self {
+        Self {
+            path,
+            cache: RwLock::new(Cache {
+                modified: None,
+                callers: HashMap::new(),
+            }),
+        }
+    }
+
+    /// The caller of `token`, if it is one of the stored tokens.
+    pub fn authenticate(&self, token: &str) -> Option<Caller> {
+        self.reload();
+        self.cache
+            .read()
+            .unwrap()
+            .callers
+            .get(&hash(token))
+            .cloned()
+    }
+
+    fn reload(&self) {
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok();
+        if self.cache.read().unwrap().modified == modified {
+            return;
+        }
+
+        let records = self.list().unwrap_or_else(|e| {
+            tracing::error!("failed to load the tokens: {e}");
+            vec![]
+        });
+
+        let mut cache = self.cache.write().unwrap();
+        cache.modified = modified;
+        cache.callers = records
+            .into_iter()
+            .map(|r| (r.hash, Caller { user: r.user }))
+            .collect();
+    }
+
+    pub fn list(&self) -> Result<Vec<TokenRecord>, String> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+
+        let file = File::open(&self.path).map_err(|e| format!("{e}"))?;
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("{}: {e}", self.path.display()))
+    }
+
+    fn save(&self, records: &[TokenRecord]) -> Result<(), String> {
+        let mut tmpname = self.path.file_name().unwrap_or_default().to_owned();
+        tmpname.push(".tmp");
+        let tmppath = self.path.with_file_name(tmpname);
+
+        // a torn file would lock every client out, so the new one is on
+        // disk before it replaces the old
+        let write = || -> std::io::Result<()> {
+            let mut bw = BufWriter::new(File::create(&tmppath)?);
+            serde_json::to_writer_pretty(&mut bw, records)?;
+            bw.into_inner()?.sync_all()?;
+            std::fs::rename(&tmppath, &self.path)
+        };
+
+        write().map_err(|e| {
+            let _ = std::fs::remove_file(&tmppath);
+            format!("{}: {e}", self.path.display())
+        })
+    }
+
+    /// Stores a new token named `name`, acting for `user` if given, and
+    /// returns it. The token itself is not kept, so this is the only chance
+    /// to see it.
+    pub fn create(
+        &self,
+        name: &str,
+        user: Option<&str>,
+    ) -> Result<String, String> {
+        let mut records = self.list()?;
+        if records.iter().any(|r| r.name == name) {
+            return Err(format!("token {name} already exists"));
+        }
+
+        let token = generate();
+        records.push(TokenRecord {
+            name: name.to_string(),
+            hash: hash(&token),
+            created_at: Utc::now(),
+            user: user.map(str::to_string),
+        });
+        self.save(&records)?;
+
+        Ok(token)
+    }
+
+    pub fn revoke(&self, name: &str) -> Result<(), String> {
+        let mut records = self.list()?;
+        let len = records.len();
+        records.retain(|r| r.name != name);
+        if records.len() == len {
+            return Err(format!("token {name} is not found"));
+        }
+
+        self.save(&records)
+    }
+}
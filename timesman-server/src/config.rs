@@ -1,28 +1,155 @@
 use serde::{Deserialize, Serialize};
 use std::io::Read;
+use std::sync::Arc;
 use std::{default::Default, fs::File, path::PathBuf};
+use tokio::sync::Mutex;
 
-use toml;
+use timesman_bstore::json::JsonStore;
+use timesman_bstore::notify::NotifyingStore;
+use timesman_bstore::ram::RamStore;
+use timesman_bstore::remote::RemoteStore;
+use timesman_bstore::sqlite::SqliteStoreBuilder;
+use timesman_bstore::Store;
+use timesman_server::auth::Tokens;
+use timesman_server::{Listener, Protocol};
 
 #[derive(Deserialize, Serialize, Clone)]
 pub struct Config {
-    pub listen: String,
-    pub front_type: String,
-    pub store_type: String,
-    pub store_param: String,
+    /// The front-ends to serve, each from its own `[[listeners]]` table.
+    #[serde(default)]
+    pub listeners: Vec<Listener>,
+    /// The address of the single front-end of older configs.
+    #[serde(default, skip_serializing)]
+    pub listen: Option<String>,
+    /// The protocol of the single front-end of older configs.
+    #[serde(default, skip_serializing)]
+    pub front_type: Option<Protocol>,
+    /// The backend keeping the data, configured by the table of its name.
+    pub store_type: StoreType,
+    #[serde(default)]
+    pub sqlite: SqliteConfig,
+    #[serde(default)]
+    pub json: JsonConfig,
+    pub proxy: Option<ProxyConfig>,
+    /// Requires API tokens when set; every request is accepted otherwise.
+    pub auth: Option<AuthConfig>,
+    /// Serves listeners beyond the loopback interface without `[auth]`,
+    /// which is refused otherwise.
+    #[serde(default)]
+    pub allow_anonymous: bool,
+    /// The seconds to wait for the requests in flight on shutdown.
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout: u64,
+    /// The path of the sqlite or json backend of older configs.
+    #[serde(default, skip_serializing)]
+    pub store_param: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreType {
+    Sqlite,
+    Json,
+    /// Keeps everything in memory and loses it on exit, e.g. for tests.
+    Memory,
+    /// Forwards everything to an upstream timesd.
+    Proxy,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct SqliteConfig {
+    pub path: String,
+}
+
+impl Default for SqliteConfig {
+    fn default() -> Self {
+        Self {
+            path: "./database.db".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct JsonConfig {
+    pub path: PathBuf,
+}
+
+impl Default for JsonConfig {
+    fn default() -> Self {
+        Self {
+            path: "./database.json".into(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct ProxyConfig {
+    /// The protocol to talk to the upstream with.
+    pub protocol: Protocol,
+    /// The URL of the upstream, e.g. `http://127.0.0.1:8080`.
+    pub upstream: String,
+    /// The API token to send to the upstream.
+    pub token: Option<String>,
+    /// The PEM bundle of the CAs trusted to sign the certificate of a grpc
+    /// upstream, instead of the system roots.
+    pub ca: Option<PathBuf>,
+    /// The PEM certificate presented to a grpc upstream requiring one.
+    pub cert: Option<PathBuf>,
+    /// The PEM private key of `cert`.
+    pub key: Option<PathBuf>,
+}
+
+impl ProxyConfig {
+    fn has_tls(&self) -> bool {
+        self.ca.is_some() || self.cert.is_some() || self.key.is_some()
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct AuthConfig {
+    /// The file keeping the hashes of the API tokens.
+    pub tokens: PathBuf,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            listen: "localhost:8080".to_string(),
-            front_type: "http".to_string(),
-            store_type: "sqlite".to_string(),
-            store_param: "./database.db".to_string(),
+            listeners: vec![Listener {
+                protocol: Protocol::Http,
+                address: "localhost:8080".to_string(),
+                tls: None,
+            }],
+            listen: None,
+            front_type: None,
+            store_type: StoreType::Sqlite,
+            sqlite: SqliteConfig::default(),
+            json: JsonConfig::default(),
+            proxy: None,
+            auth: None,
+            allow_anonymous: false,
+            shutdown_timeout: default_shutdown_timeout(),
+            store_param: None,
         }
     }
 }
 
+fn default_shutdown_timeout() -> u64 {
+    10
+}
+
+/// Whether only the local host can reach `listener`. Host names other than
+/// `localhost` may resolve to anything, so they are not.
+fn is_loopback(listener: &Listener) -> bool {
+    if let Ok(addr) = listener.address.parse::<std::net::SocketAddr>() {
+        return addr.ip().is_loopback();
+    }
+
+    listener
+        .address
+        .rsplit_once(':')
+        .is_some_and(|(host, _)| host == "localhost")
+}
+
 impl Config {
     pub fn load(path: PathBuf) -> Result<Self, String> {
         if !path.exists() {
@@ -33,8 +160,332 @@ impl Config {
         let mut file = File::open(path).map_err(|e| format!("{e}"))?;
         file.read_to_string(&mut buf).map_err(|e| format!("{e}"))?;
 
-        let config: Config =
-            toml::from_str(&buf).map_err(|e| format!("{e}"))?;
+        Self::parse(&buf)
+    }
+
+    fn parse(buf: &str) -> Result<Self, String> {
+        let mut config: Config =
+            toml::from_str(buf).map_err(|e| format!("{e}"))?;
+
+        match (config.listen.take(), config.front_type.take()) {
+            (Some(address), Some(protocol)) => {
+                config.listeners.push(Listener {
+                    protocol,
+                    address,
+                    tls: None,
+                })
+            }
+            (None, None) => {}
+            _ => {
+                return Err(
+                    "listen and front_type must be given together".to_string()
+                )
+            }
+        }
+        if config.listeners.is_empty() {
+            return Err("no listeners are configured".to_string());
+        }
+
+        if let Some(param) = config.store_param.take() {
+            match config.store_type {
+                StoreType::Sqlite => config.sqlite.path = param,
+                StoreType::Json => config.json.path = param.into(),
+                _ => {
+                    return Err("store_param is only used by sqlite and json"
+                        .to_string())
+                }
+            }
+        }
+        if config.store_type == StoreType::Proxy && config.proxy.is_none() {
+            return Err("the proxy store needs a [proxy] table".to_string());
+        }
+        if config
+            .listeners
+            .iter()
+            .any(|l| l.protocol == Protocol::Http && l.tls.is_some())
+        {
+            return Err("TLS is only supported by grpc listeners".to_string());
+        }
+        if let Some(proxy) = &config.proxy {
+            if proxy.protocol == Protocol::Http && proxy.has_tls() {
+                return Err("ca, cert and key are only used by grpc upstreams"
+                    .to_string());
+            }
+        }
+        if config.auth.is_none() && !config.allow_anonymous {
+            if let Some(l) = config.listeners.iter().find(|l| !is_loopback(l)) {
+                return Err(format!(
+                    "{} is reachable from other hosts, which needs an [auth] \
+                     table, or allow_anonymous = true to accept every request",
+                    l.address
+                ));
+            }
+        }
+
         Ok(config)
     }
+
+    /// The API tokens the listeners require, if any.
+    pub fn tokens(&self) -> Option<Arc<Tokens>> {
+        self.auth
+            .as_ref()
+            .map(|auth| Arc::new(Tokens::open(auth.tokens.clone())))
+    }
+
+    /// Opens the configured backend, ready to be shared by the listeners.
+    pub async fn open_store(
+        &self,
+    ) -> Result<Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>, String>
+    {
+        let store: Box<dyn Store + Send + Sync + 'static> =
+            match self.store_type {
+                StoreType::Sqlite => Box::new(
+                    SqliteStoreBuilder::new(&self.sqlite.path)
+                        .build()
+                        .await
+                        .map_err(|e| format!("sqlite: {}", e.message()))?,
+                ),
+                StoreType::Json => Box::new(
+                    JsonStore::build(self.json.path.clone())
+                        .map_err(|e| format!("json: {}", e.message()))?,
+                ),
+                StoreType::Memory => Box::new(RamStore::new()),
+                StoreType::Proxy => self.open_proxy().await?,
+            };
+
+        let store: Box<dyn Store + Send + Sync + 'static> =
+            Box::new(NotifyingStore::new(store));
+        Ok(Arc::new(Mutex::new(store)))
+    }
+
+    async fn open_proxy(
+        &self,
+    ) -> Result<Box<dyn Store + Send + Sync + 'static>, String> {
+        let Some(proxy) = &self.proxy else {
+            return Err("the proxy store needs a [proxy] table".to_string());
+        };
+
+        let mut store: Box<dyn Store + Send + Sync + 'static> = match proxy
+            .protocol
+        {
+            Protocol::Http => {
+                let store = RemoteStore::new(proxy.upstream.clone());
+                match &proxy.token {
+                    Some(token) => Box::new(store.with_token(token.clone())),
+                    None => Box::new(store),
+                }
+            }
+            #[cfg(feature = "grpc")]
+            Protocol::Grpc => {
+                let mut builder = timesman_bstore::grpc::GrpcStoreBuilder::new(
+                    proxy.upstream.clone(),
+                );
+                if let Some(token) = &proxy.token {
+                    builder = builder.token(token.clone());
+                }
+                if proxy.has_tls() {
+                    builder = builder.tls(timesman_grpc::ClientTls {
+                        ca: proxy.ca.clone(),
+                        cert: proxy.cert.clone(),
+                        key: proxy.key.clone(),
+                    });
+                }
+                Box::new(
+                    builder
+                        .build()
+                        .await
+                        .map_err(|e| format!("proxy: {}", e.message()))?,
+                )
+            }
+            #[cfg(not(feature = "grpc"))]
+            Protocol::Grpc => {
+                return Err("Grpc is not supported by this build".into())
+            }
+        };
+
+        store
+            .check()
+            .await
+            .map_err(|e| format!("proxy: {}", e.message()))?;
+        Ok(store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_backend_tables() {
+        let config = Config::parse(
+            r#"
+            store_type = "json"
+
+            [[listeners]]
+            protocol = "http"
+            address = "127.0.0.1:8080"
+
+            [[listeners]]
+            protocol = "grpc"
+            address = "127.0.0.1:50051"
+
+            [listeners.tls]
+            cert = "server.pem"
+            key = "server.key"
+            client_ca = "ca.pem"
+
+            [json]
+            path = "times.json"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.store_type, StoreType::Json);
+        assert!(config.listeners[0].tls.is_none());
+        let tls = config.listeners[1].tls.as_ref().unwrap();
+        assert_eq!(tls.client_ca, Some(PathBuf::from("ca.pem")));
+        assert_eq!(config.json.path, PathBuf::from("times.json"));
+        assert_eq!(config.sqlite.path, "./database.db");
+        assert_eq!(config.shutdown_timeout, 10);
+    }
+
+    #[test]
+    fn accepts_older_configs() {
+        let config = Config::parse(
+            r#"
+            listen = "127.0.0.1:8080"
+            front_type = "grpc"
+            store_type = "sqlite"
+            store_param = "old.db"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.listeners.len(), 1);
+        assert_eq!(config.listeners[0].protocol, Protocol::Grpc);
+        assert_eq!(config.sqlite.path, "old.db");
+    }
+
+    #[test]
+    fn rejects_incomplete_configs() {
+        let listeners = r#"
+            [[listeners]]
+            protocol = "http"
+            address = "127.0.0.1:8080"
+        "#;
+
+        for store in ["store_type = \"proxy\"", "store_type = \"mongo\""] {
+            assert!(Config::parse(&format!("{store}\n{listeners}")).is_err());
+        }
+        assert!(Config::parse("store_type = \"memory\"").is_err());
+
+        let http_tls = r#"
+            store_type = "memory"
+
+            [[listeners]]
+            protocol = "http"
+            address = "127.0.0.1:8080"
+
+            [listeners.tls]
+            cert = "server.pem"
+            key = "server.key"
+        "#;
+        assert!(Config::parse(http_tls).is_err());
+    }
+
+    #[test]
+    fn requires_auth_beyond_loopback() {
+        let listen = |address: &str| {
+            format!(
+                r#"
+                store_type = "memory"
+
+                [[listeners]]
+                protocol = "grpc"
+                address = "{address}"
+                "#
+            )
+        };
+
+        for address in ["127.0.0.1:8080", "[::1]:8080", "localhost:8080"] {
+            assert!(Config::parse(&listen(address)).is_ok(), "{address}");
+        }
+        for address in ["0.0.0.0:8080", "192.0.2.1:8080", "example.com:80"] {
+            let err = Config::parse(&listen(address)).err().unwrap();
+            assert!(err.contains("[auth]"), "{address}");
+
+            let opened = format!("allow_anonymous = true\n{}", listen(address));
+            assert!(Config::parse(&opened).is_ok());
+            let authed = format!(
+                "{}\n[auth]\ntokens = \"tokens.json\"",
+                listen(address)
+            );
+            assert!(Config::parse(&authed).is_ok());
+        }
+    }
+
+    #[actix_web::test]
+    async fn proxies_to_an_upstream() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let upstream = Config::parse(
+            r#"
+            store_type = "memory"
+
+            [[listeners]]
+            protocol = "http"
+            address = "127.0.0.1:0"
+            "#,
+        )
+        .unwrap()
+        .open_store()
+        .await
+        .unwrap();
+        {
+            let addr = addr.clone();
+            let upstream = upstream.clone();
+            actix_web::rt::spawn(async move {
+                use timesman_server::TimesManServer;
+                timesman_server::http::HttpServer::default()
+                    .run(&addr, upstream)
+                    .await
+                    .unwrap();
+            });
+        }
+
+        let config = format!(
+            r#"
+            store_type = "proxy"
+
+            [[listeners]]
+            protocol = "grpc"
+            address = "127.0.0.1:0"
+
+            [proxy]
+            protocol = "http"
+            upstream = "http://{addr}"
+            "#
+        );
+        let config = Config::parse(&config).unwrap();
+
+        let mut proxy = None;
+        for _ in 0..100 {
+            if let Ok(store) = config.open_store().await {
+                proxy = Some(store);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        let proxy = proxy.expect("upstream did not come up");
+
+        let times = proxy
+            .lock()
+            .await
+            .create_times("proxied".to_string())
+            .await
+            .unwrap();
+        let upstream_times = upstream.lock().await.get_times().await.unwrap();
+        assert_eq!(upstream_times.len(), 1);
+        assert_eq!(upstream_times[0].id, times.id);
+    }
 }
@@ -1,53 +1,325 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::pin::Pin;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::{Mutex, MutexGuard};
+use tokio_stream::wrappers::TcpListenerStream;
+use tokio_stream::Stream;
+use tokio_util::sync::CancellationToken;
 
-use super::TimesManServer;
+use super::acl::{self, authorize, Access, Reader};
+use super::auth::{Caller, Tokens};
+use super::{TimesManServer, TlsConfig};
 
-use timesman_bstore::Store;
+use timesman_bstore::{
+    PostQuery, SearchFilter, Store, StoreError, MAX_ATTACHMENT_SIZE,
+};
 
 use async_trait::async_trait;
+use futures_util::{future, stream, StreamExt};
+use timesman_type::Change;
 
 use timesman_grpc::grpc;
 use timesman_grpc::grpc::times_man_server;
+use timesman_grpc::grpc::{download_chunk, upload_chunk};
 
 use tonic::transport::server::Server;
+use tonic::transport::{Certificate, Identity, ServerTlsConfig};
 
-pub struct GrpcServer {}
+#[derive(Default)]
+pub struct GrpcServer {
+    /// The API tokens to accept. Every request is accepted without them.
+    pub tokens: Option<Arc<Tokens>>,
+    /// Serves over TLS, requiring client certificates if it has a CA.
+    pub tls: Option<ServerTlsConfig>,
+}
+
+/// Reads the files of `config` into the configuration of a server.
+pub fn tls_config(config: &TlsConfig) -> Result<ServerTlsConfig, String> {
+    let read = |path: &PathBuf| {
+        std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))
+    };
+
+    let mut tls = ServerTlsConfig::new()
+        .identity(Identity::from_pem(read(&config.cert)?, read(&config.key)?));
+    if let Some(ca) = &config.client_ca {
+        tls = tls.client_ca_root(Certificate::from_pem(read(ca)?));
+    }
+
+    Ok(tls)
+}
 
 #[tonic::async_trait]
 impl TimesManServer for GrpcServer {
-    async fn run(
+    async fn run_until(
         &self,
         listen: &str,
         store: Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
-    ) {
-        let addr = listen.parse().unwrap();
-
-        Server::builder()
-            .add_service(times_man_server::TimesManServer::new(TMServer {
-                store,
-            }))
-            .serve(addr)
+        shutdown: CancellationToken,
+    ) -> Result<(), String> {
+        let listener = TcpListener::bind(listen)
             .await
-            .unwrap();
+            .map_err(|e| format!("failed to bind {listen}: {e}"))?;
+
+        let changes = ChangeLog::start(&store, shutdown.clone()).await;
+
+        let mut builder = Server::builder();
+        if let Some(tls) = &self.tls {
+            builder = builder
+                .tls_config(tls.clone())
+                .map_err(|e| format!("{listen}: {e}"))?;
+        }
+
+        builder
+            .add_service(times_man_server::TimesManServer::with_interceptor(
+                TMServer { store, changes },
+                Authenticator {
+                    tokens: self.tokens.clone(),
+                },
+            ))
+            .serve_with_incoming_shutdown(
+                TcpListenerStream::new(listener),
+                shutdown.cancelled_owned(),
+            )
+            .await
+            .map_err(|e| format!("{listen}: {e}"))
     }
 }
 
+/// Rejects requests without one of the API tokens, if the server has any.
+#[derive(Clone)]
+struct Authenticator {
+    tokens: Option<Arc<Tokens>>,
+}
+
+impl tonic::service::Interceptor for Authenticator {
+    fn call(
+        &mut self,
+        mut req: tonic::Request<()>,
+    ) -> Result<tonic::Request<()>, tonic::Status> {
+        let Some(tokens) = &self.tokens else {
+            return Ok(req);
+        };
+
+        let caller = timesman_grpc::bearer_token(req.metadata())
+            .and_then(|token| tokens.authenticate(token));
+        match caller {
+            Some(caller) => {
+                req.extensions_mut().insert(caller);
+                Ok(req)
+            }
+            None => Err(tonic::Status::unauthenticated(
+                "a valid API token is required",
+            )),
+        }
+    }
+}
+
+/// The user the request acts for, if its token has one.
+fn user_of<T>(request: &tonic::Request<T>) -> Option<String> {
+    request
+        .extensions()
+        .get::<Caller>()
+        .and_then(|caller| caller.user.clone())
+}
+
+/// Number of changes kept for resuming watches.
+const HISTORY_SIZE: usize = 1024;
+
+type WatchStream<E> =
+    Pin<Box<dyn Stream<Item = Result<E, tonic::Status>> + Send>>;
+
+struct History {
+    /// The token of the latest change.
+    last: u64,
+    changes: VecDeque<(u64, Change)>,
+}
+
+/// Numbers the changes of the store with tokens, and keeps the latest ones
+/// so that a watch can resume after the last change it saw.
+struct ChangeLog {
+    history: std::sync::Mutex<History>,
+    tx: broadcast::Sender<(u64, Change)>,
+    /// Ends the watches, which would hold off the shutdown otherwise.
+    shutdown: CancellationToken,
+}
+
+impl ChangeLog {
+    /// Starts logging the changes of `store`, if it reports them.
+    async fn start(
+        store: &Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
+        shutdown: CancellationToken,
+    ) -> Option<Arc<Self>> {
+        let mut rx = match store.lock().await.subscribe().await {
+            Ok(rx) => rx,
+            Err(e) => {
+                tracing::warn!("watching is disabled: {e}");
+                return None;
+            }
+        };
+
+        let (tx, _) = broadcast::channel(HISTORY_SIZE);
+        let log = Arc::new(Self {
+            history: std::sync::Mutex::new(History {
+                last: 0,
+                changes: VecDeque::new(),
+            }),
+            tx,
+            shutdown,
+        });
+
+        let logger = log.clone();
+        tokio::spawn(async move {
+            loop {
+                let change = rx.recv().await;
+                let mut history = logger.history.lock().unwrap();
+                match change {
+                    Ok(change) => {
+                        history.last += 1;
+                        let token = history.last;
+                        if history.changes.len() == HISTORY_SIZE {
+                            history.changes.pop_front();
+                        }
+                        history.changes.push_back((token, change.clone()));
+                        // sent under the lock, see watch()
+                        let _ = logger.tx.send((token, change));
+                    }
+                    Err(RecvError::Lagged(n)) => {
+                        // watches can not resume across the lost changes
+                        tracing::warn!("lost {n} changes of the store");
+                        history.last += n;
+                        history.changes.clear();
+                    }
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        });
+
+        Some(log)
+    }
+
+    /// Streams the changes after `resume_token`, or from now on without
+    /// one, which `reader` may see. The first item is the token the stream
+    /// starts after. Returns `None` when the changes after the token are no
+    /// longer kept.
+    fn watch<E: Send + 'static>(
+        &self,
+        resume_token: Option<u64>,
+        reader: Reader,
+        start: fn(u64) -> E,
+        event: impl Fn(u64, Change) -> Option<E> + Send + 'static,
+    ) -> Option<WatchStream<E>> {
+        // subscribing under the lock neither misses nor repeats a change
+        let history = self.history.lock().unwrap();
+        let rx = self.tx.subscribe();
+
+        let token = resume_token.unwrap_or(history.last);
+        let oldest =
+            history.changes.front().map_or(history.last, |(t, _)| t - 1);
+        if token > history.last || token < oldest {
+            return None;
+        }
+
+        let replay: Vec<(u64, Change)> = history
+            .changes
+            .iter()
+            .filter(|(t, _)| *t > token)
+            .cloned()
+            .collect();
+        drop(history);
+
+        let live = stream::unfold(Some(rx), |rx| async move {
+            let mut rx = rx?;
+            match rx.recv().await {
+                Ok(change) => Some((Ok(change), Some(rx))),
+                Err(RecvError::Lagged(_)) => Some((
+                    Err(tonic::Status::data_loss(
+                        "the watch fell behind, resume it from the last token",
+                    )),
+                    None,
+                )),
+                Err(RecvError::Closed) => None,
+            }
+        });
+
+        let events = stream::iter(replay.into_iter().map(Ok))
+            .chain(live)
+            .take_until(self.shutdown.clone().cancelled_owned())
+            .filter(move |change| {
+                let reader = reader.clone();
+                let tid = change.as_ref().ok().map(|(_, c)| c.tid());
+                async move {
+                    match tid {
+                        Some(tid) => reader.can_read(tid).await,
+                        None => true,
+                    }
+                }
+            })
+            .filter_map(move |change| {
+                future::ready(match change {
+                    Ok((token, change)) => event(token, change).map(Ok),
+                    Err(e) => Some(Err(e)),
+                })
+            });
+
+        Some(Box::pin(
+            stream::once(future::ready(Ok(start(token)))).chain(events),
+        ))
+    }
+}
+
+fn cannot_resume(token: Option<u64>) -> tonic::Status {
+    tonic::Status::out_of_range(format!(
+        "cannot resume from token {}, watch again without one",
+        token.unwrap_or_default()
+    ))
+}
+
 struct TMServer {
     store: Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
+    changes: Option<Arc<ChangeLog>>,
+}
+
+impl TMServer {
+    /// Locks the store once `user` may access the times `tid` as `access`.
+    async fn lock_times(
+        &self,
+        user: Option<&str>,
+        tid: u64,
+        access: Access,
+    ) -> Result<
+        MutexGuard<'_, Box<dyn Store + Send + Sync + 'static>>,
+        StoreError,
+    > {
+        let mut store = self.store.lock().await;
+        authorize(&mut **store, user, tid, access).await?;
+        Ok(store)
+    }
+
+    fn changes(&self) -> Result<&ChangeLog, StoreError> {
+        self.changes.as_deref().ok_or_else(|| {
+            StoreError::Unsupported(
+                "the store does not report its changes".to_string(),
+            )
+        })
+    }
 }
 
 #[async_trait]
 impl times_man_server::TimesMan for TMServer {
     async fn get_times(
         &self,
-        _request: tonic::Request<()>,
+        request: tonic::Request<()>,
     ) -> Result<tonic::Response<grpc::TimesArray>, tonic::Status> {
-        let mut store = self.store.lock().await;
+        let user = user_of(&request);
 
-        let times = store.get_times().await.map_err(|e| {
-            tonic::Status::new(tonic::Code::Aborted, format!("{e}"))
-        })?;
+        let mut store = self.store.lock().await;
+        let times = match user {
+            Some(user) => store.get_visible_times(user).await?,
+            None => store.get_times().await?,
+        };
 
         let timeses = times
             .iter()
@@ -59,71 +331,529 @@ impl times_man_server::TimesMan for TMServer {
 
     async fn create_times(
         &self,
-        _request: tonic::Request<grpc::TimesTitle>,
+        request: tonic::Request<grpc::TimesTitle>,
     ) -> Result<tonic::Response<grpc::Times>, tonic::Status> {
-        Err(tonic::Status::new(
-            tonic::Code::Unimplemented,
-            "unimplemented",
-        ))
+        let user = user_of(&request);
+        let title = request.into_inner().title;
+
+        let mut store = self.store.lock().await;
+        let times = match user {
+            Some(user) => store.create_times_for(user, title).await?,
+            None => store.create_times(title).await?,
+        };
+
+        Ok(tonic::Response::new(times.into()))
     }
 
     async fn delete_times(
         &self,
-        _request: tonic::Request<grpc::TimesId>,
+        request: tonic::Request<grpc::TimesId>,
     ) -> Result<tonic::Response<()>, tonic::Status> {
-        Err(tonic::Status::new(
-            tonic::Code::Unimplemented,
-            "unimplemented",
-        ))
+        let user = user_of(&request);
+        let tid = request.into_inner().id;
+
+        let mut store = self
+            .lock_times(user.as_deref(), tid, Access::Manage)
+            .await?;
+        store.delete_times(tid).await?;
+
+        Ok(tonic::Response::new(()))
     }
 
     async fn update_times(
         &self,
-        _request: tonic::Request<grpc::Times>,
+        request: tonic::Request<grpc::Times>,
     ) -> Result<tonic::Response<grpc::Times>, tonic::Status> {
-        Err(tonic::Status::new(
-            tonic::Code::Unimplemented,
-            "unimplemented",
-        ))
+        let user = user_of(&request);
+        let times: timesman_type::Times = request.into_inner().try_into()?;
+
+        let mut store = self
+            .lock_times(user.as_deref(), times.id, Access::Manage)
+            .await?;
+        let times = store.update_times(times).await?;
+
+        Ok(tonic::Response::new(times.into()))
+    }
+
+    async fn get_acl(
+        &self,
+        request: tonic::Request<grpc::TimesId>,
+    ) -> Result<tonic::Response<grpc::Acl>, tonic::Status> {
+        let user = user_of(&request);
+        let tid = request.into_inner().id;
+
+        let mut store =
+            self.lock_times(user.as_deref(), tid, Access::Read).await?;
+        let acl = store.get_acl(tid).await?;
+
+        Ok(tonic::Response::new(acl.into()))
+    }
+
+    async fn set_acl(
+        &self,
+        request: tonic::Request<grpc::SetAclParam>,
+    ) -> Result<tonic::Response<grpc::Acl>, tonic::Status> {
+        let user = user_of(&request);
+        let param = request.into_inner();
+        let Some(acl) = param.acl else {
+            return Err(tonic::Status::invalid_argument(
+                "an update needs the ACL",
+            ));
+        };
+
+        let mut store = self
+            .lock_times(user.as_deref(), param.id, Access::Manage)
+            .await?;
+        let acl = store.set_acl(param.id, acl.into()).await?;
+
+        Ok(tonic::Response::new(acl.into()))
     }
 
     async fn get_posts(
         &self,
-        _request: tonic::Request<grpc::TimesId>,
+        request: tonic::Request<grpc::GetPostsParam>,
     ) -> Result<tonic::Response<grpc::PostArray>, tonic::Status> {
-        Err(tonic::Status::new(
-            tonic::Code::Unimplemented,
-            "unimplemented",
-        ))
+        let user = user_of(&request);
+        let param = request.into_inner();
+        let query = PostQuery {
+            cursor: param.cursor,
+            since: param.since.map(timesman_grpc::date_time).transpose()?,
+            until: param.until.map(timesman_grpc::date_time).transpose()?,
+            limit: param.limit.map(|l| l as usize),
+        };
+
+        let mut store = self
+            .lock_times(user.as_deref(), param.id, Access::Read)
+            .await?;
+        let page = store.get_posts_page(param.id, query).await?;
+
+        let posts = page.posts.into_iter().map(|p| p.into()).collect();
+
+        Ok(tonic::Response::new(grpc::PostArray {
+            posts,
+            next: page.next,
+        }))
     }
 
     async fn create_post(
         &self,
-        _request: tonic::Request<grpc::CreatePostPrams>,
+        request: tonic::Request<grpc::CreatePostPrams>,
     ) -> Result<tonic::Response<grpc::Post>, tonic::Status> {
-        Err(tonic::Status::new(
-            tonic::Code::Unimplemented,
-            "unimplemented",
-        ))
+        let user = user_of(&request);
+        let param = request.into_inner();
+
+        let mut store = self
+            .lock_times(user.as_deref(), param.id, Access::Write)
+            .await?;
+        let post = match user {
+            Some(user) => {
+                store.create_post_by(param.id, user, param.text).await?
+            }
+            None => store.create_post(param.id, param.text).await?,
+        };
+
+        Ok(tonic::Response::new(post.into()))
     }
 
     async fn delete_post(
         &self,
-        _request: tonic::Request<grpc::DeletePostParam>,
+        request: tonic::Request<grpc::DeletePostParam>,
     ) -> Result<tonic::Response<()>, tonic::Status> {
-        Err(tonic::Status::new(
-            tonic::Code::Unimplemented,
-            "unimplemented",
-        ))
+        let user = user_of(&request);
+        let param = request.into_inner();
+
+        let mut store = self
+            .lock_times(user.as_deref(), param.tid, Access::Write)
+            .await?;
+        store.delete_post(param.tid, param.pid).await?;
+
+        Ok(tonic::Response::new(()))
     }
 
     async fn update_post(
         &self,
-        _request: tonic::Request<grpc::UpdatePostParam>,
+        request: tonic::Request<grpc::UpdatePostParam>,
     ) -> Result<tonic::Response<grpc::Post>, tonic::Status> {
-        Err(tonic::Status::new(
-            tonic::Code::Unimplemented,
-            "unimplemented",
-        ))
+        let user = user_of(&request);
+        let param = request.into_inner();
+        let Some(post) = param.post else {
+            return Err(tonic::Status::invalid_argument(
+                "an update needs the post",
+            ));
+        };
+
+        let mut store = self
+            .lock_times(user.as_deref(), param.tid, Access::Write)
+            .await?;
+        let post = store.update_post(param.tid, post.try_into()?).await?;
+
+        Ok(tonic::Response::new(post.into()))
+    }
+
+    async fn search_posts(
+        &self,
+        request: tonic::Request<grpc::SearchPostsParam>,
+    ) -> Result<tonic::Response<grpc::TimesPostArray>, tonic::Status> {
+        let user = user_of(&request);
+        let param = request.into_inner();
+        let filter = SearchFilter {
+            tid: param.tid,
+            since: param.since.map(timesman_grpc::date_time).transpose()?,
+            until: param.until.map(timesman_grpc::date_time).transpose()?,
+            limit: param.limit.map(|l| l as usize),
+        };
+
+        let mut store = self.store.lock().await;
+        let posts = acl::readable_search(
+            &mut **store,
+            user.as_deref(),
+            param.query,
+            filter,
+        )
+        .await?;
+
+        let posts = posts.into_iter().map(|p| p.into()).collect();
+
+        Ok(tonic::Response::new(grpc::TimesPostArray { posts }))
+    }
+
+    async fn list_tags(
+        &self,
+        request: tonic::Request<()>,
+    ) -> Result<tonic::Response<grpc::TagArray>, tonic::Status> {
+        let user = user_of(&request);
+
+        let mut store = self.store.lock().await;
+        let tags = acl::readable_tags(&mut **store, user.as_deref()).await?;
+
+        let tags = tags.into_iter().map(|t| t.into()).collect();
+
+        Ok(tonic::Response::new(grpc::TagArray { tags }))
+    }
+
+    async fn get_posts_by_tag(
+        &self,
+        request: tonic::Request<grpc::TagName>,
+    ) -> Result<tonic::Response<grpc::TimesPostArray>, tonic::Status> {
+        let user = user_of(&request);
+        let tag = request.into_inner().name;
+
+        let mut store = self.store.lock().await;
+        let posts = store.get_posts_by_tag(tag).await?;
+        let posts =
+            acl::retain_readable(&mut **store, user.as_deref(), posts, |p| {
+                p.tid
+            })
+            .await?;
+
+        let posts = posts.into_iter().map(|p| p.into()).collect();
+
+        Ok(tonic::Response::new(grpc::TimesPostArray { posts }))
+    }
+
+    async fn list_trash(
+        &self,
+        request: tonic::Request<()>,
+    ) -> Result<tonic::Response<grpc::Trash>, tonic::Status> {
+        let user = user_of(&request);
+
+        let mut store = self.store.lock().await;
+        let trash = acl::readable_trash(&mut **store, user.as_deref()).await?;
+
+        Ok(tonic::Response::new(trash.into()))
+    }
+
+    async fn restore_times(
+        &self,
+        request: tonic::Request<grpc::TimesId>,
+    ) -> Result<tonic::Response<grpc::Times>, tonic::Status> {
+        let user = user_of(&request);
+        let tid = request.into_inner().id;
+
+        let mut store = self
+            .lock_times(user.as_deref(), tid, Access::Manage)
+            .await?;
+        let times = store.restore_times(tid).await?;
+
+        Ok(tonic::Response::new(times.into()))
+    }
+
+    async fn restore_post(
+        &self,
+        request: tonic::Request<grpc::PostKey>,
+    ) -> Result<tonic::Response<grpc::Post>, tonic::Status> {
+        let user = user_of(&request);
+        let key = request.into_inner();
+
+        let mut store = self
+            .lock_times(user.as_deref(), key.tid, Access::Write)
+            .await?;
+        let post = store.restore_post(key.tid, key.pid).await?;
+
+        Ok(tonic::Response::new(post.into()))
+    }
+
+    async fn purge_times(
+        &self,
+        request: tonic::Request<grpc::TimesId>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        let user = user_of(&request);
+        let tid = request.into_inner().id;
+
+        let mut store = self
+            .lock_times(user.as_deref(), tid, Access::Manage)
+            .await?;
+        store.purge_times(tid).await?;
+
+        Ok(tonic::Response::new(()))
+    }
+
+    async fn purge_post(
+        &self,
+        request: tonic::Request<grpc::PostKey>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        let user = user_of(&request);
+        let key = request.into_inner();
+
+        let mut store = self
+            .lock_times(user.as_deref(), key.tid, Access::Write)
+            .await?;
+        store.purge_post(key.tid, key.pid).await?;
+
+        Ok(tonic::Response::new(()))
+    }
+
+    async fn purge_trash(
+        &self,
+        request: tonic::Request<grpc::PurgeTrashParam>,
+    ) -> Result<tonic::Response<grpc::PurgeCount>, tonic::Status> {
+        acl::authorize_purge_trash(user_of(&request).as_deref())?;
+
+        let before = request
+            .into_inner()
+            .before
+            .map(timesman_grpc::date_time)
+            .transpose()?;
+
+        let mut store = self.store.lock().await;
+        let count = store.purge_trash(before).await?;
+
+        Ok(tonic::Response::new(grpc::PurgeCount { count }))
+    }
+
+    async fn list_revisions(
+        &self,
+        request: tonic::Request<grpc::PostKey>,
+    ) -> Result<tonic::Response<grpc::RevisionArray>, tonic::Status> {
+        let user = user_of(&request);
+        let key = request.into_inner();
+
+        let mut store = self
+            .lock_times(user.as_deref(), key.tid, Access::Read)
+            .await?;
+        let revisions = store.list_revisions(key.tid, key.pid).await?;
+
+        Ok(tonic::Response::new(grpc::RevisionArray {
+            revisions: revisions.into_iter().map(|r| r.into()).collect(),
+        }))
+    }
+
+    async fn get_revision(
+        &self,
+        request: tonic::Request<grpc::RevisionKey>,
+    ) -> Result<tonic::Response<grpc::Revision>, tonic::Status> {
+        let user = user_of(&request);
+        let key = request.into_inner();
+
+        let mut store = self
+            .lock_times(user.as_deref(), key.tid, Access::Read)
+            .await?;
+        let revision = store.get_revision(key.tid, key.pid, key.rev).await?;
+
+        Ok(tonic::Response::new(revision.into()))
+    }
+
+    async fn revert_post(
+        &self,
+        request: tonic::Request<grpc::RevisionKey>,
+    ) -> Result<tonic::Response<grpc::Post>, tonic::Status> {
+        let user = user_of(&request);
+        let key = request.into_inner();
+
+        let mut store = self
+            .lock_times(user.as_deref(), key.tid, Access::Write)
+            .await?;
+        let post = store.revert_post(key.tid, key.pid, key.rev).await?;
+
+        Ok(tonic::Response::new(post.into()))
+    }
+
+    async fn list_attachments(
+        &self,
+        request: tonic::Request<grpc::TimesId>,
+    ) -> Result<tonic::Response<grpc::AttachmentArray>, tonic::Status> {
+        let user = user_of(&request);
+        let tid = request.into_inner().id;
+
+        let mut store =
+            self.lock_times(user.as_deref(), tid, Access::Read).await?;
+        let attachments = store.list_attachments(tid).await?;
+
+        Ok(tonic::Response::new(grpc::AttachmentArray {
+            attachments: attachments.into_iter().map(|a| a.into()).collect(),
+        }))
+    }
+
+    async fn upload_attachment(
+        &self,
+        request: tonic::Request<tonic::Streaming<grpc::UploadChunk>>,
+    ) -> Result<tonic::Response<grpc::Attachment>, tonic::Status> {
+        let user = user_of(&request);
+        let mut stream = request.into_inner();
+
+        let header = match stream.message().await?.and_then(|c| c.chunk) {
+            Some(upload_chunk::Chunk::Header(header)) => header,
+            _ => {
+                return Err(tonic::Status::invalid_argument(
+                    "an upload must start with a header",
+                ))
+            }
+        };
+
+        // refuse the upload before receiving it, without holding the store
+        // while it arrives
+        drop(
+            self.lock_times(user.as_deref(), header.tid, Access::Write)
+                .await?,
+        );
+
+        let mut data = vec![];
+        while let Some(chunk) = stream.message().await? {
+            let Some(upload_chunk::Chunk::Data(bytes)) = chunk.chunk else {
+                return Err(tonic::Status::invalid_argument(
+                    "an upload has only one header",
+                ));
+            };
+            if data.len() + bytes.len() > MAX_ATTACHMENT_SIZE {
+                return Err(tonic::Status::invalid_argument(format!(
+                    "attachment is larger than {MAX_ATTACHMENT_SIZE} bytes"
+                )));
+            }
+            data.extend_from_slice(&bytes);
+        }
+
+        // the access may have changed in the meantime
+        let mut store = self
+            .lock_times(user.as_deref(), header.tid, Access::Write)
+            .await?;
+        let attachment = store
+            .attach(header.tid, header.pid, header.name, header.mime, data)
+            .await?;
+
+        Ok(tonic::Response::new(attachment.into()))
+    }
+
+    type DownloadAttachmentStream = Pin<
+        Box<
+            dyn Stream<Item = Result<grpc::DownloadChunk, tonic::Status>>
+                + Send,
+        >,
+    >;
+
+    async fn download_attachment(
+        &self,
+        request: tonic::Request<grpc::AttachmentKey>,
+    ) -> Result<tonic::Response<Self::DownloadAttachmentStream>, tonic::Status>
+    {
+        let user = user_of(&request);
+        let key = request.into_inner();
+
+        let (attachment, data) = {
+            let mut store = self
+                .lock_times(user.as_deref(), key.tid, Access::Read)
+                .await?;
+            let attachment = store.get_attachment(key.tid, key.id).await?;
+            let data = store.read_attachment(key.tid, key.id).await?;
+            (attachment, data)
+        };
+
+        let header = download_chunk::Chunk::Attachment(attachment.into());
+        let chunks: Vec<grpc::DownloadChunk> = std::iter::once(header)
+            .chain(
+                data.chunks(timesman_grpc::CHUNK_SIZE)
+                    .map(|c| download_chunk::Chunk::Data(c.to_vec())),
+            )
+            .map(|chunk| grpc::DownloadChunk { chunk: Some(chunk) })
+            .collect();
+
+        let stream = tokio_stream::iter(chunks.into_iter().map(Ok));
+        Ok(tonic::Response::new(Box::pin(stream)))
+    }
+
+    async fn delete_attachment(
+        &self,
+        request: tonic::Request<grpc::AttachmentKey>,
+    ) -> Result<tonic::Response<()>, tonic::Status> {
+        let user = user_of(&request);
+        let key = request.into_inner();
+
+        let mut store = self
+            .lock_times(user.as_deref(), key.tid, Access::Write)
+            .await?;
+        store.detach(key.tid, key.id).await?;
+
+        Ok(tonic::Response::new(()))
+    }
+
+    type WatchTimesStream = WatchStream<grpc::TimesEvent>;
+
+    async fn watch_times(
+        &self,
+        request: tonic::Request<grpc::WatchParam>,
+    ) -> Result<tonic::Response<Self::WatchTimesStream>, tonic::Status> {
+        let reader = Reader::new(self.store.clone(), user_of(&request));
+        let param = request.into_inner();
+
+        let stream = self
+            .changes()?
+            .watch(
+                param.resume_token,
+                reader,
+                |token| grpc::TimesEvent { token, event: None },
+                grpc::TimesEvent::from_change,
+            )
+            .ok_or_else(|| cannot_resume(param.resume_token))?;
+
+        Ok(tonic::Response::new(stream))
+    }
+
+    type WatchPostsStream = WatchStream<grpc::PostEvent>;
+
+    async fn watch_posts(
+        &self,
+        request: tonic::Request<grpc::WatchPostsParam>,
+    ) -> Result<tonic::Response<Self::WatchPostsStream>, tonic::Status> {
+        let reader = Reader::new(self.store.clone(), user_of(&request));
+        let param = request.into_inner();
+        let tid = param.tid;
+
+        let stream = self
+            .changes()?
+            .watch(
+                param.resume_token,
+                reader,
+                |token| grpc::PostEvent {
+                    token,
+                    tid: 0,
+                    event: None,
+                },
+                move |token, change| {
+                    grpc::PostEvent::from_change(token, change)
+                        .filter(|e| tid.is_none_or(|tid| e.tid == tid))
+                },
+            )
+            .ok_or_else(|| cannot_resume(param.resume_token))?;
+
+        Ok(tonic::Response::new(stream))
     }
 }
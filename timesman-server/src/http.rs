@@ -1,222 +1,787 @@
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, MutexGuard};
 
-use timesman_bstore::Store;
-use timesman_type::{Post, Times};
+use timesman_bstore::{
+    PostQuery, SearchFilter, Store, StoreError, MAX_ATTACHMENT_SIZE,
+};
+use timesman_type::{
+    Acl, Attachment, Change, Post, Revision, Tag, Times, TimesPost, Trash,
+};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
 
+use super::acl::{self, authorize, Access, Reader};
+use super::auth::{Caller, Tokens};
 use super::TimesManServer;
 
-use actix_web::{web, App, HttpResponse, Responder};
+use actix_multipart::Multipart;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::http::header::{
+    self, ContentDisposition, DispositionParam, DispositionType,
+};
+use actix_web::middleware::{from_fn, Next};
+use actix_web::{
+    http::StatusCode, web, App, HttpMessage, HttpRequest, HttpResponse,
+};
 use async_trait::async_trait;
+use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{IntoParams, Modify, OpenApi, ToSchema};
+
+/// Every route of the REST API is under this prefix.
+pub const API_PREFIX: &str = "/api/v1";
+
+/// The OpenAPI description of the REST API, served at `/openapi.json`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "timesman", description = "The REST API of timesd"),
+    servers((url = "/api/v1")),
+    modifiers(&BearerAuth),
+    security(("token" = [])),
+    paths(
+        get_times,
+        create_times,
+        put_times,
+        patch_times,
+        delete_times,
+        get_acl,
+        put_acl,
+        get_posts,
+        create_post,
+        get_latest_post,
+        get_post,
+        put_post,
+        patch_post,
+        delete_post,
+        list_revisions,
+        get_revision,
+        revert_post,
+        upload_attachment,
+        list_attachments,
+        download_attachment,
+        delete_attachment,
+        events,
+        search_posts,
+        list_tags,
+        get_posts_by_tag,
+        list_trash,
+        purge_trash,
+        purge_times,
+        restore_times,
+        purge_post,
+        restore_post,
+    )
+)]
+pub struct ApiDoc;
+
+/// Documents the API tokens the server may require.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components =
+            openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "token",
+            SecurityScheme::Http(
+                HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build(),
+            ),
+        );
+    }
+}
 
 #[derive(Clone)]
 struct Context {
     store: Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
+    tokens: Option<Arc<Tokens>>,
+    /// Ends the event streams, which would hold off the shutdown otherwise.
+    shutdown: CancellationToken,
+    /// The requests in flight, waited for before the workers stop.
+    requests: TaskTracker,
 }
 
-pub struct HttpServer {}
+#[derive(Default)]
+pub struct HttpServer {
+    /// The API tokens to accept. Every request is accepted without them.
+    pub tokens: Option<Arc<Tokens>>,
+}
 
 #[async_trait]
 impl TimesManServer for HttpServer {
-    async fn run(
+    async fn run_until(
         &self,
         listen: &str,
         store: Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
-    ) {
-        actix_web::HttpServer::new(move || {
-            App::new()
-                .app_data(web::Data::new(Context {
-                    store: store.clone(),
-                }))
-                .route("/times", web::get().to(get_times))
-                .route("/times", web::post().to(create_times))
-                .route("/times/{tid}", web::delete().to(delete_times))
-                .route("/times/{tid}", web::get().to(get_posts))
-                .route("/times/{tid}", web::post().to(post_post))
-        })
-        .bind(listen)
-        .unwrap()
-        .run()
+        shutdown: CancellationToken,
+    ) -> Result<(), String> {
+        let requests = TaskTracker::new();
+        let ctx = web::Data::new(Context {
+            store,
+            tokens: self.tokens.clone(),
+            shutdown: shutdown.clone(),
+            requests: requests.clone(),
+        });
+        let server = actix_web::HttpServer::new(move || app(ctx.clone()))
+            // the signals are handled by the caller for every front-end
+            .disable_signals()
+            .bind(listen)
+            .map_err(|e| format!("failed to bind {listen}: {e}"))?
+            .run();
+
+        // the workers of actix drop the requests they still have once the
+        // server stops, so they are waited for while it is only paused
+        let handle = server.handle();
+        actix_web::rt::spawn(async move {
+            shutdown.cancelled().await;
+            handle.pause().await;
+            requests.close();
+            requests.wait().await;
+            handle.stop(true).await;
+        });
+        server.await.map_err(|e| format!("{listen}: {e}"))
+    }
+}
+
+/// The application of every worker, all sharing `ctx`.
+fn app(
+    ctx: web::Data<Context>,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    App::new()
+        .app_data(ctx)
+        .app_data(
+            web::JsonConfig::default()
+                .error_handler(|e, _| bad_request(e.to_string(), e.into())),
+        )
+        .app_data(
+            web::QueryConfig::default()
+                .error_handler(|e, _| bad_request(e.to_string(), e.into())),
+        )
+        .app_data(
+            web::PathConfig::default()
+                .error_handler(|e, _| bad_request(e.to_string(), e.into())),
+        )
+        .route("/openapi.json", web::get().to(openapi))
+        .service(
+            web::scope(API_PREFIX)
+                .wrap(from_fn(authenticate))
+                .configure(routes),
+        )
+        .default_service(web::to(no_route))
+        .wrap(from_fn(track_request))
+}
+
+/// A resource which answers the methods it has no route for with 405.
+fn resource(path: &str) -> actix_web::Resource {
+    web::resource(path).default_service(web::to(method_not_allowed))
+}
+
+fn routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(resource("/times").get(get_times).post(create_times))
+        .service(
+            resource("/times/{tid}")
+                .put(put_times)
+                .patch(patch_times)
+                .delete(delete_times),
+        )
+        .service(resource("/times/{tid}/acl").get(get_acl).put(put_acl))
+        .service(
+            resource("/times/{tid}/posts")
+                .get(get_posts)
+                .post(create_post),
+        )
+        .service(resource("/times/{tid}/posts/latest").get(get_latest_post))
+        .service(
+            resource("/times/{tid}/posts/{pid}")
+                .get(get_post)
+                .put(put_post)
+                .patch(patch_post)
+                .delete(delete_post),
+        )
+        .service(
+            resource("/times/{tid}/posts/{pid}/revisions").get(list_revisions),
+        )
+        .service(
+            resource("/times/{tid}/posts/{pid}/revisions/{rev}")
+                .get(get_revision),
+        )
+        .service(
+            resource("/times/{tid}/posts/{pid}/revisions/{rev}/revert")
+                .post(revert_post),
+        )
+        .service(
+            resource("/times/{tid}/posts/{pid}/attachments")
+                .post(upload_attachment),
+        )
+        .service(resource("/times/{tid}/attachments").get(list_attachments))
+        .service(
+            resource("/times/{tid}/attachments/{aid}")
+                .get(download_attachment)
+                .delete(delete_attachment),
+        )
+        .service(resource("/events").get(events))
+        .service(resource("/search").get(search_posts))
+        .service(resource("/tags").get(list_tags))
+        .service(resource("/tags/{tag}").get(get_posts_by_tag))
+        .service(resource("/trash").get(list_trash).delete(purge_trash))
+        .service(resource("/trash/times/{tid}").delete(purge_times))
+        .service(resource("/trash/times/{tid}/restore").post(restore_times))
+        .service(resource("/trash/times/{tid}/posts/{pid}").delete(purge_post))
+        .service(
+            resource("/trash/times/{tid}/posts/{pid}/restore")
+                .post(restore_post),
+        );
+}
+
+/// The body of every failed request.
+#[derive(Serialize, ToSchema)]
+struct ErrorResponse {
+    error: ErrorDetail,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ErrorDetail {
+    /// What went wrong, as a stable snake case name.
+    #[schema(value_type = String, example = "not_found")]
+    code: &'static str,
+    message: String,
+}
+
+fn error_body(
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+) -> HttpResponse {
+    HttpResponse::build(status).json(ErrorResponse {
+        error: ErrorDetail { code, message },
+    })
+}
+
+fn error_response(e: StoreError) -> HttpResponse {
+    let (status, code) = match &e {
+        StoreError::NotFound(_) => (StatusCode::NOT_FOUND, "not_found"),
+        StoreError::InvalidArgument(_) => {
+            (StatusCode::BAD_REQUEST, "invalid_argument")
+        }
+        StoreError::Unsupported(_) => {
+            (StatusCode::NOT_IMPLEMENTED, "unsupported")
+        }
+        StoreError::Conflict(_) => (StatusCode::CONFLICT, "conflict"),
+        StoreError::Backend(_) => {
+            (StatusCode::INTERNAL_SERVER_ERROR, "backend")
+        }
+        StoreError::Unauthenticated(_) => {
+            (StatusCode::UNAUTHORIZED, "unauthenticated")
+        }
+        StoreError::PermissionDenied(_) => {
+            (StatusCode::FORBIDDEN, "permission_denied")
+        }
+    };
+
+    error_body(status, code, e.message().to_string())
+}
+
+/// Reports a request actix could not extract as a JSON error.
+fn bad_request(message: String, e: actix_web::Error) -> actix_web::Error {
+    let resp = error_body(StatusCode::BAD_REQUEST, "invalid_argument", message);
+    InternalError::from_response(e, resp).into()
+}
+
+/// Counts the request as in flight until its response is ready, and turns
+/// away those arriving on open connections once the shutdown has begun.
+async fn track_request(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let Some(ctx) = req.app_data::<web::Data<Context>>().cloned() else {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    };
+
+    if ctx.shutdown.is_cancelled() {
+        let resp = error_body(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "unavailable",
+            "the server is shutting down".to_string(),
+        );
+        return Ok(req.into_response(resp).map_into_right_body());
+    }
+
+    ctx.requests
+        .track_future(next.call(req))
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+/// Rejects requests without one of the API tokens, if the server has any.
+async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let tokens = req
+        .app_data::<web::Data<Context>>()
+        .and_then(|ctx| ctx.tokens.clone());
+
+    if let Some(tokens) = tokens {
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+
+        let Some(caller) = token.and_then(|t| tokens.authenticate(t)) else {
+            let mut resp = error_body(
+                StatusCode::UNAUTHORIZED,
+                "unauthenticated",
+                "a valid API token is required".to_string(),
+            );
+            resp.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static("Bearer"),
+            );
+            return Ok(req.into_response(resp).map_into_right_body());
+        };
+        req.extensions_mut().insert(caller);
+    }
+
+    next.call(req)
         .await
-        .unwrap();
+        .map(ServiceResponse::map_into_left_body)
+}
+
+/// The user the request acts for, if its token has one.
+fn user_of(req: &HttpRequest) -> Option<String> {
+    req.extensions()
+        .get::<Caller>()
+        .and_then(|caller| caller.user.clone())
+}
+
+/// Locks the store once the caller may access the times `tid` as `access`.
+async fn lock_times<'a>(
+    ctx: &'a Context,
+    req: &HttpRequest,
+    tid: u64,
+    access: Access,
+) -> Result<MutexGuard<'a, Box<dyn Store + Send + Sync + 'static>>, HttpResponse>
+{
+    let mut store = ctx.store.lock().await;
+    match authorize(&mut **store, user_of(req).as_deref(), tid, access).await {
+        Ok(()) => Ok(store),
+        Err(e) => {
+            tracing::info!("refused access to times {tid}: {e}");
+            Err(error_response(e))
+        }
     }
 }
 
-#[derive(Serialize)]
-struct ResponseBase {
-    status: u64,
-    text: String,
+async fn openapi() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+async fn no_route(req: HttpRequest) -> HttpResponse {
+    error_body(
+        StatusCode::NOT_FOUND,
+        "not_found",
+        format!("no route for {} {}", req.method(), req.path()),
+    )
 }
 
-#[derive(Serialize)]
-struct ResponseTimes {
-    base: ResponseBase,
-    times: Vec<Times>,
+async fn method_not_allowed(req: HttpRequest) -> HttpResponse {
+    error_body(
+        StatusCode::METHOD_NOT_ALLOWED,
+        "method_not_allowed",
+        format!("{} is not allowed on {}", req.method(), req.path()),
+    )
 }
 
-async fn get_times(ctx: web::Data<Context>) -> impl Responder {
-    let mut store = ctx.store.lock().await; //.unwrap();
-    let times = match store.get_times().await {
+#[utoipa::path(
+    get,
+    path = "/times",
+    responses(
+        (status = 200, description = "The times the caller may see", body = Vec<Times>),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn get_times(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+) -> HttpResponse {
+    let mut store = ctx.store.lock().await;
+    let times = match user_of(&http_req) {
+        Some(user) => store.get_visible_times(user).await,
+        None => store.get_times().await,
+    };
+    let times = match times {
         Ok(times) => times,
         Err(e) => {
             tracing::info!("failed to get times from store {e}");
-            let resp = ResponseBase { status: 1, text: e };
-
-            return HttpResponse::Ok()
-                .body(serde_json::to_string(&resp).unwrap());
+            return error_response(e);
         }
     };
 
-    tracing::info!("get times. num: {}", times.iter().len());
+    tracing::info!("get times. num: {}", times.len());
 
-    let resp = ResponseTimes {
-        base: ResponseBase {
-            status: 0,
-            text: "Ok".to_string(),
-        },
-        times,
-    };
-
-    HttpResponse::Ok().body(serde_json::to_string(&resp).unwrap())
+    HttpResponse::Ok().json(times)
 }
 
-#[derive(Deserialize)]
-struct CreateTimesRequest {
+#[derive(Deserialize, ToSchema)]
+struct TimesBody {
     title: String,
 }
 
+#[derive(Deserialize, ToSchema)]
+struct TimesPatch {
+    title: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/times",
+    request_body = TimesBody,
+    responses(
+        (status = 201, description = "The created times", body = Times),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
 async fn create_times(
     ctx: web::Data<Context>,
-    req: web::Json<CreateTimesRequest>,
-) -> impl Responder {
-    let mut store = ctx.store.lock().await; //.unwrap();
-    let times = match store.create_times(req.title.clone()).await {
+    http_req: HttpRequest,
+    req: web::Json<TimesBody>,
+) -> HttpResponse {
+    let title = req.into_inner().title;
+
+    let mut store = ctx.store.lock().await;
+    let times = match user_of(&http_req) {
+        Some(user) => store.create_times_for(user, title).await,
+        None => store.create_times(title).await,
+    };
+    let times = match times {
         Ok(times) => times,
         Err(e) => {
             tracing::info!("failed to create title: {e}");
-            let resp = ResponseBase { status: 1, text: e };
-
-            return HttpResponse::Ok()
-                .body(serde_json::to_string(&resp).unwrap());
+            return error_response(e);
         }
     };
 
     tracing::info!(
         "create times with title: {}, id: {}",
-        &req.title,
-        &times.id
+        times.title,
+        times.id
     );
-    #[derive(Serialize)]
-    struct Response {
-        base: ResponseBase,
-        times: Times,
-    }
 
-    let resp = Response {
-        base: ResponseBase {
-            status: 0,
-            text: "Ok".to_string(),
+    HttpResponse::Created().json(times)
+}
+
+/// Changes the given fields of the times `tid`.
+async fn update_times(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    tid: u64,
+    patch: TimesPatch,
+) -> HttpResponse {
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Manage).await
+    {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    let result = match store.get_times().await {
+        Ok(times) => match times.into_iter().find(|t| t.id == tid) {
+            Some(mut times) => {
+                if let Some(title) = patch.title {
+                    times.title = title;
+                }
+                store.update_times(times).await
+            }
+            None => Err(StoreError::NotFound(format!("times {tid}"))),
         },
-        times,
+        Err(e) => Err(e),
     };
 
-    HttpResponse::Ok().body(serde_json::to_string(&resp).unwrap())
+    let times = match result {
+        Ok(times) => times,
+        Err(e) => {
+            tracing::info!("failed to update times {}: {}", tid, &e);
+            return error_response(e);
+        }
+    };
+
+    tracing::info!("update times {}", tid);
+
+    HttpResponse::Ok().json(times)
+}
+
+#[utoipa::path(
+    put,
+    path = "/times/{tid}",
+    params(("tid" = u64, Path, description = "The id of the times")),
+    request_body = TimesBody,
+    responses(
+        (status = 200, description = "The updated times", body = Times),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn put_times(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<u64>,
+    req: web::Json<TimesBody>,
+) -> HttpResponse {
+    let patch = TimesPatch {
+        title: Some(req.into_inner().title),
+    };
+    update_times(ctx, http_req, path.into_inner(), patch).await
+}
+
+#[utoipa::path(
+    patch,
+    path = "/times/{tid}",
+    params(("tid" = u64, Path, description = "The id of the times")),
+    request_body = TimesPatch,
+    responses(
+        (status = 200, description = "The updated times", body = Times),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn patch_times(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<u64>,
+    req: web::Json<TimesPatch>,
+) -> HttpResponse {
+    update_times(ctx, http_req, path.into_inner(), req.into_inner()).await
 }
 
+#[utoipa::path(
+    delete,
+    path = "/times/{tid}",
+    params(("tid" = u64, Path, description = "The id of the times")),
+    responses(
+        (status = 204, description = "The times is in the trash"),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
 async fn delete_times(
     ctx: web::Data<Context>,
+    http_req: HttpRequest,
     path: web::Path<u64>,
-) -> impl Responder {
+) -> HttpResponse {
     let tid = path.into_inner();
-    let mut store = ctx.store.lock().await;
 
-    match store.delete_times(tid).await {
-        Ok(()) => {}
-        Err(e) => {
-            tracing::info!("failed to delete times: {e}");
-            let resp = ResponseBase { status: 1, text: e };
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Manage).await
+    {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    if let Err(e) = store.delete_times(tid).await {
+        tracing::info!("failed to delete times: {e}");
+        return error_response(e);
+    }
+
+    tracing::info!("move the times {} to the trash", tid);
+
+    HttpResponse::NoContent().finish()
+}
+
+#[utoipa::path(
+    get,
+    path = "/times/{tid}/acl",
+    params(("tid" = u64, Path, description = "The id of the times")),
+    responses(
+        (status = 200, description = "Who may see and change the times", body = Acl),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn get_acl(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<u64>,
+) -> HttpResponse {
+    let tid = path.into_inner();
 
-            return HttpResponse::Ok()
-                .body(serde_json::to_string(&resp).unwrap());
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Read).await {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    let acl = match store.get_acl(tid).await {
+        Ok(acl) => acl,
+        Err(e) => {
+            tracing::info!("failed to get the ACL of times {}: {}", tid, &e);
+            return error_response(e);
         }
-    }
-    /* TODO: asynchronous delete */
+    };
 
-    tracing::info!("mark as delete the times {}", tid);
-    let resp = ResponseBase {
-        status: 0,
-        text: "Ok".to_string(),
+    HttpResponse::Ok().json(acl)
+}
+
+/// The ACL of a times but its owner, which cannot be changed.
+#[derive(Deserialize, ToSchema)]
+struct AclBody {
+    #[serde(default)]
+    readers: Vec<String>,
+    #[serde(default)]
+    writers: Vec<String>,
+    #[serde(default)]
+    public: bool,
+}
+
+#[utoipa::path(
+    put,
+    path = "/times/{tid}/acl",
+    params(("tid" = u64, Path, description = "The id of the times")),
+    request_body = AclBody,
+    responses(
+        (status = 200, description = "The updated ACL", body = Acl),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn put_acl(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<u64>,
+    req: web::Json<AclBody>,
+) -> HttpResponse {
+    let tid = path.into_inner();
+    let req = req.into_inner();
+    let acl = Acl {
+        owner: None,
+        readers: req.readers,
+        writers: req.writers,
+        public: req.public,
     };
 
-    HttpResponse::Ok().body(serde_json::to_string(&resp).unwrap())
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Manage).await
+    {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    let acl = match store.set_acl(tid, acl).await {
+        Ok(acl) => acl,
+        Err(e) => {
+            tracing::info!("failed to set the ACL of times {}: {}", tid, &e);
+            return error_response(e);
+        }
+    };
+
+    tracing::info!("set the ACL of times {}", tid);
+
+    HttpResponse::Ok().json(acl)
 }
 
-#[derive(Serialize)]
-struct GetPostResponse {
-    base: ResponseBase,
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct GetPostRequest {
+    cursor: Option<u64>,
+    since: Option<chrono::DateTime<chrono::Utc>>,
+    until: Option<chrono::DateTime<chrono::Utc>>,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct PostPageResponse {
     posts: Vec<Post>,
+    next: Option<u64>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/times/{tid}/posts",
+    params(("tid" = u64, Path, description = "The id of the times"), GetPostRequest),
+    responses(
+        (status = 200, description = "A page of posts", body = PostPageResponse),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
 async fn get_posts(
     ctx: web::Data<Context>,
+    http_req: HttpRequest,
     path: web::Path<u64>,
-) -> impl Responder {
+    req: web::Query<GetPostRequest>,
+) -> HttpResponse {
     let tid = path.into_inner();
+    let req = req.into_inner();
+    let query = PostQuery {
+        cursor: req.cursor,
+        since: req.since,
+        until: req.until,
+        limit: req.limit,
+    };
 
-    let mut store = ctx.store.lock().await;
-    let posts = match store.get_posts(tid).await {
-        Ok(posts) => posts,
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Read).await {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    let page = match store.get_posts_page(tid, query).await {
+        Ok(page) => page,
         Err(e) => {
             tracing::info!("failed to get posts for times {}: {}", tid, &e);
-            let resp = ResponseBase { status: 1, text: e };
-
-            return HttpResponse::Ok()
-                .body(serde_json::to_string(&resp).unwrap());
+            return error_response(e);
         }
     };
 
-    tracing::info!("get posts ({}) for times {}", posts.iter().len(), tid);
-
-    let resp = GetPostResponse {
-        base: ResponseBase {
-            status: 0,
-            text: "Ok".to_string(),
-        },
-        posts,
-    };
+    tracing::info!("get posts ({}) for times {}", page.posts.len(), tid);
 
-    HttpResponse::Ok().body(serde_json::to_string(&resp).unwrap())
+    HttpResponse::Ok().json(PostPageResponse {
+        posts: page.posts,
+        next: page.next,
+    })
 }
 
-#[derive(Serialize)]
-struct PostPostResponse {
-    base: ResponseBase,
-    pid: u64,
+#[derive(Deserialize, ToSchema)]
+struct PostBody {
+    post: String,
 }
 
-#[derive(Deserialize)]
-struct PostPostRequest {
-    post: String,
+#[derive(Deserialize, ToSchema)]
+struct PostPatch {
+    post: Option<String>,
 }
 
-async fn post_post(
+#[utoipa::path(
+    post,
+    path = "/times/{tid}/posts",
+    params(("tid" = u64, Path, description = "The id of the times")),
+    request_body = PostBody,
+    responses(
+        (status = 201, description = "The created post", body = Post),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn create_post(
     ctx: web::Data<Context>,
+    http_req: HttpRequest,
     path: web::Path<u64>,
-    req: web::Json<PostPostRequest>,
-) -> impl Responder {
+    req: web::Json<PostBody>,
+) -> HttpResponse {
     let tid = path.into_inner();
-    let post = req.post.clone();
+    let post = req.into_inner().post;
 
-    let mut store = ctx.store.lock().await;
-    let post = match store.create_post(tid, post).await {
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Write).await
+    {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    let post = match user_of(&http_req) {
+        Some(user) => store.create_post_by(tid, user, post).await,
+        None => store.create_post(tid, post).await,
+    };
+    let post = match post {
         Ok(post) => post,
         Err(e) => {
             tracing::info!("failed to create a post for times {}: {}", tid, &e);
-            let resp = ResponseBase { status: 1, text: e };
-
-            return HttpResponse::Ok()
-                .body(serde_json::to_string(&resp).unwrap());
+            return error_response(e);
         }
     };
 
@@ -227,13 +792,905 @@ async fn post_post(
         tid
     );
 
-    let resp = PostPostResponse {
-        base: ResponseBase {
-            status: 0,
-            text: "Ok".to_string(),
-        },
-        pid: post.id,
+    HttpResponse::Created().json(post)
+}
+
+#[utoipa::path(
+    get,
+    path = "/times/{tid}/posts/latest",
+    params(("tid" = u64, Path, description = "The id of the times")),
+    responses(
+        (status = 200, description = "The latest post", body = Post),
+        (status = 204, description = "The times has no post"),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn get_latest_post(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<u64>,
+) -> HttpResponse {
+    let tid = path.into_inner();
+
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Read).await {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    match store.get_latest_post(tid).await {
+        Ok(Some(post)) => HttpResponse::Ok().json(post),
+        Ok(None) => HttpResponse::NoContent().finish(),
+        Err(e) => {
+            tracing::info!("failed to get the latest post of {}: {}", tid, &e);
+            error_response(e)
+        }
+    }
+}
+
+async fn find_post(
+    store: &mut (dyn Store + Send + Sync + 'static),
+    tid: u64,
+    pid: u64,
+) -> Result<Post, StoreError> {
+    store
+        .get_posts(tid)
+        .await?
+        .into_iter()
+        .find(|p| p.id == pid)
+        .ok_or(StoreError::NotFound(format!("post {pid}")))
+}
+
+#[utoipa::path(
+    get,
+    path = "/times/{tid}/posts/{pid}",
+    params(("tid" = u64, Path, description = "The id of the times"), ("pid" = u64, Path, description = "The id of the post")),
+    responses(
+        (status = 200, description = "The post", body = Post),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn get_post(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<(u64, u64)>,
+) -> HttpResponse {
+    let (tid, pid) = path.into_inner();
+
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Read).await {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    match find_post(&mut **store, tid, pid).await {
+        Ok(post) => HttpResponse::Ok().json(post),
+        Err(e) => {
+            tracing::info!("failed to get post {} of {}: {}", pid, tid, &e);
+            error_response(e)
+        }
+    }
+}
+
+/// Changes the given fields of the post `pid`.
+async fn update_post(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    tid: u64,
+    pid: u64,
+    patch: PostPatch,
+) -> HttpResponse {
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Write).await
+    {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    let result = match find_post(&mut **store, tid, pid).await {
+        Ok(mut post) => {
+            if let Some(text) = patch.post {
+                post.post = text;
+            }
+            store.update_post(tid, post).await
+        }
+        Err(e) => Err(e),
+    };
+
+    let post = match result {
+        Ok(post) => post,
+        Err(e) => {
+            tracing::info!("failed to update post {} of {}: {}", pid, tid, &e);
+            return error_response(e);
+        }
+    };
+
+    tracing::info!("update post {} of times {}", pid, tid);
+
+    HttpResponse::Ok().json(post)
+}
+
+#[utoipa::path(
+    put,
+    path = "/times/{tid}/posts/{pid}",
+    params(("tid" = u64, Path, description = "The id of the times"), ("pid" = u64, Path, description = "The id of the post")),
+    request_body = PostBody,
+    responses(
+        (status = 200, description = "The updated post", body = Post),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn put_post(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<(u64, u64)>,
+    req: web::Json<PostBody>,
+) -> HttpResponse {
+    let (tid, pid) = path.into_inner();
+    let patch = PostPatch {
+        post: Some(req.into_inner().post),
+    };
+    update_post(ctx, http_req, tid, pid, patch).await
+}
+
+#[utoipa::path(
+    patch,
+    path = "/times/{tid}/posts/{pid}",
+    params(("tid" = u64, Path, description = "The id of the times"), ("pid" = u64, Path, description = "The id of the post")),
+    request_body = PostPatch,
+    responses(
+        (status = 200, description = "The updated post", body = Post),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn patch_post(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<(u64, u64)>,
+    req: web::Json<PostPatch>,
+) -> HttpResponse {
+    let (tid, pid) = path.into_inner();
+    update_post(ctx, http_req, tid, pid, req.into_inner()).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/times/{tid}/posts/{pid}",
+    params(("tid" = u64, Path, description = "The id of the times"), ("pid" = u64, Path, description = "The id of the post")),
+    responses(
+        (status = 204, description = "The post is in the trash"),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn delete_post(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<(u64, u64)>,
+) -> HttpResponse {
+    let (tid, pid) = path.into_inner();
+
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Write).await
+    {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    if let Err(e) = store.delete_post(tid, pid).await {
+        tracing::info!("failed to delete post {} of {}: {}", pid, tid, &e);
+        return error_response(e);
+    }
+
+    tracing::info!("move the post {} of times {} to the trash", pid, tid);
+
+    HttpResponse::NoContent().finish()
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct SearchRequest {
+    q: String,
+    tid: Option<u64>,
+    since: Option<chrono::DateTime<chrono::Utc>>,
+    until: Option<chrono::DateTime<chrono::Utc>>,
+    limit: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/search",
+    params(SearchRequest),
+    responses(
+        (status = 200, description = "The matching posts", body = Vec<TimesPost>),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn search_posts(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    req: web::Query<SearchRequest>,
+) -> HttpResponse {
+    let req = req.into_inner();
+    let filter = SearchFilter {
+        tid: req.tid,
+        since: req.since,
+        until: req.until,
+        limit: req.limit,
+    };
+
+    let user = user_of(&http_req);
+    let mut store = ctx.store.lock().await;
+    let posts = acl::readable_search(
+        &mut **store,
+        user.as_deref(),
+        req.q.clone(),
+        filter,
+    )
+    .await;
+    let posts = match posts {
+        Ok(posts) => posts,
+        Err(e) => {
+            tracing::info!("failed to search posts for {}: {}", req.q, &e);
+            return error_response(e);
+        }
     };
 
-    HttpResponse::Ok().body(serde_json::to_string(&resp).unwrap())
+    tracing::info!("search posts ({}) for {}", posts.len(), req.q);
+
+    HttpResponse::Ok().json(posts)
+}
+
+#[utoipa::path(
+    get,
+    path = "/times/{tid}/posts/{pid}/revisions",
+    params(("tid" = u64, Path, description = "The id of the times"), ("pid" = u64, Path, description = "The id of the post")),
+    responses(
+        (status = 200, description = "The earlier versions of the post", body = Vec<Revision>),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn list_revisions(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<(u64, u64)>,
+) -> HttpResponse {
+    let (tid, pid) = path.into_inner();
+
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Read).await {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    let revisions = match store.list_revisions(tid, pid).await {
+        Ok(revisions) => revisions,
+        Err(e) => {
+            tracing::info!(
+                "failed to list revisions of {} in {}: {}",
+                pid,
+                tid,
+                &e
+            );
+            return error_response(e);
+        }
+    };
+
+    tracing::info!("list revisions of post {}. num: {}", pid, revisions.len());
+
+    HttpResponse::Ok().json(revisions)
+}
+
+#[utoipa::path(
+    get,
+    path = "/times/{tid}/posts/{pid}/revisions/{rev}",
+    params(("tid" = u64, Path, description = "The id of the times"), ("pid" = u64, Path, description = "The id of the post"), ("rev" = u64, Path, description = "The number of the revision")),
+    responses(
+        (status = 200, description = "The revision", body = Revision),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn get_revision(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<(u64, u64, u64)>,
+) -> HttpResponse {
+    let (tid, pid, rev) = path.into_inner();
+
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Read).await {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    match store.get_revision(tid, pid, rev).await {
+        Ok(revision) => HttpResponse::Ok().json(revision),
+        Err(e) => {
+            tracing::info!("failed to get revision {} of {}: {}", rev, pid, &e);
+            error_response(e)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/times/{tid}/posts/{pid}/revisions/{rev}/revert",
+    params(("tid" = u64, Path, description = "The id of the times"), ("pid" = u64, Path, description = "The id of the post"), ("rev" = u64, Path, description = "The number of the revision")),
+    responses(
+        (status = 200, description = "The reverted post", body = Post),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn revert_post(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<(u64, u64, u64)>,
+) -> HttpResponse {
+    let (tid, pid, rev) = path.into_inner();
+
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Write).await
+    {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    let post = match store.revert_post(tid, pid, rev).await {
+        Ok(post) => post,
+        Err(e) => {
+            tracing::info!("failed to revert post {} to {}: {}", pid, rev, &e);
+            return error_response(e);
+        }
+    };
+
+    tracing::info!("revert post {} of times {} to revision {}", pid, tid, rev);
+
+    HttpResponse::Ok().json(post)
+}
+
+#[utoipa::path(
+    get,
+    path = "/times/{tid}/attachments",
+    params(("tid" = u64, Path, description = "The id of the times")),
+    responses(
+        (status = 200, description = "The files attached to the posts", body = Vec<Attachment>),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn list_attachments(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<u64>,
+) -> HttpResponse {
+    let tid = path.into_inner();
+
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Read).await {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    let attachments = match store.list_attachments(tid).await {
+        Ok(attachments) => attachments,
+        Err(e) => {
+            tracing::info!("failed to list attachments of {}: {}", tid, &e);
+            return error_response(e);
+        }
+    };
+
+    tracing::info!("list attachments of {}. num: {}", tid, attachments.len());
+
+    HttpResponse::Ok().json(attachments)
+}
+
+/// The multipart body of an upload. It only describes the request in the
+/// OpenAPI document; [`read_upload`] parses the actual one.
+#[allow(dead_code)]
+#[derive(ToSchema)]
+struct Upload {
+    #[schema(value_type = String, format = Binary)]
+    file: Vec<u8>,
+}
+
+/// Reads the `file` field of a multipart upload as (name, MIME type, data).
+async fn read_upload(
+    mut payload: Multipart,
+) -> Result<(String, String, Vec<u8>), StoreError> {
+    let invalid = |e: actix_multipart::MultipartError| {
+        StoreError::InvalidArgument(format!("malformed upload: {e}"))
+    };
+
+    while let Some(field) = payload.next().await {
+        let mut field = field.map_err(invalid)?;
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let name = field
+            .content_disposition()
+            .and_then(|cd| cd.get_filename())
+            .unwrap_or_default()
+            .to_string();
+        let mime = field
+            .content_type()
+            .map(|m| m.to_string())
+            .unwrap_or("application/octet-stream".to_string());
+
+        let mut data = vec![];
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(invalid)?;
+            if data.len() + chunk.len() > MAX_ATTACHMENT_SIZE {
+                return Err(StoreError::InvalidArgument(format!(
+                    "attachment is larger than {MAX_ATTACHMENT_SIZE} bytes"
+                )));
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        return Ok((name, mime, data));
+    }
+
+    Err(StoreError::InvalidArgument(
+        "the upload has no file field".to_string(),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/times/{tid}/posts/{pid}/attachments",
+    params(("tid" = u64, Path, description = "The id of the times"), ("pid" = u64, Path, description = "The id of the post")),
+    request_body(content = Upload, content_type = "multipart/form-data"),
+    responses(
+        (status = 201, description = "The attached file", body = Attachment),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn upload_attachment(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<(u64, u64)>,
+    payload: Multipart,
+) -> HttpResponse {
+    let (tid, pid) = path.into_inner();
+
+    // refuse the upload before receiving it, without holding the store
+    // while it arrives
+    if let Err(resp) = lock_times(&ctx, &http_req, tid, Access::Write).await {
+        return resp;
+    }
+
+    let (name, mime, data) = match read_upload(payload).await {
+        Ok(upload) => upload,
+        Err(e) => {
+            tracing::info!("failed to receive an attachment: {e}");
+            return error_response(e);
+        }
+    };
+
+    // the access may have changed in the meantime
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Write).await
+    {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    let attachment = match store.attach(tid, pid, name, mime, data).await {
+        Ok(attachment) => attachment,
+        Err(e) => {
+            tracing::info!(
+                "failed to attach to post {} of {}: {}",
+                pid,
+                tid,
+                &e
+            );
+            return error_response(e);
+        }
+    };
+
+    tracing::info!(
+        "attach {} ({} bytes) to post {}",
+        attachment.name,
+        attachment.size,
+        pid
+    );
+
+    HttpResponse::Created().json(attachment)
+}
+
+#[utoipa::path(
+    get,
+    path = "/times/{tid}/attachments/{aid}",
+    params(("tid" = u64, Path, description = "The id of the times"), ("aid" = u64, Path, description = "The id of the attachment")),
+    responses(
+        (status = 200, description = "The content of the file, with its MIME type"),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn download_attachment(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<(u64, u64)>,
+) -> HttpResponse {
+    let (tid, aid) = path.into_inner();
+
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Read).await {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    let result = match store.get_attachment(tid, aid).await {
+        Ok(attachment) => store
+            .read_attachment(tid, aid)
+            .await
+            .map(|data| (attachment, data)),
+        Err(e) => Err(e),
+    };
+    let (attachment, data) = match result {
+        Ok(found) => found,
+        Err(e) => {
+            tracing::info!("failed to read attachment {}: {}", aid, &e);
+            return error_response(e);
+        }
+    };
+
+    tracing::info!("download attachment {} of {}", aid, tid);
+
+    HttpResponse::Ok()
+        .content_type(attachment.mime)
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Inline,
+            parameters: vec![DispositionParam::Filename(attachment.name)],
+        })
+        .body(data)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/times/{tid}/attachments/{aid}",
+    params(("tid" = u64, Path, description = "The id of the times"), ("aid" = u64, Path, description = "The id of the attachment")),
+    responses(
+        (status = 204, description = "The file is deleted"),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn delete_attachment(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<(u64, u64)>,
+) -> HttpResponse {
+    let (tid, aid) = path.into_inner();
+
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Write).await
+    {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    if let Err(e) = store.detach(tid, aid).await {
+        tracing::info!("failed to delete attachment {}: {}", aid, &e);
+        return error_response(e);
+    }
+
+    tracing::info!("delete attachment {} of {}", aid, tid);
+
+    HttpResponse::NoContent().finish()
+}
+
+#[utoipa::path(
+    get,
+    path = "/tags",
+    responses(
+        (status = 200, description = "Every hashtag in use", body = Vec<Tag>),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn list_tags(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+) -> HttpResponse {
+    let user = user_of(&http_req);
+
+    let mut store = ctx.store.lock().await;
+    let tags = match acl::readable_tags(&mut **store, user.as_deref()).await {
+        Ok(tags) => tags,
+        Err(e) => {
+            tracing::info!("failed to list tags: {e}");
+            return error_response(e);
+        }
+    };
+
+    tracing::info!("list tags. num: {}", tags.len());
+
+    HttpResponse::Ok().json(tags)
+}
+
+#[utoipa::path(
+    get,
+    path = "/tags/{tag}",
+    params(("tag" = String, Path, description = "The hashtag without the #")),
+    responses(
+        (status = 200, description = "The posts using the hashtag", body = Vec<TimesPost>),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn get_posts_by_tag(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let tag = path.into_inner();
+    let user = user_of(&http_req);
+
+    let mut store = ctx.store.lock().await;
+    let posts = match store.get_posts_by_tag(tag.clone()).await {
+        Ok(posts) => {
+            acl::retain_readable(&mut **store, user.as_deref(), posts, |p| {
+                p.tid
+            })
+            .await
+        }
+        Err(e) => Err(e),
+    };
+    let posts = match posts {
+        Ok(posts) => posts,
+        Err(e) => {
+            tracing::info!("failed to get posts tagged {}: {}", tag, &e);
+            return error_response(e);
+        }
+    };
+
+    tracing::info!("get posts ({}) tagged {}", posts.len(), tag);
+
+    HttpResponse::Ok().json(posts)
+}
+
+#[utoipa::path(
+    get,
+    path = "/trash",
+    responses(
+        (status = 200, description = "The trash", body = Trash),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn list_trash(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+) -> HttpResponse {
+    let user = user_of(&http_req);
+
+    let mut store = ctx.store.lock().await;
+    let trash = match acl::readable_trash(&mut **store, user.as_deref()).await {
+        Ok(trash) => trash,
+        Err(e) => {
+            tracing::info!("failed to list the trash: {e}");
+            return error_response(e);
+        }
+    };
+
+    tracing::info!(
+        "list trash. times: {}, posts: {}",
+        trash.times.len(),
+        trash.posts.len()
+    );
+
+    HttpResponse::Ok().json(trash)
+}
+
+#[utoipa::path(
+    post,
+    path = "/trash/times/{tid}/restore",
+    params(("tid" = u64, Path, description = "The id of the times")),
+    responses(
+        (status = 200, description = "The restored times", body = Times),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn restore_times(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<u64>,
+) -> HttpResponse {
+    let tid = path.into_inner();
+
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Manage).await
+    {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    let times: Times = match store.restore_times(tid).await {
+        Ok(times) => times,
+        Err(e) => {
+            tracing::info!("failed to restore times {}: {}", tid, &e);
+            return error_response(e);
+        }
+    };
+
+    tracing::info!("restore times {}", tid);
+
+    HttpResponse::Ok().json(times)
+}
+
+#[utoipa::path(
+    post,
+    path = "/trash/times/{tid}/posts/{pid}/restore",
+    params(("tid" = u64, Path, description = "The id of the times"), ("pid" = u64, Path, description = "The id of the post")),
+    responses(
+        (status = 200, description = "The restored post", body = Post),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn restore_post(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<(u64, u64)>,
+) -> HttpResponse {
+    let (tid, pid) = path.into_inner();
+
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Write).await
+    {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    let post = match store.restore_post(tid, pid).await {
+        Ok(post) => post,
+        Err(e) => {
+            tracing::info!("failed to restore post {} of {}: {}", pid, tid, &e);
+            return error_response(e);
+        }
+    };
+
+    tracing::info!("restore post {} of times {}", pid, tid);
+
+    HttpResponse::Ok().json(post)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/trash/times/{tid}",
+    params(("tid" = u64, Path, description = "The id of the times")),
+    responses(
+        (status = 204, description = "The times is deleted for good"),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn purge_times(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<u64>,
+) -> HttpResponse {
+    let tid = path.into_inner();
+
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Manage).await
+    {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    if let Err(e) = store.purge_times(tid).await {
+        tracing::info!("failed to purge times {}: {}", tid, &e);
+        return error_response(e);
+    }
+
+    tracing::info!("purge times {}", tid);
+
+    HttpResponse::NoContent().finish()
+}
+
+#[utoipa::path(
+    delete,
+    path = "/trash/times/{tid}/posts/{pid}",
+    params(("tid" = u64, Path, description = "The id of the times"), ("pid" = u64, Path, description = "The id of the post")),
+    responses(
+        (status = 204, description = "The post is deleted for good"),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn purge_post(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    path: web::Path<(u64, u64)>,
+) -> HttpResponse {
+    let (tid, pid) = path.into_inner();
+
+    let mut store = match lock_times(&ctx, &http_req, tid, Access::Write).await
+    {
+        Ok(store) => store,
+        Err(resp) => return resp,
+    };
+    if let Err(e) = store.purge_post(tid, pid).await {
+        tracing::info!("failed to purge post {} of {}: {}", pid, tid, &e);
+        return error_response(e);
+    }
+
+    tracing::info!("purge post {} of times {}", pid, tid);
+
+    HttpResponse::NoContent().finish()
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct PurgeTrashRequest {
+    before: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct PurgeTrashResponse {
+    purged: u64,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/trash",
+    params(PurgeTrashRequest),
+    responses(
+        (status = 200, description = "The number of times and posts deleted", body = PurgeTrashResponse),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn purge_trash(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+    req: web::Query<PurgeTrashRequest>,
+) -> HttpResponse {
+    let before = req.into_inner().before;
+
+    if let Err(e) = acl::authorize_purge_trash(user_of(&http_req).as_deref()) {
+        tracing::info!("refused to purge the trash: {e}");
+        return error_response(e);
+    }
+
+    let mut store = ctx.store.lock().await;
+    let purged = match store.purge_trash(before).await {
+        Ok(purged) => purged,
+        Err(e) => {
+            tracing::info!("failed to purge the trash: {e}");
+            return error_response(e);
+        }
+    };
+
+    tracing::info!("purge trash. num: {}", purged);
+
+    HttpResponse::Ok().json(PurgeTrashResponse { purged })
+}
+
+/// Streams the changes to the store as server-sent events, one JSON
+/// encoded [`timesman_type::Change`] per event. The stream ends when the
+/// client falls too far behind, so it has to reload and subscribe again.
+#[utoipa::path(
+    get,
+    path = "/events",
+    responses(
+        (status = 200, description = "A server-sent event per change", content_type = "text/event-stream", body = Change),
+        (status = "default", description = "The request failed", body = ErrorResponse)
+    )
+)]
+async fn events(
+    ctx: web::Data<Context>,
+    http_req: HttpRequest,
+) -> HttpResponse {
+    let rx = match ctx.store.lock().await.subscribe().await {
+        Ok(rx) => rx,
+        Err(e) => {
+            tracing::info!("failed to subscribe: {e}");
+            return error_response(e);
+        }
+    };
+
+    tracing::info!("subscribe changes");
+
+    let reader = Reader::new(ctx.store.clone(), user_of(&http_req));
+    let shutdown = ctx.shutdown.clone().cancelled_owned();
+    let stream = futures_util::stream::unfold(
+        (rx, reader),
+        |(mut rx, reader)| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(change) => {
+                        if !reader.can_read(change.tid()).await {
+                            continue;
+                        }
+                        let event = format!(
+                            "data: {}\n\n",
+                            serde_json::to_string(&change).unwrap()
+                        );
+                        let event = web::Bytes::from(event);
+                        return Some((
+                            Ok::<_, actix_web::Error>(event),
+                            (rx, reader),
+                        ));
+                    }
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::info!("subscriber lagged {n} changes behind");
+                        return None;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        },
+    )
+    .take_until(shutdown);
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream)
 }
@@ -1,17 +1,145 @@
+pub mod acl;
+pub mod auth;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 pub mod http;
 
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
 
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 
 use timesman_bstore::Store;
 
+use auth::Tokens;
+
 #[async_trait]
 pub trait TimesManServer {
+    /// Serves on `listen` until `shutdown` is cancelled, then stops
+    /// accepting connections, ends the change streams and waits for the
+    /// requests in flight. Fails if `listen` cannot be bound.
+    async fn run_until(
+        &self,
+        listen: &str,
+        store: Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
+        shutdown: CancellationToken,
+    ) -> Result<(), String>;
+
+    /// Serves on `listen` until the process exits.
     async fn run(
         &self,
         listen: &str,
         store: Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
-    );
+    ) -> Result<(), String> {
+        self.run_until(listen, store, CancellationToken::new())
+            .await
+    }
+}
+
+/// The protocol a listener speaks.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Http,
+    Grpc,
+}
+
+/// An address to serve one of the front-ends on.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Listener {
+    pub protocol: Protocol,
+    pub address: String,
+    /// Serves over TLS instead of plaintext. Only grpc listeners support it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+}
+
+/// The PEM files a listener serves TLS with.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TlsConfig {
+    /// The certificate chain of the server.
+    pub cert: PathBuf,
+    /// The private key of `cert`.
+    pub key: PathBuf,
+    /// The CAs signing client certificates. Clients must present one when
+    /// it is given.
+    pub client_ca: Option<PathBuf>,
+}
+
+impl Listener {
+    /// The front-end serving the listener. It only accepts requests with one
+    /// of `tokens`, if given.
+    pub fn server(
+        &self,
+        tokens: Option<Arc<Tokens>>,
+    ) -> Result<Box<dyn TimesManServer>, String> {
+        match (self.protocol, &self.tls) {
+            (Protocol::Http, None) => Ok(Box::new(http::HttpServer { tokens })),
+            (Protocol::Http, Some(_)) => {
+                Err("TLS is only supported by grpc listeners".to_string())
+            }
+            #[cfg(feature = "grpc")]
+            (Protocol::Grpc, tls) => Ok(Box::new(grpc::GrpcServer {
+                tokens,
+                tls: tls.as_ref().map(grpc::tls_config).transpose()?,
+            })),
+            #[cfg(not(feature = "grpc"))]
+            (Protocol::Grpc, _) => {
+                Err("Grpc is not supported by this build".to_string())
+            }
+        }
+    }
+}
+
+/// Runs a front-end on every listener, all sharing `store` and `tokens`,
+/// until `shutdown` is cancelled and they have drained. Fails if a listener
+/// fails, stopping the others, or if requests are still in flight
+/// `drain_timeout` after the shutdown.
+pub async fn serve(
+    listeners: &[Listener],
+    store: Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>,
+    tokens: Option<Arc<Tokens>>,
+    shutdown: CancellationToken,
+    drain_timeout: Duration,
+) -> Result<(), String> {
+    let mut servers = vec![];
+    for listener in listeners {
+        let server = listener
+            .server(tokens.clone())
+            .map_err(|e| format!("{}: {e}", listener.address))?;
+        servers.push((server, listener));
+    }
+
+    let runs = servers.iter().map(|(server, listener)| {
+        tracing::info!(
+            "serving {:?} on {}",
+            listener.protocol,
+            listener.address
+        );
+        let (store, shutdown) = (store.clone(), shutdown.clone());
+        async move {
+            server
+                .run_until(&listener.address, store, shutdown.clone())
+                .await
+                .inspect_err(|_| shutdown.cancel())
+        }
+    });
+    let runs = futures_util::future::try_join_all(runs);
+    tokio::pin!(runs);
+
+    tokio::select! {
+        result = &mut runs => return result.map(|_| ()),
+        _ = shutdown.cancelled() => {}
+    }
+    tracing::info!("draining the requests in flight");
+    tokio::time::timeout(drain_timeout, runs)
+        .await
+        .map_err(|_| {
+            format!("requests were still in flight after {drain_timeout:?}")
+        })?
+        .map(|_| ())
 }
@@ -1,64 +1,226 @@
 mod config;
-#[cfg(feature = "grpc")]
-mod grpc;
-mod http;
 
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::process::ExitCode;
+use std::time::Duration;
 
-use clap::Parser;
-use timesman_bstore::sqlite::SqliteStoreBuilder;
-use timesman_bstore::Store;
-use timesman_server::TimesManServer;
+use clap::{Parser, Subcommand};
+use tokio_util::sync::CancellationToken;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
     #[arg(short, long)]
     config: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Manage the API tokens in the file of the [auth] table
+    Token {
+        #[command(subcommand)]
+        command: TokenCommand,
+    },
+    /// Manage the users of the store
+    User {
+        #[command(subcommand)]
+        command: UserCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TokenCommand {
+    /// Create a token and print it. It cannot be shown again
+    Create {
+        name: String,
+        /// The user the token acts for. Without one, the token is an
+        /// administrator's: it sees every times, and the times it creates
+        /// have no owner, so every user may read and post to them but only
+        /// tokens without a user may rename, delete or share them
+        #[arg(long)]
+        user: Option<String>,
+    },
+    /// List the names of the tokens
+    List,
+    /// Stop accepting a token
+    Revoke { name: String },
+}
+
+#[derive(Subcommand, Debug)]
+enum UserCommand {
+    /// Create a user
+    Create { name: String },
+    /// List the users
+    List,
+}
+
+async fn manage_tokens(
+    config: &config::Config,
+    cmd: &TokenCommand,
+) -> Result<(), String> {
+    let tokens = config
+        .tokens()
+        .ok_or("the config has no [auth] table for the tokens")?;
+
+    match cmd {
+        TokenCommand::Create { name, user } => {
+            if let Some(user) = user {
+                let store = config.open_store().await?;
+                let users = store
+                    .lock()
+                    .await
+                    .list_users()
+                    .await
+                    .map_err(|e| e.message().to_string())?;
+                if !users.iter().any(|u| &u.name == user) {
+                    return Err(format!("user {user} is not found"));
+                }
+            }
+            println!("{}", tokens.create(name, user.as_deref())?);
+        }
+        TokenCommand::List => {
+            for t in tokens.list()? {
+                println!("{} (created at {})", t.name, t.created_at);
+            }
+        }
+        TokenCommand::Revoke { name } => {
+            tokens.revoke(name)?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn manage_users(
+    config: &config::Config,
+    cmd: &UserCommand,
+) -> Result<(), String> {
+    let store = config.open_store().await?;
+    let mut store = store.lock().await;
+
+    match cmd {
+        UserCommand::Create { name } => {
+            let user = store
+                .create_user(name.clone())
+                .await
+                .map_err(|e| e.message().to_string())?;
+            println!("{} (id {})", user.name, user.id);
+        }
+        UserCommand::List => {
+            for u in store
+                .list_users()
+                .await
+                .map_err(|e| e.message().to_string())?
+            {
+                println!("{} (created at {})", u.name, u.created_at);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Cancels `shutdown` on the first SIGINT or SIGTERM.
+async fn shutdown_on_signal(shutdown: CancellationToken) {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut term = match signal(SignalKind::terminate()) {
+            Ok(term) => term,
+            Err(e) => {
+                tracing::warn!("SIGTERM is not handled: {e}");
+                let _ = tokio::signal::ctrl_c().await;
+                shutdown.cancel();
+                return;
+            }
+        };
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = term.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+
+    tracing::info!("shutting down");
+    shutdown.cancel();
 }
 
 #[actix_web::main]
-async fn main() -> std::io::Result<()> {
+async fn main() -> ExitCode {
     tracing_subscriber::fmt()
         .with_max_level(tracing::Level::INFO)
         .init();
 
     let args = Args::parse();
 
-    let config = config::Config::load(args.config.into()).unwrap();
-
-    let store = match &*config.store_type {
-        "sqlite" => SqliteStoreBuilder::new(&config.store_param)
-            .build()
-            .await
-            .unwrap(),
-        _ => {
-            tracing::error!("invalid config: store_type");
-            return Ok(());
+    let config = match config::Config::load(args.config.into()) {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::error!("invalid config: {e}");
+            return ExitCode::FAILURE;
         }
     };
 
-    let store: Box<dyn Store + Send + Sync + 'static> = Box::new(store);
-    let store = Arc::new(Mutex::new(store));
-
-    let server = match &*config.front_type {
-        "grpc" => {
-            let grpc_srv: Box<dyn TimesManServer> =
-                Box::new(grpc::GrpcServer {});
-            grpc_srv
-        }
-        "http" => {
-            let http_srv: Box<dyn TimesManServer> =
-                Box::new(http::HttpServer {});
-            http_srv
+    if let Some(command) = &args.command {
+        let result = match command {
+            Command::Token { command } => manage_tokens(&config, command).await,
+            Command::User { command } => manage_users(&config, command).await,
+        };
+        if let Err(e) = result {
+            tracing::error!("{e}");
+            return ExitCode::FAILURE;
         }
-        _ => {
-            panic!("unsupported server type. See the parameter of config.front_type");
+        return ExitCode::SUCCESS;
+    }
+
+    let store = match config.open_store().await {
+        Ok(store) => store,
+        Err(e) => {
+            tracing::error!("invalid config: store: {e}");
+            return ExitCode::FAILURE;
         }
     };
 
-    server.run(&config.listen, store).await;
+    let tokens = config.tokens();
+    if tokens.is_none() {
+        tracing::warn!("no [auth] table: every request is accepted");
+    }
 
-    Ok(())
+    let shutdown = CancellationToken::new();
+    actix_web::rt::spawn(shutdown_on_signal(shutdown.clone()));
+
+    let timeout = Duration::from_secs(config.shutdown_timeout);
+    let mut status = ExitCode::SUCCESS;
+    if let Err(e) = timesman_server::serve(
+        &config.listeners,
+        store.clone(),
+        tokens,
+        shutdown,
+        timeout,
+    )
+    .await
+    {
+        tracing::error!("listeners: {e}");
+        status = ExitCode::FAILURE;
+    }
+
+    // a request still holding the store after draining timed out must not
+    // keep the process alive
+    let close = async { store.lock().await.close().await };
+    match tokio::time::timeout(timeout, close).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            tracing::error!("failed to close the store: {e}");
+            status = ExitCode::FAILURE;
+        }
+        Err(_) => {
+            tracing::error!("the store was still in use after {timeout:?}");
+            status = ExitCode::FAILURE;
+        }
+    }
+
+    status
 }
@@ -0,0 +1,314 @@
+mod common;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use common::{free_addr, ram_store, wait_for, SharedStore};
+use timesman_bstore::remote::RemoteStore;
+use timesman_bstore::{SearchFilter, Store, StoreError, MAX_ATTACHMENT_SIZE};
+use timesman_server::auth::Tokens;
+use timesman_server::TimesManServer;
+use timesman_type::{Acl, Post};
+
+const USERS: [&str; 3] = ["alice", "bob", "carol"];
+
+/// A store with the users alice, bob and carol, and a token for each.
+async fn setup(name: &str) -> (PathBuf, Arc<Tokens>, SharedStore, Vec<String>) {
+    let path = std::env::temp_dir().join(format!(
+        "timesman-tokens-acl-{name}-{}.json",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let tokens = Arc::new(Tokens::open(path.clone()));
+
+    let store = ram_store();
+    let mut user_tokens = vec![];
+    for user in USERS {
+        store
+            .lock()
+            .await
+            .create_user(user.to_string())
+            .await
+            .unwrap();
+        user_tokens.push(tokens.create(user, Some(user)).unwrap());
+    }
+
+    (path, tokens, store, user_tokens)
+}
+
+const MIME: &str = "application/octet-stream";
+
+/// An attachment too large to be accepted, to tell a refused upload from
+/// one that was received first.
+fn big() -> Vec<u8> {
+    vec![0; MAX_ATTACHMENT_SIZE + 1]
+}
+
+/// Fills a times only alice reads with newer matches of a search than
+/// `readable`, which must still make it into bob's limited results.
+async fn check_search_limit(
+    alice: &mut dyn Store,
+    bob: &mut dyn Store,
+    readable: &Post,
+) {
+    let diary = alice.create_times("diary".to_string()).await.unwrap();
+    for _ in 0..3 {
+        alice
+            .create_post(diary.id, readable.post.clone())
+            .await
+            .unwrap();
+    }
+
+    let filter = SearchFilter {
+        limit: Some(1),
+        ..SearchFilter::default()
+    };
+    let hits = bob
+        .search_posts(readable.post.clone(), filter)
+        .await
+        .unwrap();
+    let pids: Vec<u64> = hits.iter().map(|h| h.post.id).collect();
+    assert_eq!(pids, [readable.id]);
+}
+
+fn team_acl() -> Acl {
+    Acl {
+        readers: vec!["bob".to_string()],
+        writers: vec!["carol".to_string()],
+        ..Acl::default()
+    }
+}
+
+#[actix_web::test]
+async fn http_enforces_the_acl() {
+    let (path, tokens, store, user_tokens) = setup("http").await;
+
+    let admin_token = tokens.create("admin", None).unwrap();
+
+    let addr = free_addr();
+    {
+        let addr = addr.clone();
+        actix_web::rt::spawn(async move {
+            timesman_server::http::HttpServer {
+                tokens: Some(tokens),
+            }
+            .run(&addr, store)
+            .await
+            .unwrap();
+        });
+    }
+    wait_for(&addr).await;
+
+    let server = format!("http://{addr}");
+    let [mut alice, mut bob, mut carol] = [0, 1, 2].map(|i| {
+        RemoteStore::new(server.clone()).with_token(user_tokens[i].clone())
+    });
+    let mut admin = RemoteStore::new(server.clone()).with_token(admin_token);
+
+    let private = alice.create_times("private".to_string()).await.unwrap();
+    alice
+        .create_post(private.id, "#secret plans".to_string())
+        .await
+        .unwrap();
+    let team = alice.create_times("team".to_string()).await.unwrap();
+    let acl = alice.set_acl(team.id, team_acl()).await.unwrap();
+    assert_eq!(acl.owner.as_deref(), Some("alice"));
+    assert_eq!(bob.get_acl(team.id).await.unwrap(), acl);
+
+    // bob only reads the team times and never sees the private one
+    let titles: Vec<String> = bob
+        .get_times()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|t| t.title)
+        .collect();
+    assert_eq!(titles, ["team"]);
+    assert!(matches!(
+        bob.get_posts(private.id).await,
+        Err(StoreError::NotFound(_))
+    ));
+    assert!(matches!(
+        bob.create_post(team.id, "hi".to_string()).await,
+        Err(StoreError::PermissionDenied(_))
+    ));
+    // the access is checked before the upload is received
+    let post = alice
+        .create_post(team.id, "plans".to_string())
+        .await
+        .unwrap();
+    assert!(matches!(
+        bob.attach(
+            team.id,
+            post.id,
+            "big".to_string(),
+            MIME.to_string(),
+            big()
+        )
+        .await,
+        Err(StoreError::PermissionDenied(_))
+    ));
+    check_search_limit(&mut alice, &mut bob, &post).await;
+    assert!(bob.list_tags().await.unwrap().is_empty());
+    assert!(bob
+        .search_posts("secret".to_string(), Default::default())
+        .await
+        .unwrap()
+        .is_empty());
+
+    // carol writes posts but may not change the times itself
+    carol.create_post(team.id, "hi".to_string()).await.unwrap();
+    let mut renamed = team.clone();
+    renamed.title = "mine".to_string();
+    assert!(matches!(
+        carol.update_times(renamed).await,
+        Err(StoreError::PermissionDenied(_))
+    ));
+    assert!(matches!(
+        carol.set_acl(team.id, Acl::default()).await,
+        Err(StoreError::PermissionDenied(_))
+    ));
+    assert!(matches!(
+        carol.purge_trash(None).await,
+        Err(StoreError::PermissionDenied(_))
+    ));
+
+    // a public times is readable by everyone, but still not writable
+    let public = Acl {
+        public: true,
+        ..Acl::default()
+    };
+    alice.set_acl(private.id, public).await.unwrap();
+    assert_eq!(bob.get_posts(private.id).await.unwrap().len(), 1);
+    assert!(matches!(
+        bob.create_post(private.id, "hi".to_string()).await,
+        Err(StoreError::PermissionDenied(_))
+    ));
+
+    // a times of no user is shared by all, but only an admin changes it
+    let lobby = admin.create_times("lobby".to_string()).await.unwrap();
+    assert_eq!(lobby.owner, None);
+    bob.create_post(lobby.id, "hello".to_string())
+        .await
+        .unwrap();
+    let mut renamed = lobby.clone();
+    renamed.title = "mine".to_string();
+    assert!(matches!(
+        bob.update_times(renamed.clone()).await,
+        Err(StoreError::PermissionDenied(_))
+    ));
+    assert!(matches!(
+        bob.delete_times(lobby.id).await,
+        Err(StoreError::PermissionDenied(_))
+    ));
+    assert!(matches!(
+        bob.set_acl(lobby.id, Acl::default()).await,
+        Err(StoreError::PermissionDenied(_))
+    ));
+    admin.update_times(renamed).await.unwrap();
+
+    let unknown = Acl {
+        readers: vec!["mallory".to_string()],
+        ..Acl::default()
+    };
+    assert!(matches!(
+        alice.set_acl(team.id, unknown).await,
+        Err(StoreError::NotFound(_))
+    ));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "grpc")]
+#[tokio::test]
+async fn grpc_enforces_the_acl() {
+    use std::time::Duration;
+    use timesman_bstore::grpc::GrpcStore;
+    use timesman_type::Change;
+
+    let (path, tokens, store, user_tokens) = setup("grpc").await;
+
+    let addr = free_addr();
+    {
+        let addr = addr.clone();
+        tokio::spawn(async move {
+            timesman_server::grpc::GrpcServer {
+                tokens: Some(tokens),
+                tls: None,
+            }
+            .run(&addr, store)
+            .await
+            .unwrap();
+        });
+    }
+    wait_for(&addr).await;
+
+    let server = format!("http://{addr}");
+    let mut clients = vec![];
+    for token in user_tokens {
+        clients.push(
+            GrpcStore::build_with_token(server.clone(), Some(token))
+                .await
+                .unwrap(),
+        );
+    }
+    let [mut alice, mut bob, mut carol] = clients.try_into().ok().unwrap();
+
+    let private = alice.create_times("private".to_string()).await.unwrap();
+    let team = alice.create_times("team".to_string()).await.unwrap();
+    alice.set_acl(team.id, team_acl()).await.unwrap();
+    let post = alice
+        .create_post(team.id, "plans".to_string())
+        .await
+        .unwrap();
+    check_search_limit(&mut alice, &mut bob, &post).await;
+
+    let mut changes = bob.subscribe().await.unwrap();
+
+    assert!(matches!(
+        bob.get_posts(private.id).await,
+        Err(StoreError::NotFound(_))
+    ));
+    assert!(matches!(
+        bob.create_post(team.id, "hi".to_string()).await,
+        Err(StoreError::PermissionDenied(_))
+    ));
+    assert!(matches!(
+        carol.delete_times(team.id).await,
+        Err(StoreError::PermissionDenied(_))
+    ));
+    assert!(matches!(
+        bob.attach(
+            team.id,
+            post.id,
+            "big".to_string(),
+            MIME.to_string(),
+            big()
+        )
+        .await,
+        Err(StoreError::PermissionDenied(_))
+    ));
+
+    // bob only hears of the changes to the times they may read
+    alice
+        .create_post(private.id, "hidden".to_string())
+        .await
+        .unwrap();
+    carol
+        .create_post(team.id, "shared".to_string())
+        .await
+        .unwrap();
+    let change = tokio::time::timeout(Duration::from_secs(1), changes.recv())
+        .await
+        .expect("no change in time")
+        .unwrap();
+    match change {
+        Change::PostCreated { tid, post } => {
+            assert_eq!(tid, team.id);
+            assert_eq!(post.author.as_deref(), Some("carol"));
+        }
+        _ => panic!("unexpected change"),
+    }
+
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,236 @@
+mod common;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use common::{free_addr, ram_store, wait_for};
+use reqwest::StatusCode;
+use serde_json::Value;
+use timesman_bstore::remote::RemoteStore;
+use timesman_bstore::{Store, StoreError};
+use timesman_server::auth::Tokens;
+use timesman_server::TimesManServer;
+
+fn tokens(name: &str) -> (PathBuf, Arc<Tokens>) {
+    let path = std::env::temp_dir().join(format!(
+        "timesman-tokens-{name}-{}.json",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    (path.clone(), Arc::new(Tokens::open(path)))
+}
+
+#[actix_web::test]
+async fn http_requires_a_token() {
+    let (path, tokens) = tokens("http");
+    let token = tokens.create("test", None).unwrap();
+
+    let addr = free_addr();
+    {
+        let addr = addr.clone();
+        let tokens = tokens.clone();
+        actix_web::rt::spawn(async move {
+            timesman_server::http::HttpServer {
+                tokens: Some(tokens),
+            }
+            .run(&addr, ram_store())
+            .await
+            .unwrap();
+        });
+    }
+    wait_for(&addr).await;
+
+    let api = format!("http://{addr}{}", timesman_server::http::API_PREFIX);
+    let client = reqwest::Client::new();
+
+    for req in [
+        client.get(format!("{api}/times")),
+        client.get(format!("{api}/times")).bearer_auth("tm_wrong"),
+    ] {
+        let resp = req.send().await.unwrap();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(resp.headers()["www-authenticate"], "Bearer");
+        let body: Value = resp.json().await.unwrap();
+        assert_eq!(body["error"]["code"], "unauthenticated");
+    }
+
+    let resp = client
+        .get(format!("{api}/times"))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = client
+        .get(format!("http://{addr}/openapi.json"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let mut store = RemoteStore::new(format!("http://{addr}"));
+    assert!(matches!(
+        store.get_times().await,
+        Err(StoreError::Unauthenticated(_))
+    ));
+    let mut store = store.with_token(token.clone());
+    store.create_times("authed".to_string()).await.unwrap();
+
+    tokens.revoke("test").unwrap();
+    assert!(matches!(
+        store.get_times().await,
+        Err(StoreError::Unauthenticated(_))
+    ));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "grpc")]
+#[tokio::test]
+async fn grpc_requires_a_token() {
+    use timesman_bstore::grpc::GrpcStore;
+
+    let (path, tokens) = tokens("grpc");
+    let token = tokens.create("test", None).unwrap();
+    assert!(tokens.create("test", None).is_err());
+
+    let addr = free_addr();
+    {
+        let addr = addr.clone();
+        tokio::spawn(async move {
+            timesman_server::grpc::GrpcServer {
+                tokens: Some(tokens),
+                tls: None,
+            }
+            .run(&addr, ram_store())
+            .await
+            .unwrap();
+        });
+    }
+    wait_for(&addr).await;
+
+    let server = format!("http://{addr}");
+    let mut store = GrpcStore::build(server.clone()).await.unwrap();
+    assert!(matches!(
+        store.get_times().await,
+        Err(StoreError::Unauthenticated(_))
+    ));
+
+    let mut store =
+        GrpcStore::build_with_token(server.clone(), Some("tm_wrong".into()))
+            .await
+            .unwrap();
+    assert!(matches!(
+        store.get_times().await,
+        Err(StoreError::Unauthenticated(_))
+    ));
+
+    let mut store = GrpcStore::build_with_token(server, Some(token))
+        .await
+        .unwrap();
+    store.create_times("authed".to_string()).await.unwrap();
+    assert_eq!(store.get_times().await.unwrap().len(), 1);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+/// A store with the users alice and bob, and their tokens.
+async fn users_store(tokens: &Tokens) -> (common::SharedStore, String, String) {
+    let store = ram_store();
+    for name in ["alice", "bob"] {
+        store
+            .lock()
+            .await
+            .create_user(name.to_string())
+            .await
+            .unwrap();
+    }
+
+    let alice = tokens.create("alice", Some("alice")).unwrap();
+    let bob = tokens.create("bob", Some("bob")).unwrap();
+    (store, alice, bob)
+}
+
+#[actix_web::test]
+async fn http_tokens_act_for_their_user() {
+    let (path, tokens) = tokens("http-users");
+    let (store, alice, bob) = users_store(&tokens).await;
+
+    let addr = free_addr();
+    {
+        let addr = addr.clone();
+        actix_web::rt::spawn(async move {
+            timesman_server::http::HttpServer {
+                tokens: Some(tokens),
+            }
+            .run(&addr, store)
+            .await
+            .unwrap();
+        });
+    }
+    wait_for(&addr).await;
+
+    let server = format!("http://{addr}");
+    let mut alice = RemoteStore::new(server.clone()).with_token(alice);
+    let mut bob = RemoteStore::new(server).with_token(bob);
+
+    let times = alice.create_times("alice's".to_string()).await.unwrap();
+    assert_eq!(times.owner.as_deref(), Some("alice"));
+    let post = alice.create_post(times.id, "hi".to_string()).await.unwrap();
+    assert_eq!(post.author.as_deref(), Some("alice"));
+
+    bob.create_times("bob's".to_string()).await.unwrap();
+
+    let titles = |times: Vec<timesman_type::Times>| -> Vec<String> {
+        times.into_iter().map(|t| t.title).collect()
+    };
+    assert_eq!(titles(alice.get_times().await.unwrap()), ["alice's"]);
+    assert_eq!(titles(bob.get_times().await.unwrap()), ["bob's"]);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "grpc")]
+#[tokio::test]
+async fn grpc_tokens_act_for_their_user() {
+    use timesman_bstore::grpc::GrpcStore;
+
+    let (path, tokens) = tokens("grpc-users");
+    let (store, alice, bob) = users_store(&tokens).await;
+
+    let addr = free_addr();
+    {
+        let addr = addr.clone();
+        tokio::spawn(async move {
+            timesman_server::grpc::GrpcServer {
+                tokens: Some(tokens),
+                tls: None,
+            }
+            .run(&addr, store)
+            .await
+            .unwrap();
+        });
+    }
+    wait_for(&addr).await;
+
+    let server = format!("http://{addr}");
+    let mut alice = GrpcStore::build_with_token(server.clone(), Some(alice))
+        .await
+        .unwrap();
+    let mut bob = GrpcStore::build_with_token(server, Some(bob))
+        .await
+        .unwrap();
+
+    let times = alice.create_times("alice's".to_string()).await.unwrap();
+    assert_eq!(times.owner.as_deref(), Some("alice"));
+
+    let times = bob.create_times("bob's".to_string()).await.unwrap();
+    let post = bob.create_post(times.id, "hi".to_string()).await.unwrap();
+    assert_eq!(post.author.as_deref(), Some("bob"));
+
+    assert_eq!(alice.get_times().await.unwrap().len(), 1);
+    assert_eq!(bob.get_times().await.unwrap().len(), 1);
+
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,32 @@
+//! Helpers shared by the integration tests.
+
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use timesman_bstore::notify::NotifyingStore;
+use timesman_bstore::ram::RamStore;
+use timesman_bstore::Store;
+
+pub type SharedStore = Arc<Mutex<Box<dyn Store + Send + Sync + 'static>>>;
+
+pub fn free_addr() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+pub async fn wait_for(addr: &str) {
+    for _ in 0..100 {
+        if TcpStream::connect(addr).is_ok() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("server on {addr} did not come up");
+}
+
+pub fn ram_store() -> SharedStore {
+    let store = NotifyingStore::new(Box::new(RamStore::new()));
+    Arc::new(Mutex::new(Box::new(store)))
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::{free_addr, ram_store, wait_for};
+use timesman_bstore::remote::RemoteStore;
+use timesman_bstore::testsuite;
+use timesman_server::TimesManServer;
+
+#[actix_web::test]
+async fn remote_store_over_http() {
+    let addr = free_addr();
+    {
+        let addr = addr.clone();
+        actix_web::rt::spawn(async move {
+            timesman_server::http::HttpServer::default()
+                .run(&addr, ram_store())
+                .await
+                .unwrap();
+        });
+    }
+    wait_for(&addr).await;
+
+    let mut store = RemoteStore::new(format!("http://{addr}"));
+    let report = testsuite::run(&mut store).await;
+    report.assert_ok();
+    // the REST API covers the whole store but the users, which are managed
+    // on the server, so the ACLs of their times cannot be checked either
+    let skipped: Vec<_> = report.skipped.iter().map(|(n, _)| *n).collect();
+    assert_eq!(skipped, ["users", "acl"]);
+}
+
+#[cfg(feature = "grpc")]
+#[tokio::test]
+async fn grpc_store_over_grpc() {
+    use timesman_bstore::grpc::GrpcStore;
+
+    let addr = free_addr();
+    {
+        let addr = addr.clone();
+        tokio::spawn(async move {
+            timesman_server::grpc::GrpcServer::default()
+                .run(&addr, ram_store())
+                .await
+                .unwrap();
+        });
+    }
+    wait_for(&addr).await;
+
+    let mut store = GrpcStore::build(format!("http://{addr}")).await.unwrap();
+    testsuite::run(&mut store).await.assert_ok();
+}
@@ -0,0 +1,132 @@
+#![cfg(feature = "grpc")]
+
+mod common;
+
+use common::{free_addr, ram_store, wait_for};
+use timesman_bstore::grpc::GrpcStore;
+use timesman_bstore::{Store, StoreError};
+use timesman_grpc::grpc;
+use timesman_grpc::grpc::times_man_client::TimesManClient;
+use timesman_server::TimesManServer;
+
+async fn serve() -> String {
+    let addr = free_addr();
+    {
+        let addr = addr.clone();
+        tokio::spawn(async move {
+            timesman_server::grpc::GrpcServer::default()
+                .run(&addr, ram_store())
+                .await
+                .unwrap();
+        });
+    }
+    wait_for(&addr).await;
+
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn store_errors_become_status_codes() {
+    let server = serve().await;
+    let mut client = TimesManClient::connect(server).await.unwrap();
+
+    let status = client
+        .delete_times(grpc::TimesId { id: 1 << 40 })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    let times = client
+        .create_times(grpc::TimesTitle {
+            title: "t".to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    let status = client
+        .get_posts_by_tag(grpc::TagName {
+            name: "not a tag".to_string(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    let status = client
+        .update_post(grpc::UpdatePostParam {
+            tid: times.id,
+            post: None,
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn malformed_messages_are_invalid_arguments() {
+    let server = serve().await;
+    let mut client = TimesManClient::connect(server).await.unwrap();
+
+    let times = client
+        .create_times(grpc::TimesTitle {
+            title: "t".to_string(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+
+    let status = client
+        .update_times(grpc::Times {
+            created_at: None,
+            ..times.clone()
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+    let status = client
+        .update_times(grpc::Times {
+            updated_at: Some(prost_types::Timestamp {
+                seconds: 0,
+                nanos: -1,
+            }),
+            ..times
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+}
+
+#[tokio::test]
+async fn grpc_store_keeps_times_and_posts_intact() {
+    let mut store = GrpcStore::build(serve().await).await.unwrap();
+
+    let times = store.create_times("intact".to_string()).await.unwrap();
+    let got = store.get_times().await.unwrap();
+    assert_eq!(got.len(), 1);
+    assert_eq!(got[0].title, "intact");
+    assert_eq!(got[0].created_at, times.created_at);
+
+    let post = store
+        .create_post(times.id, "first".to_string())
+        .await
+        .unwrap();
+    let mut edited = post.clone();
+    edited.post = "edited".to_string();
+    let edited = store.update_post(times.id, edited).await.unwrap();
+    assert_eq!(edited.created_at, post.created_at);
+    assert!(edited.updated_at.is_some());
+
+    let latest = store.get_latest_post(times.id).await.unwrap().unwrap();
+    assert_eq!(latest.post, "edited");
+    assert_eq!(latest.created_at, post.created_at);
+    assert_eq!(latest.updated_at, edited.updated_at);
+
+    store.delete_post(times.id, post.id).await.unwrap();
+    assert!(store.get_latest_post(times.id).await.unwrap().is_none());
+
+    store.delete_times(times.id).await.unwrap();
+    assert!(matches!(
+        store.get_posts(times.id).await,
+        Err(StoreError::NotFound(_))
+    ));
+}
@@ -0,0 +1,378 @@
+mod common;
+
+use common::{free_addr, ram_store, wait_for};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use timesman_server::TimesManServer;
+
+async fn serve() -> String {
+    let addr = free_addr();
+    {
+        let addr = addr.clone();
+        actix_web::rt::spawn(async move {
+            timesman_server::http::HttpServer::default()
+                .run(&addr, ram_store())
+                .await
+                .unwrap();
+        });
+    }
+    wait_for(&addr).await;
+
+    format!("http://{addr}{}", timesman_server::http::API_PREFIX)
+}
+
+async fn expect_error(resp: reqwest::Response, status: StatusCode, code: &str) {
+    assert_eq!(resp.status(), status);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["error"]["code"], code);
+    assert!(body["error"]["message"].is_string());
+}
+
+#[actix_web::test]
+async fn resources_use_status_codes() {
+    let api = serve().await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .post(format!("{api}/times"))
+        .json(&json!({"title": "t"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let times: Value = resp.json().await.unwrap();
+    let tid = times["id"].as_u64().unwrap();
+
+    let resp = client
+        .get(format!("{api}/times/{tid}/posts/latest"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let resp = client
+        .post(format!("{api}/times/{tid}/posts"))
+        .json(&json!({"post": "first"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let post: Value = resp.json().await.unwrap();
+    let pid = post["id"].as_u64().unwrap();
+
+    let resp = client
+        .patch(format!("{api}/times/{tid}/posts/{pid}"))
+        .json(&json!({"post": "edited"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let post: Value = resp.json().await.unwrap();
+    assert_eq!(post["post"], "edited");
+
+    let resp = client
+        .put(format!("{api}/times/{tid}"))
+        .json(&json!({"title": "renamed"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    let times: Value = resp.json().await.unwrap();
+    assert_eq!(times["title"], "renamed");
+
+    let resp = client
+        .delete(format!("{api}/times/{tid}/posts/{pid}"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let resp = client
+        .get(format!("{api}/times/{tid}/posts/{pid}"))
+        .send()
+        .await
+        .unwrap();
+    expect_error(resp, StatusCode::NOT_FOUND, "not_found").await;
+}
+
+#[actix_web::test]
+async fn errors_use_the_error_schema() {
+    let api = serve().await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .delete(format!("{api}/times/{}", 1u64 << 40))
+        .send()
+        .await
+        .unwrap();
+    expect_error(resp, StatusCode::NOT_FOUND, "not_found").await;
+
+    let resp = client
+        .post(format!("{api}/times"))
+        .header("content-type", "application/json")
+        .body("{not json")
+        .send()
+        .await
+        .unwrap();
+    expect_error(resp, StatusCode::BAD_REQUEST, "invalid_argument").await;
+
+    let resp = client
+        .get(format!("{api}/times/not-a-number/posts"))
+        .send()
+        .await
+        .unwrap();
+    expect_error(resp, StatusCode::BAD_REQUEST, "invalid_argument").await;
+
+    let resp = client.get(format!("{api}/nowhere")).send().await.unwrap();
+    expect_error(resp, StatusCode::NOT_FOUND, "not_found").await;
+
+    let resp = client.patch(format!("{api}/times")).send().await.unwrap();
+    expect_error(resp, StatusCode::METHOD_NOT_ALLOWED, "method_not_allowed")
+        .await;
+}
+
+/// Checks live responses against the schemas of `/openapi.json`.
+struct Spec {
+    doc: Value,
+}
+
+impl Spec {
+    async fn fetch(api: &str) -> Self {
+        let root = api.strip_suffix(timesman_server::http::API_PREFIX).unwrap();
+        let resp = reqwest::get(format!("{root}/openapi.json")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        Self {
+            doc: resp.json().await.unwrap(),
+        }
+    }
+
+    /// Panics unless the spec documents `status` for `method` on `path`
+    /// and `body` matches its schema.
+    fn check(
+        &self,
+        method: &str,
+        path: &str,
+        status: StatusCode,
+        body: &Value,
+    ) {
+        let responses = &self.doc["paths"][path][method]["responses"];
+        assert!(responses.is_object(), "{method} {path} is not documented");
+
+        let response = match &responses[status.as_str()] {
+            Value::Null => &responses["default"],
+            response => response,
+        };
+        assert!(
+            response.is_object(),
+            "{method} {path} does not document {status}"
+        );
+
+        let Some(schema) =
+            response["content"]["application/json"].get("schema")
+        else {
+            assert!(body.is_null(), "{method} {path} returned {body}");
+            return;
+        };
+
+        // the schema refers to the components of the whole document
+        let schema = json!({
+            "allOf": [schema],
+            "components": self.doc["components"],
+        });
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        let errors: Vec<String> =
+            validator.iter_errors(body).map(|e| e.to_string()).collect();
+        assert!(errors.is_empty(), "{method} {path}: {errors:?} in {body}");
+    }
+
+    /// Sends a request to `url` and checks the response against `path`.
+    async fn call(
+        &self,
+        req: reqwest::RequestBuilder,
+        method: &str,
+        path: &str,
+        expected: StatusCode,
+    ) -> Value {
+        let resp = req.send().await.unwrap();
+        let status = resp.status();
+        let text = resp.text().await.unwrap();
+        let body = if text.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_str(&text).unwrap()
+        };
+
+        assert_eq!(status, expected, "{method} {path}: {body}");
+        self.check(method, path, status, &body);
+
+        body
+    }
+}
+
+#[actix_web::test]
+async fn responses_match_the_openapi_document() {
+    let api = serve().await;
+    let spec = Spec::fetch(&api).await;
+    let client = reqwest::Client::new();
+
+    assert!(spec.doc["openapi"].as_str().unwrap().starts_with("3."));
+
+    let times = spec
+        .call(
+            client
+                .post(format!("{api}/times"))
+                .json(&json!({"title": "t"})),
+            "post",
+            "/times",
+            StatusCode::CREATED,
+        )
+        .await;
+    let tid = times["id"].as_u64().unwrap();
+
+    spec.call(
+        client.get(format!("{api}/times")),
+        "get",
+        "/times",
+        StatusCode::OK,
+    )
+    .await;
+    spec.call(
+        client
+            .patch(format!("{api}/times/{tid}"))
+            .json(&json!({"title": "renamed"})),
+        "patch",
+        "/times/{tid}",
+        StatusCode::OK,
+    )
+    .await;
+    spec.call(
+        client.get(format!("{api}/times/{tid}/posts/latest")),
+        "get",
+        "/times/{tid}/posts/latest",
+        StatusCode::NO_CONTENT,
+    )
+    .await;
+
+    let post = spec
+        .call(
+            client
+                .post(format!("{api}/times/{tid}/posts"))
+                .json(&json!({"post": "hello #greeting"})),
+            "post",
+            "/times/{tid}/posts",
+            StatusCode::CREATED,
+        )
+        .await;
+    let pid = post["id"].as_u64().unwrap();
+
+    spec.call(
+        client
+            .put(format!("{api}/times/{tid}/posts/{pid}"))
+            .json(&json!({"post": "bye #greeting"})),
+        "put",
+        "/times/{tid}/posts/{pid}",
+        StatusCode::OK,
+    )
+    .await;
+    spec.call(
+        client.get(format!("{api}/times/{tid}/posts?limit=1")),
+        "get",
+        "/times/{tid}/posts",
+        StatusCode::OK,
+    )
+    .await;
+    spec.call(
+        client.get(format!("{api}/times/{tid}/posts/latest")),
+        "get",
+        "/times/{tid}/posts/latest",
+        StatusCode::OK,
+    )
+    .await;
+    spec.call(
+        client.get(format!("{api}/times/{tid}/posts/{pid}/revisions")),
+        "get",
+        "/times/{tid}/posts/{pid}/revisions",
+        StatusCode::OK,
+    )
+    .await;
+    spec.call(
+        client.get(format!("{api}/search?q=bye")),
+        "get",
+        "/search",
+        StatusCode::OK,
+    )
+    .await;
+    spec.call(
+        client.get(format!("{api}/tags")),
+        "get",
+        "/tags",
+        StatusCode::OK,
+    )
+    .await;
+    spec.call(
+        client.get(format!("{api}/tags/greeting")),
+        "get",
+        "/tags/{tag}",
+        StatusCode::OK,
+    )
+    .await;
+    spec.call(
+        client.get(format!("{api}/times/{tid}/attachments")),
+        "get",
+        "/times/{tid}/attachments",
+        StatusCode::OK,
+    )
+    .await;
+
+    spec.call(
+        client.delete(format!("{api}/times/{tid}/posts/{pid}")),
+        "delete",
+        "/times/{tid}/posts/{pid}",
+        StatusCode::NO_CONTENT,
+    )
+    .await;
+    spec.call(
+        client.get(format!("{api}/trash")),
+        "get",
+        "/trash",
+        StatusCode::OK,
+    )
+    .await;
+    spec.call(
+        client.post(format!("{api}/trash/times/{tid}/posts/{pid}/restore")),
+        "post",
+        "/trash/times/{tid}/posts/{pid}/restore",
+        StatusCode::OK,
+    )
+    .await;
+    spec.call(
+        client.delete(format!("{api}/times/{tid}")),
+        "delete",
+        "/times/{tid}",
+        StatusCode::NO_CONTENT,
+    )
+    .await;
+    spec.call(
+        client.delete(format!("{api}/trash")),
+        "delete",
+        "/trash",
+        StatusCode::OK,
+    )
+    .await;
+
+    spec.call(
+        client.get(format!("{api}/times/{tid}/posts/{pid}")),
+        "get",
+        "/times/{tid}/posts/{pid}",
+        StatusCode::NOT_FOUND,
+    )
+    .await;
+    spec.call(
+        client.post(format!("{api}/times")).json(&json!({})),
+        "post",
+        "/times",
+        StatusCode::BAD_REQUEST,
+    )
+    .await;
+}
@@ -0,0 +1,79 @@
+#![cfg(feature = "grpc")]
+
+mod common;
+
+use common::{free_addr, ram_store, wait_for};
+use serde_json::{json, Value};
+use std::time::Duration;
+use timesman_grpc::grpc;
+use timesman_grpc::grpc::times_man_client::TimesManClient;
+use timesman_server::{Listener, Protocol};
+use tokio_util::sync::CancellationToken;
+
+#[actix_web::test]
+async fn listeners_share_one_store() {
+    let http = free_addr();
+    let grpc = free_addr();
+    let listeners = vec![
+        Listener {
+            protocol: Protocol::Http,
+            address: http.clone(),
+            tls: None,
+        },
+        Listener {
+            protocol: Protocol::Grpc,
+            address: grpc.clone(),
+            tls: None,
+        },
+    ];
+    actix_web::rt::spawn(async move {
+        timesman_server::serve(
+            &listeners,
+            ram_store(),
+            None,
+            CancellationToken::new(),
+            Duration::from_secs(1),
+        )
+        .await
+        .unwrap();
+    });
+    wait_for(&http).await;
+    wait_for(&grpc).await;
+
+    let api = format!("http://{http}{}", timesman_server::http::API_PREFIX);
+    let client = reqwest::Client::new();
+    let times: Value = client
+        .post(format!("{api}/times"))
+        .json(&json!({"title": "shared"}))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let tid = times["id"].as_u64().unwrap();
+
+    let mut grpc = TimesManClient::connect(format!("http://{grpc}"))
+        .await
+        .unwrap();
+    let timeses = grpc.get_times(()).await.unwrap().into_inner().timeses;
+    assert_eq!(timeses.len(), 1);
+    assert_eq!(timeses[0].title, "shared");
+
+    grpc.create_post(grpc::CreatePostPrams {
+        id: tid,
+        text: "from grpc".to_string(),
+    })
+    .await
+    .unwrap();
+
+    let posts: Value = client
+        .get(format!("{api}/times/{tid}/posts"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(posts["posts"][0]["post"], "from grpc");
+}
@@ -0,0 +1,130 @@
+#![cfg(feature = "grpc")]
+
+mod common;
+
+use std::net::TcpStream;
+use std::time::Duration;
+
+use common::{free_addr, ram_store, wait_for, SharedStore};
+use timesman_grpc::grpc;
+use timesman_grpc::grpc::times_man_client::TimesManClient;
+use timesman_server::{Listener, Protocol};
+use tokio_util::sync::CancellationToken;
+
+fn listeners(http: &str, grpc: &str) -> Vec<Listener> {
+    vec![
+        Listener {
+            protocol: Protocol::Http,
+            address: http.to_string(),
+            tls: None,
+        },
+        Listener {
+            protocol: Protocol::Grpc,
+            address: grpc.to_string(),
+            tls: None,
+        },
+    ]
+}
+
+fn spawn_serve(
+    listeners: Vec<Listener>,
+    store: SharedStore,
+    shutdown: CancellationToken,
+    drain_timeout: Duration,
+) -> actix_web::rt::task::JoinHandle<Result<(), String>> {
+    actix_web::rt::spawn(async move {
+        timesman_server::serve(&listeners, store, None, shutdown, drain_timeout)
+            .await
+    })
+}
+
+#[actix_web::test]
+async fn shutdown_ends_streams_and_stops_listening() {
+    let (http, grpc) = (free_addr(), free_addr());
+    let shutdown = CancellationToken::new();
+    let serving = spawn_serve(
+        listeners(&http, &grpc),
+        ram_store(),
+        shutdown.clone(),
+        Duration::from_secs(5),
+    );
+    wait_for(&http).await;
+    wait_for(&grpc).await;
+
+    // streams of changes are open until the shutdown
+    let api = format!("http://{http}{}", timesman_server::http::API_PREFIX);
+    let mut events = reqwest::get(format!("{api}/events")).await.unwrap();
+    let mut client = TimesManClient::connect(format!("http://{grpc}"))
+        .await
+        .unwrap();
+    let mut watch = client
+        .watch_times(grpc::WatchParam { resume_token: None })
+        .await
+        .unwrap()
+        .into_inner();
+    watch
+        .message()
+        .await
+        .unwrap()
+        .expect("no start of the watch");
+
+    shutdown.cancel();
+    let result = tokio::time::timeout(Duration::from_secs(3), serving)
+        .await
+        .expect("the listeners did not drain")
+        .unwrap();
+    assert_eq!(result, Ok(()));
+
+    assert!(events.chunk().await.map_or(true, |c| c.is_none()));
+    assert!(watch.message().await.map_or(true, |m| m.is_none()));
+    assert!(TcpStream::connect(&http).is_err());
+    assert!(TcpStream::connect(&grpc).is_err());
+}
+
+#[actix_web::test]
+async fn shutdown_gives_up_on_stuck_requests() {
+    let (http, grpc) = (free_addr(), free_addr());
+    let store = ram_store();
+    let shutdown = CancellationToken::new();
+    let serving = spawn_serve(
+        listeners(&http, &grpc),
+        store.clone(),
+        shutdown.clone(),
+        Duration::from_millis(200),
+    );
+    wait_for(&http).await;
+
+    // the request waits for the store until the drain times out
+    let guard = store.lock().await;
+    let api = format!("http://{http}{}", timesman_server::http::API_PREFIX);
+    actix_web::rt::spawn(reqwest::get(format!("{api}/times")));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    shutdown.cancel();
+    let result = tokio::time::timeout(Duration::from_secs(3), serving)
+        .await
+        .expect("the drain did not time out")
+        .unwrap();
+    assert!(result.unwrap_err().contains("in flight"));
+    drop(guard);
+}
+
+#[actix_web::test]
+async fn bind_failures_are_errors() {
+    let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let taken = taken.local_addr().unwrap().to_string();
+
+    for (http, grpc) in [(taken.clone(), free_addr()), (free_addr(), taken)] {
+        let serving = spawn_serve(
+            listeners(&http, &grpc),
+            ram_store(),
+            CancellationToken::new(),
+            Duration::from_secs(1),
+        );
+        let result = tokio::time::timeout(Duration::from_secs(3), serving)
+            .await
+            .expect("the failure was not reported")
+            .unwrap();
+        assert!(result.unwrap_err().contains("failed to bind"));
+    }
+}
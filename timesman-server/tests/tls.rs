@@ -0,0 +1,159 @@
+#![cfg(feature = "grpc")]
+
+mod common;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use common::{free_addr, ram_store, wait_for};
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, ExtendedKeyUsagePurpose,
+    IsCa, KeyPair,
+};
+use timesman_bstore::grpc::GrpcStoreBuilder;
+use timesman_bstore::{Store, StoreError};
+use timesman_grpc::ClientTls;
+use timesman_server::{Listener, Protocol, TlsConfig};
+use tokio_util::sync::CancellationToken;
+
+/// A CA with a certificate it signed for the server and one for a client,
+/// written as PEM files to a directory of their own.
+struct Pki {
+    dir: PathBuf,
+}
+
+impl Pki {
+    fn generate(name: &str) -> Self {
+        let dir = std::env::temp_dir()
+            .join(format!("timesman-tls-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let ca_key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(vec![]).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = params.self_signed(&ca_key).unwrap();
+        std::fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+
+        let sign = |file: &str, names: Vec<String>, usage| {
+            let key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(names).unwrap();
+            params.extended_key_usages = vec![usage];
+            let cert: Certificate =
+                params.signed_by(&key, &ca, &ca_key).unwrap();
+            std::fs::write(dir.join(format!("{file}.pem")), cert.pem())
+                .unwrap();
+            std::fs::write(
+                dir.join(format!("{file}.key")),
+                key.serialize_pem(),
+            )
+            .unwrap();
+        };
+        sign(
+            "server",
+            vec!["127.0.0.1".to_string()],
+            ExtendedKeyUsagePurpose::ServerAuth,
+        );
+        sign("client", vec![], ExtendedKeyUsagePurpose::ClientAuth);
+
+        Self { dir }
+    }
+
+    fn path(&self, file: &str) -> Option<PathBuf> {
+        Some(self.dir.join(file))
+    }
+
+    fn server_tls(&self, client_ca: bool) -> TlsConfig {
+        TlsConfig {
+            cert: self.dir.join("server.pem"),
+            key: self.dir.join("server.key"),
+            client_ca: client_ca.then(|| self.dir.join("ca.pem")),
+        }
+    }
+}
+
+impl Drop for Pki {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Serves gRPC over `tls` and returns the URL of the server.
+async fn serve(tls: TlsConfig) -> String {
+    let addr = free_addr();
+    let listeners = vec![Listener {
+        protocol: Protocol::Grpc,
+        address: addr.clone(),
+        tls: Some(tls),
+    }];
+    actix_web::rt::spawn(async move {
+        timesman_server::serve(
+            &listeners,
+            ram_store(),
+            None,
+            CancellationToken::new(),
+            Duration::from_secs(1),
+        )
+        .await
+        .unwrap();
+    });
+    wait_for(&addr).await;
+
+    format!("https://{addr}")
+}
+
+async fn connect(server: &str, tls: ClientTls) -> Result<(), StoreError> {
+    let mut store = GrpcStoreBuilder::new(server.to_string())
+        .tls(tls)
+        .build()
+        .await?;
+    store.create_times("secure".to_string()).await?;
+    store.check().await
+}
+
+#[actix_web::test]
+async fn grpc_serves_tls() {
+    let pki = Pki::generate("server");
+    let server = serve(pki.server_tls(false)).await;
+
+    let trusted = ClientTls {
+        ca: pki.path("ca.pem"),
+        ..ClientTls::default()
+    };
+    connect(&server, trusted).await.unwrap();
+
+    // the system roots do not know the CA of the test
+    assert!(connect(&server, ClientTls::default()).await.is_err());
+
+    let plaintext = GrpcStoreBuilder::new(server.replacen("https", "http", 1));
+    let result = async { plaintext.build().await?.check().await }.await;
+    assert!(result.is_err());
+}
+
+#[actix_web::test]
+async fn grpc_requires_client_certificates() {
+    let pki = Pki::generate("client");
+    let server = serve(pki.server_tls(true)).await;
+
+    let identified = ClientTls {
+        ca: pki.path("ca.pem"),
+        cert: pki.path("client.pem"),
+        key: pki.path("client.key"),
+    };
+    connect(&server, identified).await.unwrap();
+
+    let anonymous = ClientTls {
+        ca: pki.path("ca.pem"),
+        ..ClientTls::default()
+    };
+    assert!(connect(&server, anonymous).await.is_err());
+
+    let keyless = ClientTls {
+        ca: pki.path("ca.pem"),
+        cert: pki.path("client.pem"),
+        key: None,
+    };
+    assert!(matches!(
+        connect(&server, keyless).await,
+        Err(StoreError::InvalidArgument(_))
+    ));
+}
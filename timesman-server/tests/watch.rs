@@ -0,0 +1,142 @@
+#![cfg(feature = "grpc")]
+
+mod common;
+
+use std::time::Duration;
+
+use common::{free_addr, ram_store, wait_for, SharedStore};
+use timesman_bstore::grpc::GrpcStore;
+use timesman_bstore::Store;
+use timesman_grpc::grpc;
+use timesman_grpc::grpc::times_man_client::TimesManClient;
+use timesman_server::TimesManServer;
+use timesman_type::Change;
+
+/// How long a change may take to reach a watching client.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+async fn serve() -> (String, SharedStore) {
+    let addr = free_addr();
+    let store = ram_store();
+    {
+        let addr = addr.clone();
+        let store = store.clone();
+        tokio::spawn(async move {
+            timesman_server::grpc::GrpcServer::default()
+                .run(&addr, store)
+                .await
+                .unwrap();
+        });
+    }
+    wait_for(&addr).await;
+
+    (format!("http://{addr}"), store)
+}
+
+async fn next<T>(stream: &mut tonic::Streaming<T>) -> T {
+    tokio::time::timeout(TIMEOUT, stream.message())
+        .await
+        .expect("no event in time")
+        .unwrap()
+        .expect("the watch ended")
+}
+
+#[tokio::test]
+async fn grpc_store_receives_changes() {
+    let (server, shared) = serve().await;
+
+    let mut store = GrpcStore::build(server).await.unwrap();
+    let mut rx = store.subscribe().await.unwrap();
+
+    let (times, post) = {
+        let mut shared = shared.lock().await;
+        let times = shared.create_times("watched".to_string()).await.unwrap();
+        let post = shared
+            .create_post(times.id, "hello".to_string())
+            .await
+            .unwrap();
+        (times, post)
+    };
+
+    // times and posts are watched separately, so they may come in any order
+    let mut created = (false, false);
+    for _ in 0..2 {
+        let change = tokio::time::timeout(TIMEOUT, rx.recv()).await.unwrap();
+        match change.unwrap() {
+            Change::TimesCreated { times: t } => created.0 = t.id == times.id,
+            Change::PostCreated { tid, post: p } => {
+                created.1 = tid == times.id && p.id == post.id
+            }
+            _ => panic!("unexpected change"),
+        }
+    }
+    assert_eq!(created, (true, true));
+}
+
+#[tokio::test]
+async fn watch_posts_resumes_after_token() {
+    let (server, shared) = serve().await;
+    let mut client = TimesManClient::connect(server).await.unwrap();
+
+    let (tid, other) = {
+        let mut shared = shared.lock().await;
+        let tid = shared.create_times("one".to_string()).await.unwrap().id;
+        let other = shared.create_times("two".to_string()).await.unwrap().id;
+        (tid, other)
+    };
+
+    let param = grpc::WatchPostsParam {
+        tid: Some(tid),
+        resume_token: None,
+    };
+    let mut stream = client.watch_posts(param).await.unwrap().into_inner();
+    assert!(next(&mut stream).await.event.is_none());
+
+    let mut shared = shared.lock().await;
+    shared
+        .create_post(other, "elsewhere".to_string())
+        .await
+        .unwrap();
+    shared.create_post(tid, "a".to_string()).await.unwrap();
+
+    let a = next(&mut stream).await;
+    assert_eq!(a.tid, tid);
+    let token = a.token;
+    drop(stream);
+
+    shared.create_post(tid, "b".to_string()).await.unwrap();
+    shared
+        .create_post(other, "elsewhere".to_string())
+        .await
+        .unwrap();
+    shared.create_post(tid, "c".to_string()).await.unwrap();
+    drop(shared);
+
+    let param = grpc::WatchPostsParam {
+        tid: Some(tid),
+        resume_token: Some(token),
+    };
+    let mut stream = client.watch_posts(param).await.unwrap().into_inner();
+    assert_eq!(next(&mut stream).await.token, token);
+
+    let mut texts = vec![];
+    for _ in 0..2 {
+        match next(&mut stream).await.into_change().unwrap() {
+            Some(Change::PostCreated { post, .. }) => texts.push(post.post),
+            _ => panic!("expected a created post"),
+        }
+    }
+    assert_eq!(texts, vec!["b", "c"]);
+}
+
+#[tokio::test]
+async fn watch_rejects_unknown_token() {
+    let (server, _) = serve().await;
+    let mut client = TimesManClient::connect(server).await.unwrap();
+
+    let param = grpc::WatchParam {
+        resume_token: Some(1 << 40),
+    };
+    let status = client.watch_times(param).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::OutOfRange);
+}
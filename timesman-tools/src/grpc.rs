@@ -1,8 +1,12 @@
+use timesman_grpc::grpc;
 use timesman_grpc::grpc::times_man_client::TimesManClient;
-use timesman_type::{Post, Times};
+use timesman_grpc::{ClientTls, ConvertError, TokenInterceptor};
+use timesman_type::{Acl, Change, Post, Times, Trash};
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::{Channel, Endpoint};
 
 pub struct GrpcClient {
-    client: TimesManClient<tonic::transport::channel::Channel>,
+    client: TimesManClient<InterceptedService<Channel, TokenInterceptor>>,
     rt: tokio::runtime::Runtime,
 }
 
@@ -13,52 +17,258 @@ impl super::Client for GrpcClient {
             .block_on(async { self.client.get_times(()).await.unwrap() })
             .into_inner();
 
-        let r = tary
-            .timeses
-            .iter()
-            .map(|t| t.clone().into())
-            .collect::<Vec<Times>>();
-        Ok(r)
+        tary.timeses
+            .into_iter()
+            .map(|t| t.try_into().map_err(|e: ConvertError| e.to_string()))
+            .collect()
     }
 
     fn create_times(&mut self, title: String) -> Result<Times, String> {
-        Err("not yet implemented".to_string())
+        let times = self
+            .rt
+            .block_on(async {
+                self.client.create_times(grpc::TimesTitle { title }).await
+            })
+            .map_err(|e| e.message().to_string())?;
+
+        times
+            .into_inner()
+            .try_into()
+            .map_err(|e: ConvertError| e.to_string())
     }
 
     fn delete_times(&mut self, tid: u64) -> Result<(), String> {
-        Err("not yet implemented".to_string())
+        self.rt
+            .block_on(async {
+                self.client.delete_times(grpc::TimesId { id: tid }).await
+            })
+            .map_err(|e| e.message().to_string())?;
+
+        Ok(())
     }
 
     fn update_times(&mut self, times: Times) -> Result<Times, String> {
-        Err("not yet implemented".to_string())
+        let param: grpc::Times = times.into();
+        let times = self
+            .rt
+            .block_on(async { self.client.update_times(param).await })
+            .map_err(|e| e.message().to_string())?;
+
+        times
+            .into_inner()
+            .try_into()
+            .map_err(|e: ConvertError| e.to_string())
     }
 
     fn get_posts(&mut self, tid: u64) -> Result<Vec<Post>, String> {
-        Err("not yet implemented".to_string())
+        let param = grpc::GetPostsParam {
+            id: tid,
+            ..Default::default()
+        };
+        let posts = self
+            .rt
+            .block_on(async { self.client.get_posts(param).await })
+            .map_err(|e| e.message().to_string())?;
+
+        posts
+            .into_inner()
+            .posts
+            .into_iter()
+            .map(|p| p.try_into().map_err(|e: ConvertError| e.to_string()))
+            .collect()
     }
+
     fn create_post(&mut self, tid: u64, text: String) -> Result<Post, String> {
-        Err("not yet implemented".to_string())
+        let param = grpc::CreatePostPrams { id: tid, text };
+        let post = self
+            .rt
+            .block_on(async { self.client.create_post(param).await })
+            .map_err(|e| e.message().to_string())?;
+
+        post.into_inner()
+            .try_into()
+            .map_err(|e: ConvertError| e.to_string())
     }
 
     fn delete_post(&mut self, tid: u64, pid: u64) -> Result<(), String> {
-        Err("not yet implemented".to_string())
+        self.rt
+            .block_on(async {
+                self.client
+                    .delete_post(grpc::DeletePostParam { tid, pid })
+                    .await
+            })
+            .map_err(|e| e.message().to_string())?;
+
+        Ok(())
     }
 
     fn update_post(&mut self, tid: u64, post: Post) -> Result<Post, String> {
-        Err("not yet implemented".to_string())
+        let param = grpc::UpdatePostParam {
+            tid,
+            post: Some(post.into()),
+        };
+        let post = self
+            .rt
+            .block_on(async { self.client.update_post(param).await })
+            .map_err(|e| e.message().to_string())?;
+
+        post.into_inner()
+            .try_into()
+            .map_err(|e: ConvertError| e.to_string())
+    }
+
+    fn get_acl(&mut self, tid: u64) -> Result<Acl, String> {
+        let acl = self
+            .rt
+            .block_on(async {
+                self.client.get_acl(grpc::TimesId { id: tid }).await
+            })
+            .map_err(|e| e.message().to_string())?;
+
+        Ok(acl.into_inner().into())
+    }
+
+    fn set_acl(&mut self, tid: u64, acl: Acl) -> Result<Acl, String> {
+        let param = grpc::SetAclParam {
+            id: tid,
+            acl: Some(acl.into()),
+        };
+        let acl = self
+            .rt
+            .block_on(async { self.client.set_acl(param).await })
+            .map_err(|e| e.message().to_string())?;
+
+        Ok(acl.into_inner().into())
+    }
+
+    fn list_trash(&mut self) -> Result<Trash, String> {
+        let trash = self
+            .rt
+            .block_on(async { self.client.list_trash(()).await })
+            .map_err(|e| e.message().to_string())?;
+
+        trash
+            .into_inner()
+            .try_into()
+            .map_err(|e: ConvertError| e.to_string())
+    }
+
+    fn restore_times(&mut self, tid: u64) -> Result<Times, String> {
+        let times = self
+            .rt
+            .block_on(async {
+                self.client.restore_times(grpc::TimesId { id: tid }).await
+            })
+            .map_err(|e| e.message().to_string())?;
+
+        times
+            .into_inner()
+            .try_into()
+            .map_err(|e: ConvertError| e.to_string())
+    }
+
+    fn restore_post(&mut self, tid: u64, pid: u64) -> Result<Post, String> {
+        let post = self
+            .rt
+            .block_on(async {
+                self.client.restore_post(grpc::PostKey { tid, pid }).await
+            })
+            .map_err(|e| e.message().to_string())?;
+
+        post.into_inner()
+            .try_into()
+            .map_err(|e: ConvertError| e.to_string())
+    }
+
+    fn purge_times(&mut self, tid: u64) -> Result<(), String> {
+        self.rt
+            .block_on(async {
+                self.client.purge_times(grpc::TimesId { id: tid }).await
+            })
+            .map_err(|e| e.message().to_string())?;
+
+        Ok(())
+    }
+
+    fn purge_post(&mut self, tid: u64, pid: u64) -> Result<(), String> {
+        self.rt
+            .block_on(async {
+                self.client.purge_post(grpc::PostKey { tid, pid }).await
+            })
+            .map_err(|e| e.message().to_string())?;
+
+        Ok(())
+    }
+
+    fn purge_trash(
+        &mut self,
+        before: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<u64, String> {
+        let param = grpc::PurgeTrashParam {
+            before: before.map(timesman_grpc::timestamp),
+        };
+        let count = self
+            .rt
+            .block_on(async { self.client.purge_trash(param).await })
+            .map_err(|e| e.message().to_string())?;
+
+        Ok(count.into_inner().count)
+    }
+
+    fn watch_posts(
+        &mut self,
+        tid: u64,
+        f: &mut dyn FnMut(Change),
+    ) -> Result<(), String> {
+        let param = grpc::WatchPostsParam {
+            tid: Some(tid),
+            resume_token: None,
+        };
+
+        self.rt.block_on(async {
+            let mut stream = self
+                .client
+                .watch_posts(param)
+                .await
+                .map_err(|e| e.message().to_string())?
+                .into_inner();
+
+            while let Some(event) = stream
+                .message()
+                .await
+                .map_err(|e| e.message().to_string())?
+            {
+                if let Some(change) =
+                    event.into_change().map_err(|e| e.to_string())?
+                {
+                    f(change);
+                }
+            }
+
+            Ok(())
+        })
     }
 }
 
 impl GrpcClient {
-    pub fn new(server: &String) -> Self {
+    pub fn new(
+        server: &str,
+        token: Option<&str>,
+        tls: Option<&ClientTls>,
+    ) -> Self {
         let rt = tokio::runtime::Builder::new_current_thread()
             .enable_io()
             .build()
             .unwrap();
 
         let server: String = server.parse().unwrap();
-        let client = rt
-            .block_on(async { TimesManClient::connect(server).await.unwrap() });
+        let mut endpoint = Endpoint::new(server).unwrap();
+        if let Some(tls) = tls {
+            endpoint = endpoint.tls_config(tls.config().unwrap()).unwrap();
+        }
+        let channel = rt.block_on(async { endpoint.connect().await.unwrap() });
+        let interceptor = TokenInterceptor::new(token).unwrap();
+        let client = TimesManClient::with_interceptor(channel, interceptor);
         Self { client, rt }
     }
 }
@@ -1,19 +1,44 @@
 mod grpc;
 
+use std::path::PathBuf;
+
 use clap::{Parser, Subcommand};
+use serde::Deserialize;
+
+use timesman_grpc::ClientTls;
 
-use timesman_type::{Post, Times};
+use timesman_type::{Acl, Change, Post, Times, Trash};
 
 trait Client {
     fn get_times(&mut self) -> Result<Vec<Times>, String>;
     fn create_times(&mut self, title: String) -> Result<Times, String>;
     fn delete_times(&mut self, tid: u64) -> Result<(), String>;
     fn update_times(&mut self, times: Times) -> Result<Times, String>;
+    fn get_acl(&mut self, tid: u64) -> Result<Acl, String>;
+    fn set_acl(&mut self, tid: u64, acl: Acl) -> Result<Acl, String>;
 
     fn get_posts(&mut self, tid: u64) -> Result<Vec<Post>, String>;
     fn create_post(&mut self, tid: u64, text: String) -> Result<Post, String>;
     fn delete_post(&mut self, tid: u64, pid: u64) -> Result<(), String>;
     fn update_post(&mut self, tid: u64, post: Post) -> Result<Post, String>;
+
+    fn list_trash(&mut self) -> Result<Trash, String>;
+    fn restore_times(&mut self, tid: u64) -> Result<Times, String>;
+    fn restore_post(&mut self, tid: u64, pid: u64) -> Result<Post, String>;
+    fn purge_times(&mut self, tid: u64) -> Result<(), String>;
+    fn purge_post(&mut self, tid: u64, pid: u64) -> Result<(), String>;
+    fn purge_trash(
+        &mut self,
+        before: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<u64, String>;
+
+    /// Calls `f` with every change to the posts of `tid` until the server
+    /// ends the watch.
+    fn watch_posts(
+        &mut self,
+        tid: u64,
+        f: &mut dyn FnMut(Change),
+    ) -> Result<(), String>;
 }
 
 #[derive(Parser)]
@@ -23,6 +48,19 @@ struct Args {
     conn_type: String,
     #[arg(short, long)]
     server: Option<String>,
+    /// A TOML file with the `server`, the API `token` and the TLS `ca`,
+    /// `cert` and `key` to use
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// A PEM bundle of the CAs to trust for the server certificate
+    #[arg(long)]
+    ca: Option<PathBuf>,
+    /// A PEM client certificate, for servers requiring one
+    #[arg(long, requires = "key")]
+    cert: Option<PathBuf>,
+    /// The PEM private key of `--cert`
+    #[arg(long, requires = "cert")]
+    key: Option<PathBuf>,
     #[command(subcommand)]
     command: Command,
 }
@@ -30,13 +68,134 @@ struct Args {
 #[derive(Subcommand)]
 enum Command {
     GetTimesList,
-    CreateTimes,
-    DeleteTimes,
-    UpdateTimes,
-    GetPostList,
-    CreatePost,
-    DeletePost,
-    UpdatePost,
+    CreateTimes {
+        #[arg(long)]
+        title: String,
+    },
+    /// Move a times and its posts to the trash
+    DeleteTimes {
+        #[arg(long)]
+        tid: u64,
+    },
+    /// Rename a times
+    UpdateTimes {
+        #[arg(long)]
+        tid: u64,
+        #[arg(long)]
+        title: String,
+    },
+    /// Show who may see and change a times
+    GetAcl {
+        #[arg(long)]
+        tid: u64,
+    },
+    /// Replace who may see and change a times besides its owner
+    SetAcl {
+        #[arg(long)]
+        tid: u64,
+        /// A user who may only read the times. Repeat for more
+        #[arg(long = "reader")]
+        readers: Vec<String>,
+        /// A user who may also write posts. Repeat for more
+        #[arg(long = "writer")]
+        writers: Vec<String>,
+        /// Let every user read the times
+        #[arg(long)]
+        public: bool,
+    },
+    GetPostList {
+        #[arg(long)]
+        tid: u64,
+    },
+    CreatePost {
+        #[arg(long)]
+        tid: u64,
+        #[arg(long)]
+        text: String,
+    },
+    /// Move a post to the trash
+    DeletePost {
+        #[arg(long)]
+        tid: u64,
+        #[arg(long)]
+        pid: u64,
+    },
+    /// Replace the text of a post
+    UpdatePost {
+        #[arg(long)]
+        tid: u64,
+        #[arg(long)]
+        pid: u64,
+        #[arg(long)]
+        text: String,
+    },
+    /// List the deleted times and posts
+    ListTrash,
+    /// Bring a deleted times back with its posts
+    RestoreTimes {
+        #[arg(long)]
+        tid: u64,
+    },
+    /// Bring a deleted post back
+    RestorePost {
+        #[arg(long)]
+        tid: u64,
+        #[arg(long)]
+        pid: u64,
+    },
+    /// Permanently remove a deleted times
+    PurgeTimes {
+        #[arg(long)]
+        tid: u64,
+    },
+    /// Permanently remove a deleted post
+    PurgePost {
+        #[arg(long)]
+        tid: u64,
+        #[arg(long)]
+        pid: u64,
+    },
+    /// Permanently remove everything in the trash, or only old entries
+    PurgeTrash {
+        #[arg(long)]
+        older_than_days: Option<u64>,
+    },
+    /// Follow the posts of a times as they are made
+    WatchPosts {
+        #[arg(long)]
+        tid: u64,
+    },
+}
+
+/// The settings of `--config`. The command line takes precedence.
+#[derive(Deserialize, Default)]
+struct Config {
+    server: Option<String>,
+    token: Option<String>,
+    ca: Option<PathBuf>,
+    cert: Option<PathBuf>,
+    key: Option<PathBuf>,
+}
+
+impl Config {
+    fn load(path: &PathBuf) -> Result<Self, String> {
+        let buf = std::fs::read_to_string(path)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        toml::from_str(&buf).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// The TLS files to connect with, if any is given.
+    fn tls(&self) -> Option<ClientTls> {
+        if self.ca.is_none() && self.cert.is_none() && self.key.is_none() {
+            return None;
+        }
+
+        Some(ClientTls {
+            ca: self.ca.clone(),
+            cert: self.cert.clone(),
+            key: self.key.clone(),
+        })
+    }
 }
 
 fn list_times(times: Vec<Times>) {
@@ -45,38 +204,122 @@ fn list_times(times: Vec<Times>) {
     }
 }
 
+fn print_acl(acl: Acl) {
+    println!("owner: {}", acl.owner.as_deref().unwrap_or("(none)"));
+    println!("readers: {}", acl.readers.join(", "));
+    println!("writers: {}", acl.writers.join(", "));
+    println!("public: {}", acl.public);
+}
+
+fn list_trash(trash: Trash) {
+    for t in trash.times {
+        println!("times {} (deleted at {})", t.times, t.deleted_at);
+    }
+    for p in trash.posts {
+        println!(
+            "post {} {} {} (deleted at {})",
+            p.tid, p.post.id, p.post.post, p.deleted_at
+        );
+    }
+}
+
 fn run_command(mut c: Box<dyn Client>, cmd: &Command) -> Result<(), String> {
     match cmd {
         Command::GetTimesList => {
             list_times(c.get_times()?);
         }
-        Command::CreateTimes => {
-            unimplemented!();
-            // c.create_times()?;
+        Command::CreateTimes { title } => {
+            println!("{}", c.create_times(title.clone())?);
         }
-        Command::DeleteTimes => {
-            unimplemented!();
-            // c.delete_times()?;
+        Command::DeleteTimes { tid } => {
+            c.delete_times(*tid)?;
         }
-        Command::UpdateTimes => {
-            unimplemented!();
-            // c.update_times()?;
+        Command::UpdateTimes { tid, title } => {
+            let mut times = c
+                .get_times()?
+                .into_iter()
+                .find(|t| t.id == *tid)
+                .ok_or(format!("times {tid} is not found"))?;
+            times.title = title.clone();
+            println!("{}", c.update_times(times)?);
         }
-        Command::GetPostList => {
-            unimplemented!();
-            // c.get_posts()?;
+        Command::GetAcl { tid } => {
+            print_acl(c.get_acl(*tid)?);
         }
-        Command::CreatePost => {
-            unimplemented!();
-            // c.create_post()?;
+        Command::SetAcl {
+            tid,
+            readers,
+            writers,
+            public,
+        } => {
+            let acl = Acl {
+                owner: None,
+                readers: readers.clone(),
+                writers: writers.clone(),
+                public: *public,
+            };
+            print_acl(c.set_acl(*tid, acl)?);
         }
-        Command::DeletePost => {
-            unimplemented!();
-            // c.delete_post()?;
+        Command::GetPostList { tid } => {
+            for post in c.get_posts(*tid)? {
+                println!("{} {} {}", post.created_at, post.id, post.post);
+            }
         }
-        Command::UpdatePost => {
-            unimplemented!();
-            // c.update_post()?;
+        Command::CreatePost { tid, text } => {
+            let post = c.create_post(*tid, text.clone())?;
+            println!("{} {}", post.id, post.post);
+        }
+        Command::DeletePost { tid, pid } => {
+            c.delete_post(*tid, *pid)?;
+        }
+        Command::UpdatePost { tid, pid, text } => {
+            let mut post = c
+                .get_posts(*tid)?
+                .into_iter()
+                .find(|p| p.id == *pid)
+                .ok_or(format!("post {pid} is not found"))?;
+            post.post = text.clone();
+            let post = c.update_post(*tid, post)?;
+            println!("{} {}", post.id, post.post);
+        }
+        Command::ListTrash => {
+            list_trash(c.list_trash()?);
+        }
+        Command::RestoreTimes { tid } => {
+            println!("{}", c.restore_times(*tid)?);
+        }
+        Command::RestorePost { tid, pid } => {
+            let post = c.restore_post(*tid, *pid)?;
+            println!("{} {}", post.id, post.post);
+        }
+        Command::PurgeTimes { tid } => {
+            c.purge_times(*tid)?;
+        }
+        Command::PurgePost { tid, pid } => {
+            c.purge_post(*tid, *pid)?;
+        }
+        Command::PurgeTrash { older_than_days } => {
+            let before = older_than_days.map(|days| {
+                chrono::Utc::now() - chrono::TimeDelta::days(days as i64)
+            });
+            println!("purged {}", c.purge_trash(before)?);
+        }
+        Command::WatchPosts { tid } => {
+            c.watch_posts(*tid, &mut |change| match change {
+                Change::PostCreated { post, .. } => {
+                    println!("{} {} {}", post.created_at, post.id, post.post);
+                }
+                Change::PostUpdated { post, .. } => {
+                    println!(
+                        "{} {} (edited) {}",
+                        post.created_at, post.id, post.post
+                    );
+                }
+                Change::PostDeleted { pid, .. } => {
+                    println!("{pid} (deleted)");
+                }
+                _ => {}
+            })?;
         }
     }
 
@@ -86,14 +329,34 @@ fn run_command(mut c: Box<dyn Client>, cmd: &Command) -> Result<(), String> {
 fn main() {
     let args = Args::parse();
 
-    let server = if let Some(server) = args.server {
+    let mut config = match &args.config {
+        Some(path) => match Config::load(path) {
+            Ok(config) => config,
+            Err(e) => {
+                println!("{e}");
+                return;
+            }
+        },
+        None => Config::default(),
+    };
+    config.ca = args.ca.or(config.ca);
+    if args.cert.is_some() {
+        config.cert = args.cert;
+        config.key = args.key;
+    }
+
+    let server = if let Some(server) = args.server.or(config.server.take()) {
         server
     } else {
         "http://127.0.0.1:8080/".to_string()
     };
 
     let client = match &*args.conn_type {
-        "grpc" => Box::new(grpc::GrpcClient::new(&server)),
+        "grpc" => Box::new(grpc::GrpcClient::new(
+            &server,
+            config.token.as_deref(),
+            config.tls().as_ref(),
+        )),
         _ => {
             unimplemented!();
         }
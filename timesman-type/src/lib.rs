@@ -1,11 +1,17 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Times {
     pub id: u64,
     pub title: String,
-    pub created_at: chrono::NaiveDateTime,
-    pub updated_at: Option<chrono::NaiveDateTime>,
+    /// The name of the user the times belongs to. Times made before user
+    /// accounts have none and are shared by everyone.
+    #[serde(default)]
+    pub owner: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 impl std::fmt::Display for Times {
@@ -23,9 +29,252 @@ impl std::fmt::Display for Times {
 }
 
 #[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Post {
     pub id: u64,
     pub post: String,
-    pub created_at: chrono::NaiveDateTime,
-    pub updated_at: Option<chrono::NaiveDateTime>,
+    /// The name of the user who wrote the post, if it was written by one.
+    #[serde(default)]
+    pub author: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl Post {
+    /// The hashtags written in the post. See [`parse_tags`].
+    pub fn tags(&self) -> Vec<String> {
+        parse_tags(&self.post)
+    }
+}
+
+/// Characters which may follow the `#` of a hashtag.
+pub fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Extracts the hashtags from a text, e.g. `meeting` from `#Meeting`.
+///
+/// A hashtag starts a word. Tags are lowercased, returned without the
+/// leading `#`, and each appears once in order of first appearance.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+
+    for word in text.split_whitespace() {
+        let Some(rest) = word.strip_prefix('#') else {
+            continue;
+        };
+
+        let tag: String = rest
+            .chars()
+            .take_while(|c| is_tag_char(*c))
+            .collect::<String>()
+            .to_lowercase();
+
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    tags
+}
+
+/// A user account of a store.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct User {
+    pub id: u64,
+    /// Unique in the store, and how times and posts refer to the user.
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Who may see and change a times.
+///
+/// The owner may do anything, writers may add and edit posts, and readers
+/// may only look. A public times can be read by every user.
+///
+/// A times without an owner predates user accounts or was created by a
+/// request acting for no user, such as an administrator's. Every user may
+/// read and post to it, but only requests acting for no user may change
+/// the times itself.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Acl {
+    /// The owner of the times, which cannot be changed.
+    #[serde(default)]
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub readers: Vec<String>,
+    #[serde(default)]
+    pub writers: Vec<String>,
+    #[serde(default)]
+    pub public: bool,
+}
+
+impl Acl {
+    /// Whether `user` may change the times itself, e.g. rename or delete
+    /// it, and its ACL.
+    pub fn can_manage(&self, user: &str) -> bool {
+        self.owner.as_deref() == Some(user)
+    }
+
+    /// Whether `user` may add, edit and delete posts.
+    pub fn can_write(&self, user: &str) -> bool {
+        self.owner.is_none()
+            || self.can_manage(user)
+            || self.writers.iter().any(|w| w == user)
+    }
+
+    /// Whether `user` may see the times and its posts.
+    pub fn can_read(&self, user: &str) -> bool {
+        self.public
+            || self.can_write(user)
+            || self.readers.iter().any(|r| r == user)
+    }
+}
+
+/// An earlier version of a post, recorded when the post was edited.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Revision {
+    /// Numbers the versions of a post, starting at 1 for the original.
+    pub rev: u64,
+    pub post: String,
+    /// When this version was written.
+    pub created_at: DateTime<Utc>,
+}
+
+/// A file attached to a post. The content is stored apart from the
+/// record and addressed by its hash.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Attachment {
+    pub id: u64,
+    /// The post the file is attached to.
+    pub pid: u64,
+    pub name: String,
+    pub mime: String,
+    pub size: u64,
+    /// SHA-256 of the content, hex encoded.
+    pub hash: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Attachment {
+    pub fn is_image(&self) -> bool {
+        self.mime.starts_with("image/")
+    }
+}
+
+/// A hashtag and the number of posts using it.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Tag {
+    pub name: String,
+    pub count: u64,
+}
+
+/// A post together with the id of the times it belongs to.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TimesPost {
+    pub tid: u64,
+    pub post: Post,
+}
+
+/// A times in the trash, together with its posts.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TrashedTimes {
+    pub times: Times,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// A post in the trash whose times is not in the trash.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TrashedPost {
+    pub tid: u64,
+    pub post: Post,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// Everything which can be restored, most recently deleted first.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Trash {
+    pub times: Vec<TrashedTimes>,
+    pub posts: Vec<TrashedPost>,
+}
+
+/// A change made to a store, as published to its subscribers.
+///
+/// Restoring from the trash is reported as a creation, since the times or
+/// post becomes visible again.
+#[derive(Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    TimesCreated { times: Times },
+    TimesUpdated { times: Times },
+    TimesDeleted { tid: u64 },
+    PostCreated { tid: u64, post: Post },
+    PostUpdated { tid: u64, post: Post },
+    PostDeleted { tid: u64, pid: u64 },
+}
+
+impl Change {
+    /// The times the change happened in.
+    pub fn tid(&self) -> u64 {
+        match self {
+            Change::TimesCreated { times } | Change::TimesUpdated { times } => {
+                times.id
+            }
+            Change::TimesDeleted { tid }
+            | Change::PostCreated { tid, .. }
+            | Change::PostUpdated { tid, .. }
+            | Change::PostDeleted { tid, .. } => *tid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tags() {
+        assert_eq!(
+            parse_tags("#Meeting about #bug-42, #bug_1 and #meeting"),
+            vec!["meeting", "bug-42", "bug_1"]
+        );
+        assert_eq!(parse_tags("#日本語 です"), vec!["日本語"]);
+    }
+
+    #[test]
+    fn ignores_non_tags() {
+        assert!(parse_tags("# heading ## a#b #!x").is_empty());
+    }
+
+    #[test]
+    fn grants_access_by_role() {
+        let acl = Acl {
+            owner: Some("alice".to_string()),
+            readers: vec!["bob".to_string()],
+            writers: vec!["carol".to_string()],
+            public: false,
+        };
+
+        assert!(acl.can_manage("alice"));
+        assert!(!acl.can_manage("carol"));
+        assert!(acl.can_write("carol") && !acl.can_write("bob"));
+        assert!(acl.can_read("bob") && !acl.can_read("dave"));
+        assert!(Acl {
+            public: true,
+            ..acl
+        }
+        .can_read("dave"));
+        assert!(Acl::default().can_write("dave"));
+        assert!(!Acl::default().can_manage("dave"));
+    }
 }